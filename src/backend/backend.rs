@@ -12,6 +12,8 @@
 //!
 //! On the multi backend multiple backends could be running at the same time.
 
+use std::{error::Error, fmt};
+
 use libc;
 use wlroots_sys::{self, wlr_backend, wlr_backend_is_wl, wlr_backend_is_x11,
                   wlr_backend_is_drm, wlr_backend_is_headless, wlr_backend_is_multi,
@@ -27,6 +29,66 @@ pub type UnsafeRenderSetupFunction = unsafe extern "C" fn(egl: *mut wlroots_sys:
                                                           visual_id: i32)
                                                           -> *mut wlroots_sys::wlr_renderer;
 
+/// The kinds of backends a `Selection` can choose from.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Kind {
+    Drm,
+    Wayland,
+    X11,
+    Headless,
+    /// The input devices of the DRM backend. It has no outputs, so a
+    /// `Selection` never chooses it on its own.
+    Libinput
+}
+
+/// Why a backend could not be created.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum CreationError {
+    /// wlroots could not create a backend of this kind, e.g. because there's
+    /// no Wayland or X11 server to connect to.
+    Backend(Kind),
+    /// wlroots could not pick a backend from the environment.
+    AutoCreate,
+    /// A session could not be opened, so the DRM and libinput backends can't
+    /// be used.
+    Session,
+    /// The GPU could not be opened, or there was none to be found.
+    Gpu(Option<backend::Gpu>)
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Kind::Drm => "DRM",
+            Kind::Wayland => "Wayland",
+            Kind::X11 => "X11",
+            Kind::Headless => "headless",
+            Kind::Libinput => "libinput"
+        };
+        f.write_str(name)
+    }
+}
+
+impl fmt::Display for CreationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CreationError::Backend(kind) => write!(f, "Could not construct {} backend", kind),
+            CreationError::Gpu(Some(ref gpu)) => write!(f, "Could not open GPU {}", gpu),
+            _ => f.write_str(self.description())
+        }
+    }
+}
+
+impl Error for CreationError {
+    fn description(&self) -> &str {
+        match *self {
+            CreationError::Backend(_) => "Could not construct backend",
+            CreationError::AutoCreate => "Could not auto construct backend",
+            CreationError::Session => "Could not open a session",
+            CreationError::Gpu(_) => "Could not find a GPU to open"
+        }
+    }
+}
 
 #[derive(Debug, Hash, Eq, PartialEq)]
 pub enum Backend {
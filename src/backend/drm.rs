@@ -1,11 +1,139 @@
-use std::ptr;
+//! The DRM backend, and hotplug events for the GPUs it runs on.
+//!
+//! Outputs that are plugged in or out are reported by `output::Manager` and
+//! `output::Handler::destroyed`. Implement [`Handler`](./trait.Handler.html)
+//! and pass it to `compositor::Builder::drm_handler` to also learn when GPUs
+//! come and go, e.g. when docking an eGPU, and when the kernel reports a
+//! change on a connector.
 
-use libc::c_int;
-use wlroots_sys::{wlr_backend, wl_display, wlr_drm_backend_create, wlr_output_is_drm};
+use std::{cell::RefCell, ffi::CStr, fs, ptr, path::PathBuf};
 
-use {output::Output,
-     backend::{UnsafeRenderSetupFunction, Session},
-     utils::Handleable};
+use libc::{self, c_char, c_int};
+use wayland_sys::server::{signal::wl_signal_add, wl_event_loop, wl_event_source,
+                          WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_backend, wlr_backend_destroy, wl_display, wlr_drm_backend_create,
+                  wlr_output_is_drm, wlr_session_close_file, udev, udev_monitor};
+
+use {compositor,
+     output::Output,
+     backend::{UnsafeRenderSetupFunction, CreationError, Gpu, GpuInfo, Kind, Multi, Session,
+               session::{on_seat, udev_device, udev_device_get_property_value,
+                         udev_device_unref}},
+     utils::{Handleable, safe_as_cstring}};
+
+/// The mask for `wl_event_loop_add_fd` to be told when the fd is readable.
+const WL_EVENT_READABLE: u32 = 0x01;
+
+#[link(name = "udev")]
+extern "C" {
+    fn udev_monitor_new_from_netlink(udev: *mut udev, name: *const c_char) -> *mut udev_monitor;
+    fn udev_monitor_filter_add_match_subsystem_devtype(monitor: *mut udev_monitor,
+                                                       subsystem: *const c_char,
+                                                       devtype: *const c_char)
+                                                       -> c_int;
+    fn udev_monitor_enable_receiving(monitor: *mut udev_monitor) -> c_int;
+    fn udev_monitor_get_fd(monitor: *mut udev_monitor) -> c_int;
+    fn udev_monitor_receive_device(monitor: *mut udev_monitor) -> *mut udev_device;
+    fn udev_monitor_unref(monitor: *mut udev_monitor) -> *mut udev_monitor;
+    fn udev_device_get_action(device: *mut udev_device) -> *const c_char;
+    fn udev_device_get_sysname(device: *mut udev_device) -> *const c_char;
+    fn udev_device_get_devnode(device: *mut udev_device) -> *const c_char;
+}
+
+/// Handler for GPUs and their connectors changing while the compositor runs.
+///
+/// These are only reported on backends with a session, i.e. DRM.
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when a GPU is added, e.g. an eGPU was plugged in or its driver
+    /// was loaded.
+    ///
+    /// To use it, create a `Drm` backend for it and add it to the multi
+    /// backend of the compositor with `Multi::add`.
+    fn gpu_added(&mut self, compositor_handle: compositor::Handle, gpu: &GpuInfo) {}
+
+    /// Called when a GPU is removed.
+    ///
+    /// wlroots keeps the DRM backend of the GPU and its outputs around. To
+    /// get rid of them, remove the `Drm` backend whose `Drm::gpu` is this GPU
+    /// with `Multi::remove_and_destroy`.
+    fn gpu_removed(&mut self, compositor_handle: compositor::Handle, gpu: &GpuInfo) {}
+
+    /// Called when the kernel reports a change on the connectors of a GPU,
+    /// e.g. a monitor was plugged in, even if no output was created or
+    /// destroyed because of it.
+    ///
+    /// `connector` is the id of the connector that changed, if the kernel
+    /// says which one it was.
+    fn connector_changed(&mut self,
+                         compositor_handle: compositor::Handle,
+                         gpu: &GpuInfo,
+                         connector: Option<u32>) {
+    }
+}
+
+impl Handler for () {}
+
+/// Listens to udev for the GPUs of a session and reports them to a `Handler`.
+pub(crate) struct Monitor {
+    state: *mut MonitorState
+}
+
+struct MonitorState {
+    monitor: *mut udev_monitor,
+    source: *mut wl_event_source,
+    handler: Box<Handler>,
+    /// The seat of the session, GPUs of other seats are ignored.
+    seat: String,
+    /// The known GPUs, so they can be reported after they're gone from sysfs.
+    gpus: Vec<GpuInfo>
+}
+
+/// The properties of a udev event of the `drm` subsystem.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct UdevEvent {
+    action: Option<String>,
+    sysname: String,
+    devnode: Option<String>,
+    /// The `HOTPLUG` property, set when connectors changed.
+    hotplug: Option<String>,
+    /// The `ID_SEAT` property.
+    seat: Option<String>,
+    /// The `CONNECTOR` property, the connector that changed.
+    connector: Option<u32>
+}
+
+/// What a udev event means for the GPUs of a seat.
+#[derive(Debug, Clone, PartialEq)]
+enum GpuEvent {
+    Added(PathBuf),
+    Removed(PathBuf),
+    ConnectorChanged(PathBuf, Option<u32>)
+}
+
+wayland_listener!(pub(crate) DrmListener, *mut wlr_backend, [
+    destroy_listener => destroy_notify: |this: &mut DrmListener, _data: *mut libc::c_void,|
+    unsafe {
+        let backend = this.data;
+        let _gpu = GPUS.with(|gpus| {
+            let mut gpus = gpus.borrow_mut();
+            let index = gpus.iter().position(|&(ptr, _, _)| ptr == backend)?;
+            let (_, gpu, mut listener) = gpus.remove(index);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut (*listener.destroy_listener()).link as *mut _ as _);
+            // NOTE The listener is returned so it outlives this borrow of the list.
+            Some((gpu, listener))
+        });
+    };
+]);
+
+thread_local! {
+    /// The GPU of each DRM backend created with `Drm::new`, until the backend
+    /// is destroyed.
+    static GPUS: RefCell<Vec<(*mut wlr_backend, GpuInfo, Box<DrmListener>)>> =
+        RefCell::new(Vec::new());
+}
 
 /// When the compositor is ran on a TTY and has full control of the system resources.
 ///
@@ -35,7 +163,7 @@ impl Drm {
                       gpu_fd: c_int,
                       parent: Option<Drm>,
                       render_setup_func: Option<UnsafeRenderSetupFunction>)
-                      -> Self {
+                      -> Result<Self, CreationError> {
         let parent_ptr = parent.map(|backend| backend.as_ptr()).unwrap_or_else(ptr::null_mut);
         let backend = wlr_drm_backend_create(display,
                                              session.as_ptr(),
@@ -43,9 +171,98 @@ impl Drm {
                                              parent_ptr,
                                              render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct X11 backend");
+            return Err(CreationError::Backend(Kind::Drm))
         }
-        Drm { backend }
+        let gpu = fs::read_link(format!("/proc/self/fd/{}", gpu_fd)).ok()
+            .and_then(GpuInfo::from_path);
+        if let Some(gpu) = gpu {
+            let mut listener = DrmListener::new(backend);
+            wl_signal_add(&mut (*backend).events.destroy as *mut _ as _,
+                          listener.destroy_listener() as _);
+            GPUS.with(|gpus| gpus.borrow_mut().push((backend, gpu, listener)));
+        }
+        Ok(Drm { backend })
+    }
+
+    /// Creates a DRM backend for each of the GPUs, in a multi backend.
+    ///
+    /// The first GPU is the primary one, which renders for the others. Use
+    /// the order of `Session::find_gpus` to get the boot GPU as the primary.
+    ///
+    /// Secondary GPUs that can't be used are skipped. The multi backend has
+    /// no input devices, add a `Libinput` backend to it for those.
+    pub unsafe fn new_multi_gpu(display: *mut wl_display,
+                                mut session: Session,
+                                gpus: &[GpuInfo],
+                                render_setup_func: Option<UnsafeRenderSetupFunction>)
+                                -> Result<Multi, CreationError> {
+        let (primary_gpu, secondary_gpus) = match gpus.split_first() {
+            Some(gpus) => gpus,
+            None => return Err(CreationError::Gpu(None))
+        };
+        let session_ptr = session.as_ptr();
+        let primary_fd = session.open_file(&primary_gpu.path);
+        if primary_fd < 0 {
+            return Err(CreationError::Gpu(Some(Gpu::Path(primary_gpu.path.clone()))))
+        }
+        let primary = Drm::new(display,
+                               Session::from_ptr(session_ptr),
+                               primary_fd,
+                               None,
+                               render_setup_func);
+        let primary = match primary {
+            Ok(primary) => primary,
+            Err(err) => {
+                wlr_session_close_file(session_ptr, primary_fd);
+                return Err(err)
+            }
+        };
+        // NOTE Destroying the DRM backend closes its GPU.
+        let multi = match Multi::new(display) {
+            Some(multi) => multi,
+            None => {
+                wlr_backend_destroy(primary.backend);
+                return Err(CreationError::Backend(Kind::Drm))
+            }
+        };
+        if !multi.add_backend(primary.backend) {
+            wlr_backend_destroy(primary.backend);
+            wlr_backend_destroy(multi.backend);
+            return Err(CreationError::Backend(Kind::Drm))
+        }
+        for gpu in secondary_gpus {
+            let gpu_fd = session.open_file(&gpu.path);
+            if gpu_fd < 0 {
+                wlr_log!(WLR_ERROR, "Could not open GPU {}", gpu.path.display());
+                continue
+            }
+            let parent = Drm { backend: primary.backend };
+            match Drm::new(display,
+                           Session::from_ptr(session_ptr),
+                           gpu_fd,
+                           Some(parent),
+                           render_setup_func) {
+                Ok(secondary) => {
+                    if !multi.add_backend(secondary.backend) {
+                        wlr_log!(WLR_ERROR, "Could not add the backend of GPU {}",
+                                 gpu.path.display());
+                        wlr_backend_destroy(secondary.backend);
+                    }
+                }
+                Err(err) => {
+                    wlr_log!(WLR_ERROR, "{} for GPU {}", err, gpu.path.display());
+                    wlr_session_close_file(session_ptr, gpu_fd);
+                }
+            }
+        }
+        Ok(multi)
+    }
+
+    /// Get the GPU this backend renders with.
+    ///
+    /// Returns `None` if sysfs knows nothing about it.
+    pub fn gpu(&self) -> Option<GpuInfo> {
+        drm_gpu(self.backend)
     }
 
     pub fn output_is_drm(&self, output: &Output) -> bool {
@@ -58,3 +275,198 @@ impl Drm {
         self.backend
     }
 }
+
+impl Monitor {
+    /// Start listening for the GPUs of the session.
+    ///
+    /// Returns `None` if udev can't be listened to.
+    pub(crate) unsafe fn new(event_loop: *mut wl_event_loop,
+                             session: &Session,
+                             handler: Box<Handler>)
+                             -> Option<Self> {
+        // NOTE wlroots reads the udev monitor of the session itself, so a new
+        // one is made instead of stealing events from it.
+        let name = safe_as_cstring("udev");
+        let monitor = udev_monitor_new_from_netlink(session.udev(), name.as_ptr());
+        if monitor.is_null() {
+            wlr_log!(WLR_ERROR, "Could not create a udev monitor for GPU hotplug");
+            return None
+        }
+        let subsystem = safe_as_cstring("drm");
+        udev_monitor_filter_add_match_subsystem_devtype(monitor,
+                                                        subsystem.as_ptr(),
+                                                        ptr::null());
+        udev_monitor_enable_receiving(monitor);
+        let state = Box::into_raw(Box::new(MonitorState { monitor,
+                                                          source: ptr::null_mut(),
+                                                          handler,
+                                                          seat: session.seat_name(),
+                                                          gpus: session.find_gpus() }));
+        let source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                   wl_event_loop_add_fd,
+                                   event_loop,
+                                   udev_monitor_get_fd(monitor),
+                                   WL_EVENT_READABLE,
+                                   udev_notify,
+                                   state as *mut _);
+        if source.is_null() {
+            udev_monitor_unref(monitor);
+            Box::from_raw(state);
+            return None
+        }
+        (*state).source = source;
+        Some(Monitor { state })
+    }
+}
+
+impl Drop for Monitor {
+    fn drop(&mut self) {
+        unsafe {
+            let state = Box::from_raw(self.state);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, state.source);
+            udev_monitor_unref(state.monitor);
+        }
+    }
+}
+
+unsafe extern "C" fn udev_notify(_fd: c_int, _mask: u32, data: *mut libc::c_void) -> c_int {
+    let state = &mut *(data as *mut MonitorState);
+    let device = udev_monitor_receive_device(state.monitor);
+    if device.is_null() {
+        return 0
+    }
+    let string = |ptr: *const c_char| if ptr.is_null() {
+        None
+    } else {
+        Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+    };
+    let hotplug = safe_as_cstring("HOTPLUG");
+    let seat = safe_as_cstring("ID_SEAT");
+    let connector = safe_as_cstring("CONNECTOR");
+    let connector = string(udev_device_get_property_value(device, connector.as_ptr()))
+        .and_then(|connector| connector.parse().ok());
+    let event = UdevEvent { action: string(udev_device_get_action(device)),
+                            sysname: string(udev_device_get_sysname(device)).unwrap_or_default(),
+                            devnode: string(udev_device_get_devnode(device)),
+                            hotplug: string(udev_device_get_property_value(device,
+                                                                           hotplug.as_ptr())),
+                            seat: string(udev_device_get_property_value(device, seat.as_ptr())),
+                            connector };
+    udev_device_unref(device);
+    let event = match gpu_event(&event, &state.seat) {
+        Some(event) => event,
+        None => return 0
+    };
+    let compositor = match compositor::handle() {
+        Some(handle) => handle,
+        None => return 0
+    };
+    match event {
+        GpuEvent::Added(path) => {
+            let gpu = match GpuInfo::from_path(&path) {
+                Some(gpu) => gpu,
+                None => return 0
+            };
+            state.gpus.retain(|known| known.path != gpu.path);
+            state.gpus.push(gpu.clone());
+            state.handler.gpu_added(compositor, &gpu)
+        }
+        GpuEvent::Removed(path) => {
+            let index = state.gpus.iter().position(|known| known.path == path);
+            let gpu = match index {
+                Some(index) => state.gpus.remove(index),
+                None => GpuInfo { path, boot_vga: false, driver: None, pci_id: None }
+            };
+            state.handler.gpu_removed(compositor, &gpu)
+        }
+        GpuEvent::ConnectorChanged(path, connector) => {
+            let gpu = state.gpus.iter().find(|known| known.path == path).cloned()
+                .or_else(|| GpuInfo::from_path(&path));
+            if let Some(gpu) = gpu {
+                state.handler.connector_changed(compositor, &gpu, connector)
+            }
+        }
+    }
+    0
+}
+
+/// Work out what the udev event means for the GPUs of the seat.
+///
+/// Returns `None` for events about other seats, about devices that aren't
+/// GPUs, and for changes that don't concern the connectors.
+fn gpu_event(event: &UdevEvent, seat: &str) -> Option<GpuEvent> {
+    // Only the primary nodes are GPUs, not the render nodes or the connectors.
+    let sysname = &event.sysname;
+    let is_card = sysname.starts_with("card") && sysname.len() > 4 &&
+        sysname[4..].chars().all(|c| c.is_ascii_digit());
+    if !is_card || !on_seat(event.seat.as_ref().map(|seat| seat.as_str()), seat) {
+        return None
+    }
+    let path = PathBuf::from(event.devnode.as_ref()?);
+    match event.action.as_ref()?.as_str() {
+        "add" => Some(GpuEvent::Added(path)),
+        "remove" => Some(GpuEvent::Removed(path)),
+        "change" if event.hotplug.as_ref().map(|hotplug| hotplug.as_str()) == Some("1") => {
+            Some(GpuEvent::ConnectorChanged(path, event.connector))
+        }
+        _ => None
+    }
+}
+
+/// Get the GPU of a DRM backend created with `Drm::new`.
+pub(crate) fn drm_gpu(backend: *mut wlr_backend) -> Option<GpuInfo> {
+    GPUS.with(|gpus| {
+        gpus.borrow().iter()
+            .find(|&&(ptr, _, _)| ptr == backend)
+            .map(|&(_, ref gpu, _)| gpu.clone())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(action: &str, sysname: &str) -> UdevEvent {
+        UdevEvent { action: Some(action.into()),
+                    sysname: sysname.into(),
+                    devnode: Some(format!("/dev/dri/{}", sysname)),
+                    ..UdevEvent::default() }
+    }
+
+    #[test]
+    fn cards_are_added_and_removed() {
+        assert_eq!(gpu_event(&card("add", "card1"), "seat0"),
+                   Some(GpuEvent::Added("/dev/dri/card1".into())));
+        assert_eq!(gpu_event(&card("remove", "card12"), "seat0"),
+                   Some(GpuEvent::Removed("/dev/dri/card12".into())));
+    }
+
+    #[test]
+    fn other_drm_devices_are_skipped() {
+        assert_eq!(gpu_event(&card("add", "renderD128"), "seat0"), None);
+        assert_eq!(gpu_event(&card("add", "card1-DP-1"), "seat0"), None);
+        assert_eq!(gpu_event(&card("add", "card"), "seat0"), None);
+        let mut no_node = card("add", "card1");
+        no_node.devnode = None;
+        assert_eq!(gpu_event(&no_node, "seat0"), None);
+    }
+
+    #[test]
+    fn cards_of_other_seats_are_skipped() {
+        let mut other_seat = card("add", "card1");
+        other_seat.seat = Some("seat1".into());
+        assert_eq!(gpu_event(&other_seat, "seat0"), None);
+        assert_eq!(gpu_event(&other_seat, "seat1"),
+                   Some(GpuEvent::Added("/dev/dri/card1".into())));
+    }
+
+    #[test]
+    fn only_hotplug_changes_are_connector_changes() {
+        let mut change = card("change", "card0");
+        assert_eq!(gpu_event(&change, "seat0"), None);
+        change.hotplug = Some("1".into());
+        change.connector = Some(42);
+        assert_eq!(gpu_event(&change, "seat0"),
+                   Some(GpuEvent::ConnectorChanged("/dev/dri/card0".into(), Some(42))));
+    }
+}
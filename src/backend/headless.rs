@@ -1,13 +1,30 @@
-use libc;
+use libc::{self, c_float};
+use wlroots_sys::wlr_input_device_type::*;
 use wlroots_sys::{wlr_backend, wlr_headless_backend_create, wlr_headless_add_output,
-                  wlr_headless_add_input_device, wlr_input_device_is_headless,
-                  wlr_output_is_headless, wlr_input_device_type, wl_display};
+                  wlr_headless_add_input_device, wlr_input_device_destroy,
+                  wlr_input_device_is_headless,
+                  wlr_output, wlr_output_destroy, wlr_output_is_headless,
+                  wlr_output_set_custom_mode, wlr_output_set_scale, wlr_input_device,
+                  wlr_input_device_type, wl_display};
 
-use {backend::UnsafeRenderSetupFunction,
+use {backend::{UnsafeRenderSetupFunction, CreationError, Kind, FakeKeyboard, FakePointer, FakeTabletTool, FakeTouch},
+     compositor,
      input,
      output::{self, Output},
      utils::Handleable};
 
+/// The configuration of a virtual output made with `Headless::add_output_with`.
+///
+/// Anything that's not set is left at the default of the headless backend,
+/// which is a refresh rate of 60Hz and a scale of 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessOutputConfig {
+    width: libc::c_uint,
+    height: libc::c_uint,
+    refresh: Option<i32>,
+    scale: Option<c_float>
+}
+
 /// In this backend the only resource the compositor uses is the Wayland file descriptor.
 /// It doesn't try to grab actual keyboard/pointers and it doesn't render anything.
 ///
@@ -23,12 +40,12 @@ impl Headless {
     /// A headless backend has no outputs or inputs by default.
     pub unsafe fn new(display: *mut wl_display,
                       render_setup_func: Option<UnsafeRenderSetupFunction>)
-                      -> Self {
+                      -> Result<Self, CreationError> {
         let backend = wlr_headless_backend_create(display, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct Headless backend");
+            return Err(CreationError::Backend(Kind::Headless))
         }
-        Headless { backend }
+        Ok(Headless { backend })
     }
 
 
@@ -47,10 +64,47 @@ impl Headless {
         }
     }
 
+    /// Create a new headless output with the given configuration, e.g. a
+    /// virtual output for a remote session.
+    ///
+    /// The output is announced with the defaults of the headless backend,
+    /// the configuration is applied right after that. The changes are
+    /// reported like any other, e.g. to `output::Handler::on_mode_change`.
+    pub fn add_output_with(&self, config: HeadlessOutputConfig) -> Option<output::Handle> {
+        unsafe {
+            let output_ptr = wlr_headless_add_output(self.backend, config.width, config.height);
+            if output_ptr.is_null() {
+                return None
+            }
+            config.apply(output_ptr);
+            Some(output::Handle::from_ptr(output_ptr))
+        }
+    }
+
+    /// Destroys a headless output, as if the monitor was unplugged.
+    ///
+    /// Returns `false` if the output is not from this backend, was already
+    /// destroyed or is being used.
+    pub fn remove_output(&self, output: output::Handle) -> bool {
+        let output_ptr = match output.run(|output| unsafe { output.as_ptr() }) {
+            Ok(output_ptr) => output_ptr,
+            Err(_) => return false
+        };
+        unsafe {
+            if !wlr_output_is_headless(output_ptr) || (*output_ptr).backend != self.backend {
+                return false
+            }
+            wlr_output_destroy(output_ptr);
+        }
+        true
+    }
+
     /// Creates a new input device.
     ///
     /// The caller is responsible for manually raising any event signals on the
-    /// new input device if it wants to simulate input events.
+    /// new input device if it wants to simulate input events. Use
+    /// `add_keyboard`, `add_pointer`, `add_touch` or `add_tablet_tool` to get a
+    /// device that can emit events safely instead.
     pub fn add_input_device(&self, input_type: wlr_input_device_type) -> Option<input::Handle> {
         unsafe {
             let device = wlr_headless_add_input_device(self.backend, input_type);
@@ -62,6 +116,66 @@ impl Headless {
         }
     }
 
+    /// Creates a new fake keyboard that can simulate key presses.
+    ///
+    /// Returns `None` without creating a device if the compositor isn't
+    /// running yet, as the fake devices need to be set up by the input manager
+    /// before they can be used.
+    /// If the input manager doesn't set up the device it's destroyed again
+    /// and `None` is returned as well.
+    pub fn add_keyboard(&self) -> Option<FakeKeyboard> {
+        unsafe {
+            self.add_raw_input_device(WLR_INPUT_DEVICE_KEYBOARD)
+                .and_then(|d| or_destroy(d, FakeKeyboard::new(d)))
+        }
+    }
+
+    /// Creates a new fake pointer that can simulate motion, buttons and scrolling.
+    ///
+    /// Like `add_keyboard`, this requires the backend to be running.
+    pub fn add_pointer(&self) -> Option<FakePointer> {
+        unsafe {
+            self.add_raw_input_device(WLR_INPUT_DEVICE_POINTER)
+                .and_then(|d| or_destroy(d, FakePointer::new(d)))
+        }
+    }
+
+    /// Creates a new fake touch device that can simulate touch points.
+    ///
+    /// Like `add_keyboard`, this requires the backend to be running.
+    pub fn add_touch(&self) -> Option<FakeTouch> {
+        unsafe {
+            self.add_raw_input_device(WLR_INPUT_DEVICE_TOUCH)
+                .and_then(|d| or_destroy(d, FakeTouch::new(d)))
+        }
+    }
+
+    /// Creates a new fake tablet tool that can simulate a pen on a tablet.
+    ///
+    /// Like `add_keyboard`, this requires the backend to be running.
+    pub fn add_tablet_tool(&self) -> Option<FakeTabletTool> {
+        unsafe {
+            self.add_raw_input_device(WLR_INPUT_DEVICE_TABLET_TOOL)
+                .and_then(|d| or_destroy(d, FakeTabletTool::new(d)))
+        }
+    }
+
+    unsafe fn add_raw_input_device(&self,
+                                   input_type: wlr_input_device_type)
+                                   -> Option<*mut wlr_input_device> {
+        // NOTE The backend is only started while the compositor runs. A device
+        // made before that is never announced, so it would be left over.
+        if compositor::handle().is_none() {
+            return None
+        }
+        let device = wlr_headless_add_input_device(self.backend, input_type);
+        if device.is_null() {
+            None
+        } else {
+            Some(device)
+        }
+    }
+
     pub fn is_headless_input_device(&self, input_device: &input::Device) -> bool {
         unsafe {
             wlr_input_device_is_headless(input_device.as_ptr())
@@ -78,3 +192,50 @@ impl Headless {
         self.backend
     }
 }
+
+/// Destroys the device if it couldn't be wrapped, e.g. because the input
+/// manager didn't set it up, so it isn't left over on the backend.
+unsafe fn or_destroy<T>(device: *mut wlr_input_device, fake: Option<T>) -> Option<T> {
+    if fake.is_none() {
+        wlr_input_device_destroy(device);
+    }
+    fake
+}
+
+impl HeadlessOutputConfig {
+    /// Make the configuration for an output of the given size, in pixels.
+    pub fn new(width: libc::c_uint, height: libc::c_uint) -> Self {
+        HeadlessOutputConfig { width,
+                               height,
+                               refresh: None,
+                               scale: None }
+    }
+
+    /// Set the refresh rate in mHz.
+    ///
+    /// The headless backend emits a frame event every `1_000_000 / refresh`
+    /// milliseconds, so this also sets how often `output::Handler::on_frame`
+    /// is called.
+    pub fn refresh(mut self, refresh: i32) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    /// Set the scale of the output.
+    pub fn scale(mut self, scale: c_float) -> Self {
+        self.scale = Some(scale);
+        self
+    }
+
+    unsafe fn apply(&self, output: *mut wlr_output) {
+        if let Some(refresh) = self.refresh {
+            let (width, height) = (self.width as i32, self.height as i32);
+            if !wlr_output_set_custom_mode(output, width, height, refresh) {
+                wlr_log!(WLR_ERROR, "Could not set refresh rate of headless output");
+            }
+        }
+        if let Some(scale) = self.scale {
+            wlr_output_set_scale(output, scale);
+        }
+    }
+}
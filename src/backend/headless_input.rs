@@ -0,0 +1,379 @@
+//! Fake input devices for the headless backend.
+//!
+//! These emit the same events a real device would, so the input handlers of
+//! the compositor (e.g. `keyboard::Handler` and `cursor::Handler`) can be
+//! driven from tests.
+//!
+//! The devices are created with e.g. `Headless::add_keyboard` and are
+//! destroyed along with the headless backend. Like other input devices they
+//! are referred to by handle, so using one after it's gone returns an error.
+
+use libc;
+use wayland_sys::server::signal::wl_signal_emit;
+use wlroots_sys::{wlr_input_device, wl_signal, wlr_axis_orientation, wlr_axis_source,
+                  wlr_button_state, wlr_key_state, wlr_keyboard_notify_key,
+                  wlr_event_keyboard_key, wlr_event_pointer_motion,
+                  wlr_event_pointer_motion_absolute, wlr_event_pointer_button,
+                  wlr_event_pointer_axis, wlr_event_touch_down, wlr_event_touch_up,
+                  wlr_event_touch_motion, wlr_event_touch_cancel, wlr_event_tablet_tool_axis,
+                  wlr_event_tablet_tool_proximity, wlr_event_tablet_tool_tip,
+                  wlr_event_tablet_tool_button, wlr_tablet_tool_proximity_state,
+                  wlr_tablet_tool_tip_state};
+use wlroots_sys::wlr_tablet_tool_axes::*;
+
+use {input::{self, keyboard, pointer, touch, tablet_tool},
+     utils::{self, current_time, HandleResult, Handleable, ToMs}};
+
+/// A fake keyboard on the headless backend.
+///
+/// Keys are processed like real key presses: the XKB state of the keyboard
+/// is updated and the key and modifiers events are emitted. This requires the
+/// keyboard to have a keymap, which the input manager sets up when the
+/// device is added.
+#[derive(Debug)]
+pub struct FakeKeyboard {
+    handle: keyboard::Handle
+}
+
+/// A fake pointer on the headless backend.
+#[derive(Debug)]
+pub struct FakePointer {
+    handle: pointer::Handle
+}
+
+/// A fake touch device on the headless backend.
+#[derive(Debug)]
+pub struct FakeTouch {
+    handle: touch::Handle
+}
+
+/// A fake tablet tool (e.g. a pen) on the headless backend.
+#[derive(Debug)]
+pub struct FakeTabletTool {
+    handle: tablet_tool::Handle
+}
+
+/// Checks that the device is still alive, and gets the pointers to the input
+/// device and the specific device it is.
+///
+/// The device is not kept borrowed, so the handlers the events are emitted to
+/// can use it.
+fn alive<T, W>(handle: &utils::Handle<*mut wlr_input_device, T, W>)
+               -> HandleResult<(*mut wlr_input_device, *mut T)>
+    where W: Handleable<*mut wlr_input_device, T>
+{
+    handle.run(|_| ())?;
+    unsafe { Ok((handle.data, handle.as_ptr())) }
+}
+
+/// Emits the event on the signal, as the backend would for a real device.
+unsafe fn emit<T>(signal: *mut wl_signal, event: &mut T) {
+    wl_signal_emit(signal as *mut _, event as *mut T as *mut libc::c_void)
+}
+
+impl FakeKeyboard {
+    /// Wraps the device, if the input manager has set it up.
+    pub(crate) unsafe fn new(device: *mut wlr_input_device) -> Option<Self> {
+        let keyboard = (*device).__bindgen_anon_1.keyboard;
+        if (*keyboard).data.is_null() {
+            return None
+        }
+        Some(FakeKeyboard { handle: keyboard::Handle::from_ptr(keyboard) })
+    }
+
+    /// Get a handle to the input device backing this fake keyboard.
+    pub fn input_handle(&self) -> input::Handle {
+        input::Handle::Keyboard(self.handle.clone())
+    }
+
+    /// Press the key with the given (evdev) keycode.
+    pub fn press_key(&mut self, keycode: u32) -> HandleResult<()> {
+        self.key(keycode, wlr_key_state::WLR_KEY_PRESSED)
+    }
+
+    /// Release the key with the given (evdev) keycode.
+    pub fn release_key(&mut self, keycode: u32) -> HandleResult<()> {
+        self.key(keycode, wlr_key_state::WLR_KEY_RELEASED)
+    }
+
+    /// Press and then release the key with the given (evdev) keycode.
+    pub fn tap_key(&mut self, keycode: u32) -> HandleResult<()> {
+        self.press_key(keycode)?;
+        self.release_key(keycode)
+    }
+
+    /// Change the state of the key with the given (evdev) keycode.
+    pub fn key(&mut self, keycode: u32, state: wlr_key_state) -> HandleResult<()> {
+        let (_, keyboard) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_keyboard_key { time_msec: current_time().to_ms(),
+                                                     keycode,
+                                                     update_state: true,
+                                                     state };
+            wlr_keyboard_notify_key(keyboard, &mut event);
+        }
+        Ok(())
+    }
+}
+
+impl FakePointer {
+    /// Wraps the device, if the input manager has set it up.
+    pub(crate) unsafe fn new(device: *mut wlr_input_device) -> Option<Self> {
+        let pointer = (*device).__bindgen_anon_1.pointer;
+        if (*pointer).data.is_null() {
+            return None
+        }
+        Some(FakePointer { handle: pointer::Handle::from_ptr(pointer) })
+    }
+
+    /// Get a handle to the input device backing this fake pointer.
+    pub fn input_handle(&self) -> input::Handle {
+        input::Handle::Pointer(self.handle.clone())
+    }
+
+    /// Move the pointer by the given delta.
+    ///
+    /// With the `relative_pointer` feature the delta is reported as both the
+    /// accelerated and unaccelerated motion.
+    pub fn move_relative(&mut self, delta_x: f64, delta_y: f64) -> HandleResult<()> {
+        let (device, pointer) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_pointer_motion { device,
+                                                       time_msec: current_time().to_ms(),
+                                                       delta_x,
+                                                       delta_y,
+                                                       #[cfg(feature = "relative_pointer")]
+                                                       unaccel_dx: delta_x,
+                                                       #[cfg(feature = "relative_pointer")]
+                                                       unaccel_dy: delta_y,
+                                                       ..Default::default() };
+            emit(&mut (*pointer).events.motion, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Move the pointer to an absolute position.
+    ///
+    /// The position is normalized, from (0.0, 0.0) in the top left to (1.0, 1.0)
+    /// in the bottom right of the area the device is mapped to.
+    pub fn move_absolute(&mut self, x: f64, y: f64) -> HandleResult<()> {
+        let (device, pointer) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_pointer_motion_absolute { device,
+                                                                time_msec: current_time().to_ms(),
+                                                                x,
+                                                                y,
+                                                                ..Default::default() };
+            emit(&mut (*pointer).events.motion_absolute, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Change the state of a button, e.g. `BTN_LEFT`.
+    pub fn button(&mut self, button: u32, state: wlr_button_state) -> HandleResult<()> {
+        let (device, pointer) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_pointer_button { device,
+                                                       time_msec: current_time().to_ms(),
+                                                       button,
+                                                       state,
+                                                       ..Default::default() };
+            emit(&mut (*pointer).events.button, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Press and then release a button, e.g. `BTN_LEFT`.
+    pub fn click(&mut self, button: u32) -> HandleResult<()> {
+        self.button(button, wlr_button_state::WLR_BUTTON_PRESSED)?;
+        self.button(button, wlr_button_state::WLR_BUTTON_RELEASED)
+    }
+
+    /// Scroll along the axis, as a scroll wheel would.
+    pub fn axis(&mut self, orientation: wlr_axis_orientation, delta: f64) -> HandleResult<()> {
+        let (device, pointer) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_pointer_axis { device,
+                                                     time_msec: current_time().to_ms(),
+                                                     source: wlr_axis_source::WLR_AXIS_SOURCE_WHEEL,
+                                                     orientation,
+                                                     delta,
+                                                     ..Default::default() };
+            emit(&mut (*pointer).events.axis, &mut event);
+        }
+        Ok(())
+    }
+}
+
+impl FakeTouch {
+    /// Wraps the device, if the input manager has set it up.
+    pub(crate) unsafe fn new(device: *mut wlr_input_device) -> Option<Self> {
+        let touch = (*device).__bindgen_anon_1.touch;
+        if (*touch).data.is_null() {
+            return None
+        }
+        Some(FakeTouch { handle: touch::Handle::from_ptr(touch) })
+    }
+
+    /// Get a handle to the input device backing this fake touch device.
+    pub fn input_handle(&self) -> input::Handle {
+        input::Handle::Touch(self.handle.clone())
+    }
+
+    /// Put a new touch point down.
+    ///
+    /// The position is normalized, from (0.0, 0.0) in the top left to (1.0, 1.0)
+    /// in the bottom right of the area the device is mapped to.
+    pub fn touch_down(&mut self, touch_id: i32, x: f64, y: f64) -> HandleResult<()> {
+        let (device, touch) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_touch_down { device,
+                                                   time_msec: current_time().to_ms(),
+                                                   touch_id,
+                                                   x,
+                                                   y,
+                                                   ..Default::default() };
+            emit(&mut (*touch).events.down, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Move a touch point that is down.
+    ///
+    /// The position is normalized, like in `touch_down`.
+    pub fn touch_motion(&mut self, touch_id: i32, x: f64, y: f64) -> HandleResult<()> {
+        let (device, touch) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_touch_motion { device,
+                                                     time_msec: current_time().to_ms(),
+                                                     touch_id,
+                                                     x,
+                                                     y,
+                                                     ..Default::default() };
+            emit(&mut (*touch).events.motion, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Lift a touch point.
+    pub fn touch_up(&mut self, touch_id: i32) -> HandleResult<()> {
+        let (device, touch) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_touch_up { device,
+                                                 time_msec: current_time().to_ms(),
+                                                 touch_id,
+                                                 ..Default::default() };
+            emit(&mut (*touch).events.up, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Cancel a touch point, e.g. because the touch was recognized as a palm.
+    pub fn touch_cancel(&mut self, touch_id: i32) -> HandleResult<()> {
+        let (device, touch) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_touch_cancel { device,
+                                                     time_msec: current_time().to_ms(),
+                                                     touch_id,
+                                                     ..Default::default() };
+            emit(&mut (*touch).events.cancel, &mut event);
+        }
+        Ok(())
+    }
+}
+
+impl FakeTabletTool {
+    /// Wraps the device, if the input manager has set it up.
+    pub(crate) unsafe fn new(device: *mut wlr_input_device) -> Option<Self> {
+        let tablet = (*device).__bindgen_anon_1.tablet;
+        if (*tablet).data.is_null() {
+            return None
+        }
+        Some(FakeTabletTool { handle: tablet_tool::Handle::from_ptr(tablet) })
+    }
+
+    /// Get a handle to the input device backing this fake tablet tool.
+    pub fn input_handle(&self) -> input::Handle {
+        input::Handle::TabletTool(self.handle.clone())
+    }
+
+    /// Move the tool to the position, e.g. while hovering or drawing.
+    ///
+    /// The position is normalized, from (0.0, 0.0) in the top left to (1.0, 1.0)
+    /// in the bottom right of the tablet.
+    pub fn move_to(&mut self, x: f64, y: f64) -> HandleResult<()> {
+        let (device, tool) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_tablet_tool_axis {
+                device,
+                time_msec: current_time().to_ms(),
+                updated_axes: WLR_TABLET_TOOL_AXIS_X as u32 | WLR_TABLET_TOOL_AXIS_Y as u32,
+                x,
+                y,
+                ..Default::default()
+            };
+            emit(&mut (*tool).events.axis, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Change the pressure of the tool on the tablet, from 0.0 to 1.0.
+    pub fn set_pressure(&mut self, pressure: f64) -> HandleResult<()> {
+        let (device, tool) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_tablet_tool_axis {
+                device,
+                time_msec: current_time().to_ms(),
+                updated_axes: WLR_TABLET_TOOL_AXIS_PRESSURE as u32,
+                pressure,
+                ..Default::default()
+            };
+            emit(&mut (*tool).events.axis, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Bring the tool into or out of proximity of the tablet at the position.
+    pub fn proximity(&mut self, x: f64, y: f64, state: wlr_tablet_tool_proximity_state) -> HandleResult<()> {
+        let (device, tool) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_tablet_tool_proximity { device,
+                                                              time_msec: current_time().to_ms(),
+                                                              x,
+                                                              y,
+                                                              state,
+                                                              ..Default::default() };
+            emit(&mut (*tool).events.proximity, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Put the tip of the tool down on, or lift it from, the tablet at the position.
+    pub fn tip(&mut self, x: f64, y: f64, state: wlr_tablet_tool_tip_state) -> HandleResult<()> {
+        let (device, tool) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_tablet_tool_tip { device,
+                                                        time_msec: current_time().to_ms(),
+                                                        x,
+                                                        y,
+                                                        state,
+                                                        ..Default::default() };
+            emit(&mut (*tool).events.tip, &mut event);
+        }
+        Ok(())
+    }
+
+    /// Change the state of a button on the tool.
+    pub fn button(&mut self, button: u32, state: wlr_button_state) -> HandleResult<()> {
+        let (device, tool) = alive(&self.handle)?;
+        unsafe {
+            let mut event = wlr_event_tablet_tool_button { device,
+                                                           time_msec: current_time().to_ms(),
+                                                           button,
+                                                           state,
+                                                           ..Default::default() };
+            emit(&mut (*tool).events.button, &mut event);
+        }
+        Ok(())
+    }
+}
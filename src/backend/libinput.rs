@@ -1,7 +1,7 @@
 use wlroots_sys::{wlr_backend, wl_display, wlr_libinput_backend_create, libinput_device,
                   wlr_libinput_get_device_handle, wlr_input_device_is_libinput};
 
-use {backend::Session, input};
+use {backend::{CreationError, Kind, Session}, input};
 
 #[derive(Debug, Hash, Eq, PartialEq)]
 pub struct Libinput {
@@ -9,12 +9,16 @@ pub struct Libinput {
 }
 
 impl Libinput {
-    pub unsafe fn new(display: *mut wl_display, session: Session) -> Self {
+    /// Creates a libinput backend, which opens the input devices of the seat
+    /// through the session.
+    pub unsafe fn new(display: *mut wl_display,
+                      session: Session)
+                      -> Result<Self, CreationError> {
         let backend = wlr_libinput_backend_create(display, session.as_ptr());
         if backend.is_null() {
-            panic!("Could not construct Wayland backend");
+            return Err(CreationError::Backend(Kind::Libinput))
         }
-        Libinput { backend }
+        Ok(Libinput { backend })
     }
 
     /// Get the underlying libinput_device handle for the given input device.
@@ -2,17 +2,21 @@ mod backend;
 mod wayland;
 mod x11;
 mod headless;
-mod drm;
+mod headless_input;
+pub mod drm;
 mod libinput;
 mod multi;
-mod session;
+mod selection;
+pub mod session;
 
 pub use self::backend::*;
-pub use self::session::*;
+pub use self::session::{Device, GpuInfo, Session};
 pub use self::wayland::*;
 pub use self::x11::*;
 pub use self::headless::*;
-pub use self::drm::*;
+pub use self::headless_input::*;
+pub use self::drm::Drm;
 pub use self::libinput::*;
 pub use self::multi::*;
+pub use self::selection::*;
 
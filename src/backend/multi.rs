@@ -1,7 +1,48 @@
-use wlroots_sys::{wlr_backend, wlr_backend_autocreate, wl_display, wlr_multi_backend_add,
-                  wlr_multi_backend_remove, wlr_multi_is_empty};
+use std::cell::RefCell;
 
-use backend::UnsafeRenderSetupFunction;
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_backend, wlr_backend_autocreate, wlr_backend_destroy, wl_display,
+                  wlr_multi_backend_add, wlr_multi_backend_create, wlr_multi_backend_remove,
+                  wlr_multi_for_each_backend, wlr_multi_is_empty};
+
+use {compositor, backend::{Backend, UnsafeRenderSetupFunction, CreationError}};
+
+/// Handler for backends being added to or removed from a `Multi` backend.
+///
+/// Set it with `Multi::set_handler`. It's only called while the compositor
+/// is running, and only for backends added or removed through `Multi`.
+/// The backends `Multi::auto_create` starts with aren't reported, nor are
+/// any that wlroots adds or removes by itself.
+#[allow(unused_variables)]
+pub trait MultiHandler {
+    /// Called after a backend was added with `Multi::add` or
+    /// `Multi::add_backend`.
+    fn backend_added(&mut self, compositor_handle: compositor::Handle, backend: &Backend) {}
+
+    /// Called when a backend is removed with `Multi::remove`,
+    /// `Multi::remove_and_destroy` or `Multi::remove_backend`, before it's
+    /// detached.
+    fn backend_removed(&mut self, compositor_handle: compositor::Handle, backend: &Backend) {}
+}
+
+wayland_listener!(pub(crate) MultiListener, *mut wlr_backend, [
+    destroy_listener => destroy_notify: |this: &mut MultiListener, _data: *mut libc::c_void,|
+    unsafe {
+        let multi = this.data;
+        HANDLERS.with(|handlers| handlers.borrow_mut().retain(|&(ptr, _)| ptr != multi));
+        let _listener = untrack(multi);
+    };
+]);
+
+thread_local! {
+    /// The handlers set on multi backends. A handler is taken out while
+    /// it's being called, so it can use the `Multi` it's set on.
+    static HANDLERS: RefCell<Vec<(*mut wlr_backend, Option<Box<MultiHandler>>)>> =
+        RefCell::new(Vec::new());
+    /// Listeners for the destruction of multi backends that have a handler.
+    static LISTENERS: RefCell<Vec<Box<MultiListener>>> = RefCell::new(Vec::new());
+}
 
 /// When multiple backends are running or when the compositor writer doesn't care and
 /// just used the auto create option in the `CompositorBuilder`.
@@ -12,14 +53,26 @@ pub struct Multi {
 
 impl Multi {
     /// Auto create a backend based on the environment.
+    ///
+    /// Use a `Selection` to control which backend is chosen and learn why.
     pub unsafe fn auto_create(display: *mut wl_display,
                               render_setup_func: Option<UnsafeRenderSetupFunction>)
-                              -> Self {
+                              -> Result<Self, CreationError> {
         let backend = wlr_backend_autocreate(display, render_setup_func);
         if backend.is_null() {
-            panic!("Could not auto construct backend");
+            return Err(CreationError::AutoCreate)
+        }
+        Ok(Multi { backend })
+    }
+
+    /// Creates an empty multi backend, to add other backends to.
+    pub unsafe fn new(display: *mut wl_display) -> Option<Self> {
+        let backend = wlr_multi_backend_create(display);
+        if backend.is_null() {
+            None
+        } else {
+            Some(Multi { backend })
         }
-        Multi { backend }
     }
 
     /// Adds the given backend to the multi backend.
@@ -28,7 +81,11 @@ impl Multi {
     ///
     /// This should be done before the new backend is started.
     pub unsafe fn add_backend(&self, new_backend: *mut wlr_backend) -> bool {
-        wlr_multi_backend_add(self.backend, new_backend)
+        if !wlr_multi_backend_add(self.backend, new_backend) {
+            return false
+        }
+        report(self.backend, &Backend::from_backend(new_backend), true);
+        true
     }
 
     /// Removes the backend.
@@ -37,12 +94,154 @@ impl Multi {
     ///
     /// Doesn't check if that backend is valid.
     pub unsafe fn remove_backend(&self, backend: *mut wlr_backend) {
+        report(self.backend, &Backend::from_backend(backend), false);
         wlr_multi_backend_remove(self.backend, backend)
     }
 
+    /// Adds a backend, e.g. a headless backend for a virtual output next to
+    /// the DRM backend. If the multi backend is running the new backend is
+    /// started, and its inputs and outputs are announced like any other.
+    ///
+    /// The backend is given back if it can't be added, e.g. because it's
+    /// already a child or it couldn't be started.
+    pub fn add(&mut self, backend: Backend) -> Result<(), Backend> {
+        unsafe {
+            let child = backend.as_ptr();
+            if self.children().iter().any(|backend| backend.as_ptr() == child) ||
+               !wlr_multi_backend_add(self.backend, child) {
+                return Err(backend)
+            }
+            report(self.backend, &backend, true);
+            Ok(())
+        }
+    }
+
+    /// Detaches a child backend from this backend. Its inputs and outputs
+    /// are no longer announced through this backend.
+    ///
+    /// The backend isn't destroyed, that's up to the caller, e.g. with
+    /// `remove_and_destroy`.
+    ///
+    /// Returns `false` if it's not a child of this backend.
+    pub fn remove(&mut self, backend: &Backend) -> bool {
+        unsafe {
+            let child = backend.as_ptr();
+            if !self.children().iter().any(|backend| backend.as_ptr() == child) {
+                return false
+            }
+            report(self.backend, backend, false);
+            wlr_multi_backend_remove(self.backend, child);
+            true
+        }
+    }
+
+    /// Removes a child backend like `remove` and destroys it, along with its
+    /// inputs and outputs.
+    ///
+    /// The backend is given back if it's not a child of this backend.
+    pub fn remove_and_destroy(&mut self, backend: Backend) -> Result<(), Backend> {
+        if !self.remove(&backend) {
+            return Err(backend)
+        }
+        unsafe { wlr_backend_destroy(backend.as_ptr()) }
+        Ok(())
+    }
+
+    /// Get the backends this backend is made of.
+    pub fn children(&self) -> Vec<Backend> {
+        unsafe extern "C" fn c_iterator(backend: *mut wlr_backend, data: *mut libc::c_void) {
+            let children = &mut *(data as *mut Vec<Backend>);
+            children.push(Backend::from_backend(backend));
+        }
+        let mut children = Vec::new();
+        unsafe {
+            wlr_multi_for_each_backend(self.backend,
+                                       Some(c_iterator),
+                                       &mut children as *mut _ as *mut libc::c_void);
+        }
+        children
+    }
+
+    /// Set the handler called when backends are added or removed, replacing
+    /// the previous one.
+    pub fn set_handler<H: MultiHandler + 'static>(&mut self, handler: H) {
+        let multi = self.backend;
+        let handler: Box<MultiHandler> = Box::new(handler);
+        let replaced = HANDLERS.with(|handlers| {
+            let mut handlers = handlers.borrow_mut();
+            match handlers.iter_mut().find(|&&mut (ptr, _)| ptr == multi) {
+                Some(&mut (_, ref mut old)) => {
+                    *old = Some(handler);
+                    return true
+                }
+                None => {}
+            }
+            handlers.push((multi, Some(handler)));
+            false
+        });
+        if !replaced {
+            unsafe { track(multi) }
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         unsafe {
             wlr_multi_is_empty(self.backend)
         }
     }
 }
+
+/// Listen for the destruction of the multi backend, to forget its handler.
+unsafe fn track(multi: *mut wlr_backend) {
+    let mut listener = MultiListener::new(multi);
+    wl_signal_add(&mut (*multi).events.destroy as *mut _ as _,
+                  listener.destroy_listener() as _);
+    LISTENERS.with(|listeners| listeners.borrow_mut().push(listener));
+}
+
+/// Stop listening for the destruction of the multi backend.
+///
+/// The listener is returned so it can outlive a notify function that is
+/// running on it.
+unsafe fn untrack(multi: *mut wlr_backend) -> Option<Box<MultiListener>> {
+    let index = LISTENERS.with(|listeners| {
+        listeners.borrow().iter().position(|listener| listener.data == multi)
+    })?;
+    let mut listener = LISTENERS.with(|listeners| listeners.borrow_mut().remove(index));
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_list_remove,
+                  &mut (*listener.destroy_listener()).link as *mut _ as _);
+    Some(listener)
+}
+
+/// Calls the handler of the multi backend, if it has one.
+unsafe fn report(multi: *mut wlr_backend, backend: &Backend, added: bool) {
+    let compositor = match compositor::handle() {
+        Some(handle) => handle,
+        None => return
+    };
+    let handler = HANDLERS.with(|handlers| {
+        handlers.borrow_mut().iter_mut()
+            .find(|&&mut (ptr, _)| ptr == multi)
+            .and_then(|&mut (_, ref mut handler)| handler.take())
+    });
+    let mut handler = match handler {
+        Some(handler) => handler,
+        None => return
+    };
+    if added {
+        handler.backend_added(compositor, backend)
+    } else {
+        handler.backend_removed(compositor, backend)
+    }
+    // NOTE The handler could have been replaced while it was running.
+    HANDLERS.with(|handlers| {
+        let mut handlers = handlers.borrow_mut();
+        let slot = handlers.iter_mut().find(|&&mut (ptr, _)| ptr == multi);
+        if let Some(&mut (_, ref mut slot)) = slot {
+            if slot.is_none() {
+                *slot = Some(handler);
+            }
+        }
+    });
+}
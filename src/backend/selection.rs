@@ -0,0 +1,448 @@
+//! Choosing the backend to run on.
+//!
+//! `Multi::auto_create` leaves the choice to wlroots, which looks at the
+//! environment and gives no indication of what it picked. A `Selection` makes
+//! the same kind of choice, but it can be steered and it reports which backend
+//! was chosen and why:
+//!
+//! ```rust,ignore
+//! let selection = backend::Selection::new()
+//!     .forbid(backend::Kind::X11)
+//!     .outputs(2);
+//! let (compositor, choice) = compositor::Builder::new()
+//!     .build_selected((), selection)
+//!     .expect("No backend could be used");
+//! wlr_log!(WLR_INFO, "{}", choice);
+//! ```
+//!
+//! Without preferences the nested Wayland backend is tried first when
+//! `WAYLAND_DISPLAY` is set, then the X11 backend when `DISPLAY` is set, and
+//! then the DRM backend. The headless backend is only used when it's
+//! preferred.
+
+use std::{env, fmt, error::Error, path::{Path, PathBuf}};
+
+use libc::c_int;
+use wlroots_sys::{wl_display, wlr_backend_destroy, wlr_session_close_file, wlr_session_destroy,
+                  wlr_session_find_gpus};
+
+use backend::{Backend, CreationError, Drm, Headless, Kind, Libinput, Multi, Session,
+              UnsafeRenderSetupFunction, Wayland, X11};
+
+/// The size of the outputs created on the headless backend.
+const HEADLESS_OUTPUT_SIZE: (u32, u32) = (1280, 720);
+
+/// The GPU the DRM backend renders with.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum Gpu {
+    /// The path to the device node, e.g. `/dev/dri/card1`.
+    Path(PathBuf),
+    /// The PCI address of the device, e.g. `0000:01:00.0`, as found in
+    /// `/dev/dri/by-path`.
+    PciId(String)
+}
+
+/// Why a backend was chosen.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Reason {
+    /// It was the first preferred backend that could be created.
+    Preferred,
+    /// `WAYLAND_DISPLAY` is set, or a remote was given, so the compositor is
+    /// nested in a Wayland compositor.
+    WaylandDisplay,
+    /// `DISPLAY` is set, or a display was given, so the compositor is nested
+    /// in an X server.
+    X11Display,
+    /// There was no nested backend to use.
+    Fallback
+}
+
+/// The backend a `Selection` chose, and why.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Choice {
+    kind: Kind,
+    reason: Reason,
+    failures: Vec<(Kind, CreationError)>
+}
+
+/// Why no backend could be chosen.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SelectionError {
+    /// Every backend that would have been tried is forbidden.
+    NoCandidates,
+    /// Every backend that was tried failed, in the order they were tried.
+    Failed(Vec<(Kind, CreationError)>)
+}
+
+/// Builder for choosing a backend.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Selection {
+    preferred: Vec<Kind>,
+    forbidden: Vec<Kind>,
+    outputs: Option<usize>,
+    gpu: Option<Gpu>,
+    wayland_remote: Option<String>,
+    x11_display: Option<String>
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Selection::default()
+    }
+
+    /// Try this kind of backend before those chosen from the environment.
+    ///
+    /// Preferred backends are tried in the order they were preferred.
+    /// Preferring `Kind::Libinput` does nothing, it's only used along with
+    /// the DRM backend.
+    pub fn prefer(mut self, kind: Kind) -> Self {
+        if !self.preferred.contains(&kind) {
+            self.preferred.push(kind);
+        }
+        self
+    }
+
+    /// Never use this kind of backend, even if it's preferred.
+    pub fn forbid(mut self, kind: Kind) -> Self {
+        if !self.forbidden.contains(&kind) {
+            self.forbidden.push(kind);
+        }
+        self
+    }
+
+    /// Set the number of outputs created on the Wayland, X11 and headless
+    /// backends. By default there's one.
+    ///
+    /// The outputs of the DRM backend are the connected monitors.
+    pub fn outputs(mut self, outputs: usize) -> Self {
+        self.outputs = Some(outputs);
+        self
+    }
+
+    /// Set the GPU the DRM backend renders with.
+    ///
+    /// By default it's the boot GPU, as picked by wlroots.
+    pub fn gpu(mut self, gpu: Gpu) -> Self {
+        self.gpu = Some(gpu);
+        self
+    }
+
+    /// Set the name of the Wayland socket to connect to on the Wayland
+    /// backend, instead of `WAYLAND_DISPLAY`.
+    pub fn wayland_remote(mut self, remote: String) -> Self {
+        self.wayland_remote = Some(remote);
+        self
+    }
+
+    /// Set the X11 display to connect to on the X11 backend, instead of
+    /// `DISPLAY`.
+    pub fn x11_display(mut self, display: String) -> Self {
+        self.x11_display = Some(display);
+        self
+    }
+
+    /// Use these remotes unless others were set on the selection.
+    pub(crate) fn default_remotes(mut self,
+                                  wayland_remote: Option<String>,
+                                  x11_display: Option<String>)
+                                  -> Self {
+        self.wayland_remote = self.wayland_remote.or(wayland_remote);
+        self.x11_display = self.x11_display.or(x11_display);
+        self
+    }
+
+    /// The backends that will be tried, in order, with the reason each would
+    /// be chosen.
+    pub fn candidates(&self) -> Vec<(Kind, Reason)> {
+        self.candidates_in(env::var_os("WAYLAND_DISPLAY").is_some(),
+                           env::var_os("DISPLAY").is_some())
+    }
+
+    /// The `candidates` for an environment that does or doesn't name a
+    /// Wayland compositor and an X server to nest in.
+    fn candidates_in(&self, wayland_display: bool, x11_display: bool) -> Vec<(Kind, Reason)> {
+        let mut candidates: Vec<(Kind, Reason)> =
+            self.preferred.iter().map(|&kind| (kind, Reason::Preferred)).collect();
+        if self.wayland_remote.is_some() || wayland_display {
+            candidates.push((Kind::Wayland, Reason::WaylandDisplay));
+        }
+        if self.x11_display.is_some() || x11_display {
+            candidates.push((Kind::X11, Reason::X11Display));
+        }
+        candidates.push((Kind::Drm, Reason::Fallback));
+        let mut seen = Vec::new();
+        candidates.retain(|&(kind, _)| {
+            if kind == Kind::Libinput || seen.contains(&kind) ||
+               self.forbidden.contains(&kind) {
+                return false
+            }
+            seen.push(kind);
+            true
+        });
+        candidates
+    }
+
+    /// Create the first of the `candidates` that can be created.
+    ///
+    /// A DRM backend is returned in a `Multi` backend, along with the libinput
+    /// backend for its input devices.
+    pub unsafe fn create(&self,
+                         display: *mut wl_display,
+                         render_setup_func: Option<UnsafeRenderSetupFunction>)
+                         -> Result<(Backend, Choice), SelectionError> {
+        let candidates = self.candidates();
+        if candidates.is_empty() {
+            return Err(SelectionError::NoCandidates)
+        }
+        let mut failures = Vec::new();
+        for (kind, reason) in candidates {
+            match self.create_kind(kind, display, render_setup_func) {
+                Ok(backend) => {
+                    wlr_log!(WLR_INFO, "Using the {} backend", kind);
+                    return Ok((backend, Choice { kind, reason, failures }))
+                }
+                Err(err) => {
+                    wlr_log!(WLR_INFO, "Not using the {} backend: {}", kind, err);
+                    failures.push((kind, err))
+                }
+            }
+        }
+        Err(SelectionError::Failed(failures))
+    }
+
+    unsafe fn create_kind(&self,
+                          kind: Kind,
+                          display: *mut wl_display,
+                          render_setup_func: Option<UnsafeRenderSetupFunction>)
+                          -> Result<Backend, CreationError> {
+        let outputs = self.outputs.unwrap_or(1);
+        match kind {
+            Kind::Wayland => {
+                let remote = self.wayland_remote.clone();
+                let backend = Wayland::new(display, remote, render_setup_func)?;
+                // NOTE These are created when the backend starts.
+                for _ in 0..outputs {
+                    backend.create_output();
+                }
+                Ok(Backend::Wayland(backend))
+            }
+            Kind::X11 => {
+                let backend = X11::new(display, self.x11_display.clone(), render_setup_func)?;
+                for _ in 0..outputs {
+                    backend.create_output();
+                }
+                Ok(Backend::X11(backend))
+            }
+            Kind::Headless => {
+                let backend = Headless::new(display, render_setup_func)?;
+                let (width, height) = HEADLESS_OUTPUT_SIZE;
+                for _ in 0..outputs {
+                    backend.add_output(width, height);
+                }
+                Ok(Backend::Headless(backend))
+            }
+            Kind::Drm => {
+                let session = Session::new(display).ok_or(CreationError::Session)?;
+                let session_ptr = session.as_ptr();
+                let result = self.create_drm(display, session, render_setup_func);
+                if result.is_err() {
+                    wlr_session_destroy(session_ptr);
+                }
+                result
+            }
+            // NOTE It's never a candidate, it's created with the DRM backend.
+            Kind::Libinput => Err(CreationError::Backend(Kind::Libinput))
+        }
+    }
+
+    unsafe fn create_drm(&self,
+                         display: *mut wl_display,
+                         mut session: Session,
+                         render_setup_func: Option<UnsafeRenderSetupFunction>)
+                         -> Result<Backend, CreationError> {
+        let gpu_fd = self.open_gpu(&mut session)?;
+        let session_ptr = session.as_ptr();
+        let multi = match Multi::new(display) {
+            Some(multi) => multi,
+            None => {
+                wlr_session_close_file(session_ptr, gpu_fd);
+                return Err(CreationError::Backend(Kind::Drm))
+            }
+        };
+        let result = Libinput::new(display, Session::from_ptr(session_ptr))
+            .and_then(|libinput| {
+                multi.add_backend(libinput.backend);
+                Drm::new(display, session, gpu_fd, None, render_setup_func)
+            });
+        match result {
+            Ok(drm) => {
+                multi.add_backend(drm.backend);
+                Ok(Backend::Multi(multi))
+            }
+            Err(err) => {
+                // NOTE The GPU is only closed by the DRM backend once it exists.
+                wlr_session_close_file(session_ptr, gpu_fd);
+                wlr_backend_destroy(multi.backend);
+                Err(err)
+            }
+        }
+    }
+
+    unsafe fn open_gpu(&self, session: &mut Session) -> Result<c_int, CreationError> {
+        let path = match self.gpu {
+            Some(Gpu::Path(ref path)) => path.clone(),
+            Some(Gpu::PciId(ref id)) => {
+                Path::new("/dev/dri/by-path").join(format!("pci-{}-card", id))
+            }
+            None => {
+                let mut gpu_fd: c_int = -1;
+                if wlr_session_find_gpus(session.as_ptr(), 1, &mut gpu_fd) == 0 {
+                    return Err(CreationError::Gpu(None))
+                }
+                return Ok(gpu_fd)
+            }
+        };
+        if !path.exists() {
+            return Err(CreationError::Gpu(self.gpu.clone()))
+        }
+        match session.open_file(&path) {
+            gpu_fd if gpu_fd < 0 => Err(CreationError::Gpu(self.gpu.clone())),
+            gpu_fd => Ok(gpu_fd)
+        }
+    }
+}
+
+impl Choice {
+    /// The kind of backend that was chosen.
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Why it was chosen.
+    pub fn reason(&self) -> Reason {
+        self.reason
+    }
+
+    /// The backends that were tried before it and why they failed.
+    pub fn failures(&self) -> &[(Kind, CreationError)] {
+        &self.failures
+    }
+}
+
+impl fmt::Display for Gpu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Gpu::Path(ref path) => write!(f, "{}", path.display()),
+            Gpu::PciId(ref id) => write!(f, "at PCI address {}", id)
+        }
+    }
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match *self {
+            Reason::Preferred => "it was preferred",
+            Reason::WaylandDisplay => "the compositor runs in a Wayland session",
+            Reason::X11Display => "the compositor runs in an X11 session",
+            Reason::Fallback => "there was no nested backend to use"
+        };
+        f.write_str(reason)
+    }
+}
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Using the {} backend because {}", self.kind, self.reason)?;
+        for &(kind, ref err) in &self.failures {
+            write!(f, "; the {} backend was tried first: {}", kind, err)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SelectionError::NoCandidates => f.write_str(self.description()),
+            SelectionError::Failed(ref failures) => {
+                f.write_str(self.description())?;
+                for &(kind, ref err) in failures {
+                    write!(f, "; {}: {}", kind, err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for SelectionError {
+    fn description(&self) -> &str {
+        match *self {
+            SelectionError::NoCandidates => "Every backend that could be used is forbidden",
+            SelectionError::Failed(_) => "No backend could be created"
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drm_is_the_fallback() {
+        assert_eq!(Selection::new().candidates_in(false, false),
+                   vec![(Kind::Drm, Reason::Fallback)]);
+    }
+
+    #[test]
+    fn nested_backends_come_first() {
+        assert_eq!(Selection::new().candidates_in(true, true),
+                   vec![(Kind::Wayland, Reason::WaylandDisplay),
+                        (Kind::X11, Reason::X11Display),
+                        (Kind::Drm, Reason::Fallback)]);
+    }
+
+    #[test]
+    fn remotes_count_as_displays() {
+        let selection = Selection::new().wayland_remote("wayland-1".into())
+                                        .x11_display(":1".into());
+        assert_eq!(selection.candidates_in(false, false),
+                   vec![(Kind::Wayland, Reason::WaylandDisplay),
+                        (Kind::X11, Reason::X11Display),
+                        (Kind::Drm, Reason::Fallback)]);
+    }
+
+    #[test]
+    fn preferred_backends_are_tried_first_and_once() {
+        let selection = Selection::new().prefer(Kind::Headless)
+                                        .prefer(Kind::X11)
+                                        .prefer(Kind::Headless);
+        assert_eq!(selection.candidates_in(true, true),
+                   vec![(Kind::Headless, Reason::Preferred),
+                        (Kind::X11, Reason::Preferred),
+                        (Kind::Wayland, Reason::WaylandDisplay),
+                        (Kind::Drm, Reason::Fallback)]);
+    }
+
+    #[test]
+    fn libinput_is_never_a_candidate() {
+        let selection = Selection::new().prefer(Kind::Libinput);
+        assert_eq!(selection.candidates_in(false, false),
+                   vec![(Kind::Drm, Reason::Fallback)]);
+    }
+
+    #[test]
+    fn forbidden_backends_are_skipped() {
+        let selection = Selection::new().prefer(Kind::X11)
+                                        .forbid(Kind::X11)
+                                        .forbid(Kind::Drm);
+        assert_eq!(selection.candidates_in(true, true),
+                   vec![(Kind::Wayland, Reason::WaylandDisplay)]);
+    }
+
+    #[test]
+    fn forbidding_everything_leaves_no_candidates() {
+        let selection = Selection::new().forbid(Kind::Drm);
+        assert_eq!(selection.candidates_in(false, false), vec![]);
+    }
+}
@@ -1,5 +1,13 @@
+//! Sessions give the compositor access to the devices of its seat, and are
+//! deactivated while another virtual terminal is in use.
+//!
+//! Implement [`Handler`](./trait.Handler.html) and pass it to
+//! `compositor::Builder::session_handler` to react to that.
+
+use std::ffi::CStr;
+use std::fs;
 use std::marker::PhantomData;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use libc::{c_int, c_uint, c_char};
 use wlroots_sys::{wl_display, wlr_session, wlr_session_create, wlr_session_destroy,
@@ -8,12 +16,44 @@ use wlroots_sys::{wl_display, wlr_session, wlr_session_create, wlr_session_destr
                   dev_t};
 
 use utils::safe_as_cstring;
+pub use manager::session_handler::Handler;
+pub(crate) use manager::session_handler::SessionListener;
+
+#[allow(non_camel_case_types)]
+pub(crate) enum udev_device {}
+
+#[link(name = "udev")]
+extern "C" {
+    fn udev_device_new_from_subsystem_sysname(udev: *mut udev,
+                                              subsystem: *const c_char,
+                                              sysname: *const c_char)
+                                              -> *mut udev_device;
+    pub(crate) fn udev_device_get_property_value(device: *mut udev_device,
+                                                 key: *const c_char)
+                                                 -> *const c_char;
+    pub(crate) fn udev_device_unref(device: *mut udev_device) -> *mut udev_device;
+}
 
 pub struct Device<'session> {
     device: *mut wlr_device,
     phantom: PhantomData<&'session ()>
 }
 
+/// A GPU found by `Session::find_gpus`.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GpuInfo {
+    /// The path to the device node, e.g. `/dev/dri/card0`.
+    pub path: PathBuf,
+    /// Whether the firmware used this GPU to boot, i.e. it drives the
+    /// built-in display or the monitors connected at boot.
+    pub boot_vga: bool,
+    /// The name of the kernel driver, e.g. `i915` or `amdgpu`.
+    pub driver: Option<String>,
+    /// The PCI address of the device, e.g. `0000:01:00.0`, if it's a PCI
+    /// device.
+    pub pci_id: Option<String>
+}
+
 pub struct Session<'session> {
     session: *mut wlr_session,
     phantom: PhantomData<&'session ()>
@@ -33,6 +73,36 @@ impl <'session> Device<'session> {
     }
 }
 
+impl GpuInfo {
+    /// Get the information sysfs has about the GPU with this device node.
+    ///
+    /// Returns `None` if it's not a DRM device.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let path = fs::canonicalize(path).ok()?;
+        let name = path.file_name()?.to_str()?.to_owned();
+        let device = Path::new("/sys/class/drm").join(&name).join("device");
+        if !device.exists() {
+            return None
+        }
+        let boot_vga = fs::read_to_string(device.join("boot_vga"))
+            .map(|boot_vga| boot_vga.trim() == "1")
+            .unwrap_or(false);
+        let file_name = |path: PathBuf| path.file_name()
+                                            .and_then(|name| name.to_str())
+                                            .map(|name| name.to_owned());
+        let driver = fs::read_link(device.join("driver")).ok().and_then(&file_name);
+        let is_pci = fs::read_link(device.join("subsystem")).ok()
+            .and_then(&file_name)
+            .map_or(false, |subsystem| subsystem == "pci");
+        let pci_id = if is_pci {
+            fs::canonicalize(&device).ok().and_then(&file_name)
+        } else {
+            None
+        };
+        Some(GpuInfo { path, boot_vga, driver, pci_id })
+    }
+}
+
 impl <'session> Session<'session> {
 	  /// Signal for when the session becomes active/inactive.
     /// It's called when we swap virtual terminal.
@@ -72,6 +142,56 @@ impl <'session> Session<'session> {
         }
     }
 
+    /// Find the GPUs of the seat of the session, in the order wlroots would
+    /// use them: the boot GPU first, then the others by device node.
+    pub fn find_gpus(&self) -> Vec<GpuInfo> {
+        let seat = self.seat_name();
+        let entries = match fs::read_dir("/dev/dri") {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new()
+        };
+        let mut gpus: Vec<GpuInfo> = entries.filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                name.starts_with("card") && name.len() > 4 &&
+                    name[4..].chars().all(|c| c.is_ascii_digit())
+            })
+            .filter_map(|entry| GpuInfo::from_path(entry.path()))
+            .filter(|gpu| on_seat(self.device_seat(gpu).as_ref().map(|seat| seat.as_str()), &seat))
+            .collect();
+        gpus.sort_by(|a, b| b.boot_vga.cmp(&a.boot_vga).then_with(|| a.path.cmp(&b.path)));
+        gpus
+    }
+
+    /// Get the name of the seat of the session, e.g. `seat0`.
+    pub(crate) fn seat_name(&self) -> String {
+        unsafe { CStr::from_ptr((*self.session).seat.as_ptr()).to_string_lossy().into_owned() }
+    }
+
+    /// Get the seat udev assigned the GPU to, if it assigned one.
+    fn device_seat(&self, gpu: &GpuInfo) -> Option<String> {
+        let sysname = safe_as_cstring(gpu.path.file_name()?.to_str()?);
+        let subsystem = safe_as_cstring("drm");
+        let key = safe_as_cstring("ID_SEAT");
+        unsafe {
+            let device = udev_device_new_from_subsystem_sysname(self.udev(),
+                                                                subsystem.as_ptr(),
+                                                                sysname.as_ptr());
+            if device.is_null() {
+                return None
+            }
+            let seat = udev_device_get_property_value(device, key.as_ptr());
+            let seat = if seat.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(seat).to_string_lossy().into_owned())
+            };
+            udev_device_unref(device);
+            seat
+        }
+    }
+
     /// Changes the virtual terminal.
     pub fn change_vt(&mut self, vt: c_uint) -> bool {
         unsafe {
@@ -135,3 +255,33 @@ impl <'session> Session<'session> {
         Session { session, phantom: PhantomData }
     }
 }
+
+/// Whether a device udev assigned to `device_seat` belongs to the seat.
+///
+/// Devices without a seat are on `seat0`.
+pub(crate) fn on_seat(device_seat: Option<&str>, seat: &str) -> bool {
+    seat.is_empty() || device_seat.unwrap_or("seat0") == seat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn devices_without_a_seat_are_on_seat0() {
+        assert!(on_seat(None, "seat0"));
+        assert!(!on_seat(None, "seat1"));
+    }
+
+    #[test]
+    fn devices_on_another_seat_are_skipped() {
+        assert!(on_seat(Some("seat1"), "seat1"));
+        assert!(!on_seat(Some("seat1"), "seat0"));
+    }
+
+    #[test]
+    fn sessions_without_a_seat_see_every_device() {
+        assert!(on_seat(Some("seat1"), ""));
+        assert!(on_seat(None, ""));
+    }
+}
@@ -3,7 +3,7 @@ use std::ptr;
 use wlroots_sys::{wlr_backend, wl_display, wlr_wl_backend_create,
                   wlr_wl_output_create, wlr_input_device_is_wl, wlr_output_is_wl};
 
-use {backend::UnsafeRenderSetupFunction,
+use {backend::{UnsafeRenderSetupFunction, CreationError, Kind},
      output::{self, Output},
      input,
      utils::{Handleable, safe_as_cstring}};
@@ -24,17 +24,19 @@ impl Wayland {
     /// The `remote` argument is the name of the host compositor wayland socket. Set
     /// to `None` for the default behaviour (WAYLAND_DISPLAY env variable or wayland-0
     /// default)
+    ///
+    /// Returns an error if the host compositor can't be connected to.
     pub unsafe fn new(display: *mut wl_display,
                       remote: Option<String>,
                       render_setup_func: Option<UnsafeRenderSetupFunction>)
-                      -> Self {
+                      -> Result<Self, CreationError> {
         let remote_cstr = remote.map(|remote| safe_as_cstring(remote));
         let remote_ptr = remote_cstr.map(|s| s.as_ptr()).unwrap_or_else(|| ptr::null_mut());
         let backend = wlr_wl_backend_create(display, remote_ptr, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct Wayland backend");
+            return Err(CreationError::Backend(Kind::Wayland))
         }
-        Wayland { backend }
+        Ok(Wayland { backend })
     }
 
 
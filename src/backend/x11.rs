@@ -3,7 +3,7 @@ use std::ptr;
 use wlroots_sys::{wlr_backend, wlr_x11_backend_create, wlr_x11_output_create,
                   wlr_input_device_is_x11, wlr_output_is_x11, wl_display};
 
-use {backend::UnsafeRenderSetupFunction,
+use {backend::{UnsafeRenderSetupFunction, CreationError, Kind},
      output::{self, Output},
      input,
      utils::{Handleable, safe_as_cstring}};
@@ -19,17 +19,21 @@ pub struct X11 {
 }
 
 impl X11 {
+    /// Creates an X11 backend, connected to `x11_display` or to the `DISPLAY`
+    /// environment variable if that's `None`.
+    ///
+    /// Returns an error if the X server can't be connected to.
     pub unsafe fn new(display: *mut wl_display,
                       x11_display: Option<String>,
                       render_setup_func: Option<UnsafeRenderSetupFunction>)
-                      -> Self {
+                      -> Result<Self, CreationError> {
         let x11_display_cstr = x11_display.map(|remote| safe_as_cstring(remote));
         let x11_display_ptr = x11_display_cstr.map(|s| s.as_ptr()).unwrap_or_else(|| ptr::null_mut());
         let backend = wlr_x11_backend_create(display, x11_display_ptr, render_setup_func);
         if backend.is_null() {
-            panic!("Could not construct X11 backend");
+            return Err(CreationError::Backend(Kind::X11))
         }
-        X11 { backend }
+        Ok(X11 { backend })
     }
 
     pub fn create_output(&self) -> Option<output::Handle> {
@@ -6,7 +6,7 @@ use std::{env, panic, ptr, any::Any, cell::{Cell, UnsafeCell},
 
 use libc;
 use wayland_sys::server::{wl_display, wl_event_loop, signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
-use wlroots_sys::{wlr_backend_destroy, wlr_backend_start,
+use wlroots_sys::{wlr_backend_destroy, wlr_backend_get_session, wlr_backend_start,
                   wlr_compositor, wlr_compositor_create, wlr_compositor_destroy,
                   wlr_xdg_shell_v6, wlr_xdg_shell_v6_create,
                   wlr_xdg_shell, wlr_xdg_shell_create};
@@ -14,7 +14,7 @@ use wlroots_sys::{wlr_backend_destroy, wlr_backend_start,
 
 use {backend::{self, UnsafeRenderSetupFunction, Backend, Session},
      data_device,
-     extensions::server_decoration,
+     extensions::{idle, server_decoration},
      surface::{self, Surface, InternalSurface},
      input,
      output,
@@ -22,6 +22,36 @@ use {backend::{self, UnsafeRenderSetupFunction, Backend, Session},
      shell::{xdg_shell, xdg_shell_v6},
      xwayland,
      utils::{HandleErr, HandleResult, Handleable}};
+#[cfg(feature = "fractional_scale")]
+use extensions::fractional_scale;
+#[cfg(feature = "idle_inhibit")]
+use extensions::idle_inhibit;
+#[cfg(feature = "input_method")]
+use extensions::input_method;
+#[cfg(feature = "keyboard_shortcuts_inhibit")]
+use extensions::keyboard_shortcuts_inhibit;
+#[cfg(feature = "output_management")]
+use extensions::output_management;
+#[cfg(feature = "output_power_management")]
+use extensions::output_power_management;
+#[cfg(feature = "pointer_constraints")]
+use extensions::pointer_constraints;
+#[cfg(feature = "pointer_gestures")]
+use extensions::pointer_gestures;
+#[cfg(feature = "presentation_time")]
+use extensions::presentation;
+#[cfg(feature = "relative_pointer")]
+use extensions::relative_pointer;
+#[cfg(feature = "tearing_control")]
+use extensions::tearing_control;
+#[cfg(feature = "input_method")]
+use extensions::text_input;
+#[cfg(feature = "viewporter")]
+use extensions::viewporter;
+#[cfg(feature = "virtual_keyboard")]
+use extensions::virtual_keyboard;
+#[cfg(feature = "virtual_pointer")]
+use extensions::virtual_pointer;
 
 /// Global compositor pointer, used to refer to the compositor state unsafely.
 pub(crate) static mut COMPOSITOR_PTR: *mut Compositor = 0 as *mut _;
@@ -88,6 +118,11 @@ pub struct Compositor {
     input_manager: Option<&'static mut input::Manager>,
     /// Manager for the outputs.
     output_manager: Option<&'static mut output::Manager>,
+    /// Listener for the session becoming active or inactive, if the backend
+    /// has a session.
+    session_listener: Option<Box<backend::session::SessionListener>>,
+    /// Listener for GPU hotplug, if the backend has a session.
+    drm_monitor: Option<backend::drm::Monitor>,
     /// Manager for stable XDG shells.
     xdg_shell_manager: Option<&'static mut xdg_shell::Manager>,
     /// Manager for XDG shells v6.
@@ -113,6 +148,53 @@ pub struct Compositor {
     socket_name: String,
     /// Optional decoration manager extension.
     pub server_decoration_manager: Option<server_decoration::Manager>,
+    #[cfg(feature = "pointer_constraints")]
+    /// Optional pointer constraints extension.
+    pub pointer_constraints: Option<pointer_constraints::Manager>,
+    #[cfg(feature = "pointer_gestures")]
+    /// Optional pointer gestures extension.
+    pub pointer_gestures: Option<pointer_gestures::Manager>,
+    #[cfg(feature = "relative_pointer")]
+    /// Optional relative pointer extension.
+    pub relative_pointer_manager: Option<relative_pointer::Manager>,
+    #[cfg(feature = "virtual_keyboard")]
+    /// Optional virtual keyboard extension.
+    pub virtual_keyboard_manager: Option<virtual_keyboard::Manager>,
+    #[cfg(feature = "virtual_pointer")]
+    /// Optional virtual pointer extension.
+    pub virtual_pointer_manager: Option<virtual_pointer::Manager>,
+    #[cfg(feature = "input_method")]
+    /// Optional text input extension.
+    pub text_input_manager: Option<text_input::Manager>,
+    #[cfg(feature = "input_method")]
+    /// Optional input method extension.
+    pub input_method_manager: Option<input_method::Manager>,
+    #[cfg(feature = "keyboard_shortcuts_inhibit")]
+    /// Optional keyboard shortcuts inhibit extension.
+    pub keyboard_shortcuts_inhibit_manager: Option<keyboard_shortcuts_inhibit::Manager>,
+    #[cfg(feature = "idle_inhibit")]
+    /// Optional idle inhibit extension.
+    pub idle_inhibit_manager: Option<idle_inhibit::Manager>,
+    #[cfg(feature = "output_management")]
+    /// Optional output management extension.
+    pub output_management: Option<output_management::Manager>,
+    #[cfg(feature = "fractional_scale")]
+    /// Optional fractional scale extension.
+    pub fractional_scale_manager: Option<fractional_scale::Manager>,
+    #[cfg(feature = "viewporter")]
+    /// Optional viewporter extension.
+    pub viewporter: Option<viewporter::Manager>,
+    #[cfg(feature = "presentation_time")]
+    /// Optional presentation time extension.
+    pub presentation: Option<presentation::Manager>,
+    #[cfg(feature = "tearing_control")]
+    /// Optional tearing control extension.
+    pub tearing_control_manager: Option<tearing_control::Manager>,
+    #[cfg(feature = "output_power_management")]
+    /// Optional output power management extension.
+    pub output_power_management: Option<output_power_management::Manager>,
+    /// Optional idle tracking and notification extensions.
+    pub idle_manager: Option<idle::Manager>,
     /// The renderer used to draw things to the screen.
     pub renderer: Option<GenericRenderer>,
     /// XWayland server, only Some if it is enabled
@@ -134,12 +216,45 @@ pub struct Builder {
     compositor_event_builder: Option<EventBuilder>,
     input_manager_builder: Option<input::manager::Builder>,
     output_manager_builder: Option<output::manager::Builder>,
+    session_handler: Option<Box<backend::session::Handler>>,
+    drm_handler: Option<Box<backend::drm::Handler>>,
     xdg_shell_manager_builder: Option<xdg_shell::manager::Builder>,
     xdg_v6_shell_manager_builder: Option<xdg_shell_v6::manager::Builder>,
     wl_shm: bool,
     gles2: bool,
     render_setup_function: Option<UnsafeRenderSetupFunction>,
     server_decoration_manager: bool,
+    #[cfg(feature = "pointer_constraints")]
+    pointer_constraints: Option<pointer_constraints::Builder>,
+    #[cfg(feature = "pointer_gestures")]
+    pointer_gestures: bool,
+    #[cfg(feature = "relative_pointer")]
+    relative_pointer_manager: bool,
+    #[cfg(feature = "virtual_keyboard")]
+    virtual_keyboard_manager: Option<virtual_keyboard::Builder>,
+    #[cfg(feature = "virtual_pointer")]
+    virtual_pointer_manager: Option<virtual_pointer::Builder>,
+    #[cfg(feature = "input_method")]
+    text_input_manager: Option<text_input::Builder>,
+    #[cfg(feature = "input_method")]
+    input_method_manager: Option<input_method::Builder>,
+    #[cfg(feature = "keyboard_shortcuts_inhibit")]
+    keyboard_shortcuts_inhibit_manager: Option<keyboard_shortcuts_inhibit::Builder>,
+    #[cfg(feature = "idle_inhibit")]
+    idle_inhibit_manager: Option<idle_inhibit::Builder>,
+    #[cfg(feature = "output_management")]
+    output_management: Option<output_management::Builder>,
+    #[cfg(feature = "fractional_scale")]
+    fractional_scale_manager: bool,
+    #[cfg(feature = "viewporter")]
+    viewporter: bool,
+    #[cfg(feature = "presentation_time")]
+    presentation: bool,
+    #[cfg(feature = "tearing_control")]
+    tearing_control_manager: bool,
+    #[cfg(feature = "output_power_management")]
+    output_power_management: Option<output_power_management::Builder>,
+    idle_manager: Option<idle::Builder>,
     wayland_remote: Option<String>,
     x11_display: Option<String>,
     data_device_manager: bool,
@@ -162,8 +277,10 @@ impl Builder {
     /// constraint is broken. This applies across threads.
     pub fn new() -> Self {
         unsafe {
-            assert_eq!(BUILDER_ACTIVE.compare_and_swap(false, true, Ordering::AcqRel),
-                       false,
+            let previous =
+                BUILDER_ACTIVE.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed);
+            assert_eq!(previous,
+                       Ok(false),
                        "A compositor builder already exists or has already been built");
         }
         Builder::default()
@@ -187,6 +304,31 @@ impl Builder {
         self
     }
 
+    /// Set the handler for the session becoming active or inactive, e.g. when
+    /// switching virtual terminals.
+    ///
+    /// Whether or not this is called, all outputs are damaged when the session
+    /// becomes active again. It has no effect on backends without a session,
+    /// such as the headless one.
+    pub fn session_handler<H>(mut self, session_handler: H) -> Self
+        where H: backend::session::Handler + 'static
+    {
+        self.session_handler = Some(Box::new(session_handler));
+        self
+    }
+
+    /// Set the handler for GPUs being added or removed, and for changes on
+    /// their connectors.
+    ///
+    /// If this function is not called then GPU hotplug isn't listened for.
+    /// It has no effect on backends without a session.
+    pub fn drm_handler<H>(mut self, drm_handler: H) -> Self
+        where H: backend::drm::Handler + 'static
+    {
+        self.drm_handler = Some(Box::new(drm_handler));
+        self
+    }
+
     /// Set callbacks for managing XDG shell resources.
     pub fn xdg_shell_manager(mut self,
                              xdg_shell_manager_builder: xdg_shell::manager::Builder)
@@ -233,6 +375,162 @@ impl Builder {
         self
     }
 
+    #[cfg(feature = "pointer_constraints")]
+    /// Set callbacks for the pointer constraints protocol extension.
+    ///
+    /// If this function is not called then clients can't lock or confine
+    /// the pointer.
+    pub fn pointer_constraints(mut self, pointer_constraints: pointer_constraints::Builder) -> Self {
+        self.pointer_constraints = Some(pointer_constraints);
+        self
+    }
+
+    #[cfg(feature = "pointer_gestures")]
+    /// Decide whether or not to enable the pointer gestures protocol extension.
+    ///
+    /// This is used to forward touchpad swipe and pinch gestures to clients.
+    pub fn pointer_gestures(mut self, pointer_gestures: bool) -> Self {
+        self.pointer_gestures = pointer_gestures;
+        self
+    }
+
+    #[cfg(feature = "relative_pointer")]
+    /// Decide whether or not to enable the relative pointer protocol extension.
+    pub fn relative_pointer_manager(mut self, relative_pointer_manager: bool) -> Self {
+        self.relative_pointer_manager = relative_pointer_manager;
+        self
+    }
+
+    #[cfg(feature = "virtual_keyboard")]
+    /// Set callbacks for the virtual keyboard protocol extension.
+    ///
+    /// If this function is not called then clients can't create virtual
+    /// keyboards.
+    pub fn virtual_keyboard_manager(mut self,
+                                    virtual_keyboard_manager: virtual_keyboard::Builder)
+                                    -> Self {
+        self.virtual_keyboard_manager = Some(virtual_keyboard_manager);
+        self
+    }
+
+    #[cfg(feature = "virtual_pointer")]
+    /// Set callbacks for the virtual pointer protocol extension.
+    ///
+    /// If this function is not called then clients can't create virtual
+    /// pointers.
+    pub fn virtual_pointer_manager(mut self,
+                                   virtual_pointer_manager: virtual_pointer::Builder)
+                                   -> Self {
+        self.virtual_pointer_manager = Some(virtual_pointer_manager);
+        self
+    }
+
+    #[cfg(feature = "input_method")]
+    /// Set callbacks for the text input protocol extension.
+    ///
+    /// This should be used together with `input_method_manager`.
+    pub fn text_input_manager(mut self, text_input_manager: text_input::Builder) -> Self {
+        self.text_input_manager = Some(text_input_manager);
+        self
+    }
+
+    #[cfg(feature = "input_method")]
+    /// Set callbacks for the input method protocol extension.
+    ///
+    /// This should be used together with `text_input_manager`.
+    pub fn input_method_manager(mut self, input_method_manager: input_method::Builder) -> Self {
+        self.input_method_manager = Some(input_method_manager);
+        self
+    }
+
+    #[cfg(feature = "keyboard_shortcuts_inhibit")]
+    /// Set callbacks for the keyboard shortcuts inhibit protocol extension.
+    pub fn keyboard_shortcuts_inhibit_manager(mut self,
+                                              manager: keyboard_shortcuts_inhibit::Builder)
+                                              -> Self {
+        self.keyboard_shortcuts_inhibit_manager = Some(manager);
+        self
+    }
+
+    #[cfg(feature = "idle_inhibit")]
+    /// Set callbacks for the idle inhibit protocol extension.
+    pub fn idle_inhibit_manager(mut self, idle_inhibit_manager: idle_inhibit::Builder) -> Self {
+        self.idle_inhibit_manager = Some(idle_inhibit_manager);
+        self
+    }
+
+    #[cfg(feature = "output_management")]
+    /// Set callbacks for the output management protocol extension.
+    ///
+    /// If this function is not called then tools such as wlr-randr can't query
+    /// or configure outputs. See `extensions::output_management` for what
+    /// happens when the callbacks are not set.
+    pub fn output_management(mut self, output_management: output_management::Builder) -> Self {
+        self.output_management = Some(output_management);
+        self
+    }
+
+    #[cfg(feature = "fractional_scale")]
+    /// Decide whether or not to enable the fractional scale protocol extension.
+    ///
+    /// Clients that support it render at the exact scale of fractional outputs,
+    /// once it's sent with `fractional_scale::Manager::send_scale`.
+    pub fn fractional_scale_manager(mut self, fractional_scale_manager: bool) -> Self {
+        self.fractional_scale_manager = fractional_scale_manager;
+        self
+    }
+
+    #[cfg(feature = "viewporter")]
+    /// Decide whether or not to enable the viewporter protocol extension.
+    ///
+    /// Surfaces that use it must be rendered with `Surface::buffer_source_box`,
+    /// otherwise cropped buffers are shown whole.
+    pub fn viewporter(mut self, viewporter: bool) -> Self {
+        self.viewporter = viewporter;
+        self
+    }
+
+    #[cfg(feature = "presentation_time")]
+    /// Decide whether or not to enable the presentation time protocol extension.
+    ///
+    /// Feedback is only sent to surfaces recorded with
+    /// `presentation::Manager::surface_rendered`.
+    pub fn presentation(mut self, presentation: bool) -> Self {
+        self.presentation = presentation;
+        self
+    }
+
+    #[cfg(feature = "tearing_control")]
+    /// Decide whether or not to enable the tearing control protocol extension.
+    ///
+    /// Clients use it to ask for their surfaces to be presented without waiting for
+    /// the vertical retrace, see `tearing_control::Manager::presentation_hint`.
+    pub fn tearing_control_manager(mut self, tearing_control_manager: bool) -> Self {
+        self.tearing_control_manager = tearing_control_manager;
+        self
+    }
+
+    #[cfg(feature = "output_power_management")]
+    /// Set callbacks for the output power management protocol extension.
+    ///
+    /// If this function is not called then clients can't turn outputs on or off.
+    pub fn output_power_management(mut self,
+                                    output_power_management: output_power_management::Builder)
+                                    -> Self {
+        self.output_power_management = Some(output_power_management);
+        self
+    }
+
+    /// Set callbacks for idle timeouts and enable the idle notification protocol
+    /// extensions.
+    ///
+    /// If this function is not called then activity isn't tracked and clients
+    /// aren't told when the session is idle.
+    pub fn idle_manager(mut self, idle_manager: idle::Builder) -> Self {
+        self.idle_manager = Some(idle_manager);
+        self
+    }
+
     /// Set callbacks for managing XDG shell v6 resources.
     ///
     /// If this function is not called then the xwayland server does not run.
@@ -267,9 +565,43 @@ impl Builder {
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
             let event_loop =
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::Multi(backend::Multi::auto_create(display as *mut _,
-                                                                     self.render_setup_function));
-            self.finish_build(data, display, event_loop, backend)
+            let backend = backend::Multi::auto_create(display as *mut _,
+                                                      self.render_setup_function)
+                .unwrap_or_else(|err| panic!("{}", err));
+            self.finish_build(data, display, event_loop, Backend::Multi(backend))
+        }
+    }
+
+    /// Makes a new compositor on the backend chosen by the selection, see
+    /// `backend::Selection`.
+    ///
+    /// The Wayland remote and X11 display set on this builder are used unless
+    /// the selection sets its own.
+    ///
+    /// Returns the compositor along with which backend was chosen and why, or
+    /// why no backend could be used.
+    pub fn build_selected<D>(mut self,
+                             data: D,
+                             selection: backend::Selection)
+                             -> Result<(Compositor, backend::Choice), backend::SelectionError>
+        where D: Any + 'static
+    {
+        unsafe {
+            let display =
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
+            let event_loop =
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
+            let selection = selection.default_remotes(self.wayland_remote.take(),
+                                                      self.x11_display.take());
+            match selection.create(display as *mut _, self.render_setup_function) {
+                Ok((backend, choice)) => {
+                    Ok((self.finish_build(data, display, event_loop, backend), choice))
+                }
+                Err(err) => {
+                    ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_destroy, display);
+                    Err(err)
+                }
+            }
         }
     }
 
@@ -296,10 +628,11 @@ impl Builder {
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
             let event_loop =
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::X11(backend::X11::new(display as *mut _,
-                                                       self.x11_display.take(),
-                                                       self.render_setup_function));
-            self.finish_build(data, display, event_loop, backend)
+            let backend = backend::X11::new(display as *mut _,
+                                            self.x11_display.take(),
+                                            self.render_setup_function)
+                .unwrap_or_else(|err| panic!("{}", err));
+            self.finish_build(data, display, event_loop, Backend::X11(backend))
         }
     }
 
@@ -314,10 +647,11 @@ impl Builder {
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
             let event_loop =
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::Wayland(backend::Wayland::new(display as *mut _,
-                                                               self.wayland_remote.take(),
-                                                               self.render_setup_function));
-            self.finish_build(data, display, event_loop, backend)
+            let backend = backend::Wayland::new(display as *mut _,
+                                                self.wayland_remote.take(),
+                                                self.render_setup_function)
+                .unwrap_or_else(|err| panic!("{}", err));
+            self.finish_build(data, display, event_loop, Backend::Wayland(backend))
         }
     }
 
@@ -334,12 +668,13 @@ impl Builder {
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
             let event_loop =
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::DRM(backend::Drm::new(display as *mut _,
-                                                       session,
-                                                       gpu_fd,
-                                                       parent,
-                                                       self.render_setup_function));
-            self.finish_build(data, display, event_loop, backend)
+            let backend = backend::Drm::new(display as *mut _,
+                                            session,
+                                            gpu_fd,
+                                            parent,
+                                            self.render_setup_function)
+                .unwrap_or_else(|err| panic!("{}", err));
+            self.finish_build(data, display, event_loop, Backend::DRM(backend))
         }
     }
 
@@ -351,9 +686,9 @@ impl Builder {
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_create,) as *mut wl_display;
             let event_loop =
                 ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_get_event_loop, display);
-            let backend = Backend::Headless(backend::Headless::new(display as *mut _,
-                                                                 self.render_setup_function));
-            self.finish_build(data, display, event_loop, backend)
+            let backend = backend::Headless::new(display as *mut _, self.render_setup_function)
+                .unwrap_or_else(|err| panic!("{}", err));
+            self.finish_build(data, display, event_loop, Backend::Headless(backend))
         }
     }
 
@@ -393,6 +728,82 @@ impl Builder {
         } else {
             None
         };
+        #[cfg(feature = "pointer_constraints")]
+        let pointer_constraints = self.pointer_constraints.take().and_then(|builder| {
+            pointer_constraints::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "pointer_gestures")]
+        let pointer_gestures = if self.pointer_gestures {
+            pointer_gestures::Manager::new(display as _)
+        } else {
+            None
+        };
+        #[cfg(feature = "relative_pointer")]
+        let relative_pointer_manager = if self.relative_pointer_manager {
+            relative_pointer::Manager::new(display as _)
+        } else {
+            None
+        };
+        #[cfg(feature = "virtual_keyboard")]
+        let virtual_keyboard_manager = self.virtual_keyboard_manager.take().and_then(|builder| {
+            virtual_keyboard::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "virtual_pointer")]
+        let virtual_pointer_manager = self.virtual_pointer_manager.take().and_then(|builder| {
+            virtual_pointer::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "input_method")]
+        let text_input_manager = self.text_input_manager.take().and_then(|builder| {
+            text_input::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "input_method")]
+        let input_method_manager = self.input_method_manager.take().and_then(|builder| {
+            input_method::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "keyboard_shortcuts_inhibit")]
+        let keyboard_shortcuts_inhibit_manager =
+            self.keyboard_shortcuts_inhibit_manager.take().and_then(|builder| {
+                keyboard_shortcuts_inhibit::Manager::new(display as _, builder)
+            });
+        #[cfg(feature = "idle_inhibit")]
+        let idle_inhibit_manager = self.idle_inhibit_manager.take().and_then(|builder| {
+            idle_inhibit::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "output_management")]
+        let output_management = self.output_management.take().and_then(|builder| {
+            output_management::Manager::new(display as _, builder)
+        });
+        #[cfg(feature = "fractional_scale")]
+        let fractional_scale_manager = if self.fractional_scale_manager {
+            fractional_scale::Manager::new(display as _)
+        } else {
+            None
+        };
+        #[cfg(feature = "viewporter")]
+        let viewporter = if self.viewporter {
+            viewporter::Manager::new(display as _)
+        } else {
+            None
+        };
+        #[cfg(feature = "presentation_time")]
+        let presentation = if self.presentation {
+            presentation::Manager::new(display as _, backend.as_ptr())
+        } else {
+            None
+        };
+        #[cfg(feature = "tearing_control")]
+        let tearing_control_manager = if self.tearing_control_manager {
+            tearing_control::Manager::new(display as _)
+        } else {
+            None
+        };
+        #[cfg(feature = "output_power_management")]
+        let output_power_management = self.output_power_management.take().and_then(|builder| {
+            output_power_management::Manager::new(display as _, builder)
+        });
+        let idle_manager = self.idle_manager.take().and_then(|builder| {
+            idle::Manager::new(display as _, builder)
+        });
 
         // Set up compositor event callbacks, if the user provided it.
         let compositor_handler = self.compositor_event_builder.take()
@@ -427,6 +838,26 @@ impl Builder {
             output_manager
         });
 
+        // Set up the session listener, if the backend has a session.
+        let session_ptr = wlr_backend_get_session(backend.as_ptr());
+        let session_listener = if session_ptr.is_null() {
+            None
+        } else {
+            let handler = self.session_handler.take().unwrap_or_else(|| Box::new(()));
+            let mut session_listener = backend::session::SessionListener::new((session_ptr,
+                                                                              handler));
+            wl_signal_add(&mut (*session_ptr).session_signal as *mut _ as _,
+                          session_listener.active_listener() as _);
+            Some(session_listener)
+        };
+        let drm_monitor = match self.drm_handler.take() {
+            Some(handler) if !session_ptr.is_null() => {
+                let session = Session::from_ptr(session_ptr);
+                backend::drm::Monitor::new(event_loop, &session, handler)
+            }
+            _ => None
+        };
+
         // Set up the xdg_shell handler and associated Wayland global,
         // if user provided a manager for it.
         let mut xdg_shell_global = ptr::null_mut();
@@ -478,6 +909,8 @@ impl Builder {
                                       socket_name,
                                       input_manager,
                                       output_manager,
+                                      session_listener,
+                                      drm_monitor,
                                       xdg_shell_manager,
                                       xdg_shell_global,
                                       xdg_v6_shell_manager,
@@ -489,6 +922,37 @@ impl Builder {
                                       event_loop,
                                       wl_shm_fd,
                                       server_decoration_manager,
+                                      #[cfg(feature = "pointer_constraints")]
+                                      pointer_constraints,
+                                      #[cfg(feature = "pointer_gestures")]
+                                      pointer_gestures,
+                                      #[cfg(feature = "relative_pointer")]
+                                      relative_pointer_manager,
+                                      #[cfg(feature = "virtual_keyboard")]
+                                      virtual_keyboard_manager,
+                                      #[cfg(feature = "virtual_pointer")]
+                                      virtual_pointer_manager,
+                                      #[cfg(feature = "input_method")]
+                                      text_input_manager,
+                                      #[cfg(feature = "input_method")]
+                                      input_method_manager,
+                                      #[cfg(feature = "keyboard_shortcuts_inhibit")]
+                                      keyboard_shortcuts_inhibit_manager,
+                                      #[cfg(feature = "idle_inhibit")]
+                                      idle_inhibit_manager,
+                                      #[cfg(feature = "output_management")]
+                                      output_management,
+                                      #[cfg(feature = "fractional_scale")]
+                                      fractional_scale_manager,
+                                      #[cfg(feature = "viewporter")]
+                                      viewporter,
+                                      #[cfg(feature = "presentation_time")]
+                                      presentation,
+                                      #[cfg(feature = "tearing_control")]
+                                      tearing_control_manager,
+                                      #[cfg(feature = "output_power_management")]
+                                      output_power_management,
+                                      idle_manager,
                                       renderer,
                                       xwayland,
                                       user_terminate,
@@ -507,8 +971,10 @@ impl Drop for Builder {
             // NOTE This will only happen if dropped outside of `finish_build`,
             // which mem::forgets(self) in order to not be able to use a builder
             // while the compositor is running.
-            assert_eq!(BUILDER_ACTIVE.compare_and_swap(true, false, Ordering::AcqRel),
-                       true,
+            let previous =
+                BUILDER_ACTIVE.compare_exchange(true, false, Ordering::Release, Ordering::Relaxed);
+            assert_eq!(previous,
+                       Ok(true),
                        "Builder was in improper state");
         }
     }
@@ -559,6 +1025,9 @@ impl Compositor {
                 panic!("A compositor is already running!")
             }
             COMPOSITOR_PTR = compositor.get();
+            // NOTE Declared after `compositor` so the pointer is cleared
+            // before the compositor is dropped, also when unwinding.
+            let _running = RunningGuard;
             wlr_log!(WLR_INFO, "Starting compositor");
             if !wlr_backend_start((*compositor.get()).backend.as_ptr()) {
                 wlr_backend_destroy((*compositor.get()).backend.as_ptr());
@@ -587,6 +1056,11 @@ impl Compositor {
         }
     }
 
+    /// Get the name of the Wayland socket clients connect to.
+    pub(crate) fn socket_name(&self) -> &str {
+        &self.socket_name
+    }
+
     /// Get a reference to the currently running backend.
     pub fn backend(&self) -> &Backend {
         &self.backend
@@ -612,15 +1086,32 @@ impl Compositor {
     }
 }
 
+/// Clears `COMPOSITOR_PTR` when the compositor stops running.
+struct RunningGuard;
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        unsafe { COMPOSITOR_PTR = 0 as *mut _ }
+    }
+}
+
 impl Drop for Compositor {
     fn drop(&mut self) {
         unsafe {
-            assert_eq!(BUILDER_ACTIVE.compare_and_swap(true, false, Ordering::AcqRel),
-                       true,
+            let previous =
+                BUILDER_ACTIVE.compare_exchange(true, false, Ordering::Release, Ordering::Relaxed);
+            assert_eq!(previous,
+                       Ok(true),
                        "Builder was in improper state");
             ffi_dispatch!(WAYLAND_SERVER_HANDLE,
                           wl_display_destroy_clients,
                           self.display);
+            self.drm_monitor.take();
+            if let Some(ref mut session_listener) = self.session_listener {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*session_listener.active_listener()).link as *mut _ as _);
+            }
             wlr_compositor_destroy(self.compositor)
         }
     }
@@ -4,6 +4,10 @@ use libc::c_double;
 use wlroots_sys::{wlr_event_pointer_axis, wlr_event_pointer_button, wlr_event_pointer_motion,
                   wlr_event_pointer_motion_absolute, wlr_axis_orientation,
                   wlr_axis_source, wlr_button_state};
+#[cfg(feature = "pointer_gestures")]
+use wlroots_sys::{wlr_event_pointer_swipe_begin, wlr_event_pointer_swipe_update,
+                  wlr_event_pointer_swipe_end, wlr_event_pointer_pinch_begin,
+                  wlr_event_pointer_pinch_update, wlr_event_pointer_pinch_end};
 
 use input;
 
@@ -52,6 +56,58 @@ pub struct AbsoluteMotion {
     device: input::Device
 }
 
+/// Event that triggers when a multi-finger swipe gesture starts on a
+/// touchpad.
+#[cfg(feature = "pointer_gestures")]
+#[derive(Debug)]
+pub struct SwipeBegin {
+    event: *mut wlr_event_pointer_swipe_begin,
+    device: input::Device
+}
+
+/// Event that triggers when the fingers of a swipe gesture move.
+#[cfg(feature = "pointer_gestures")]
+#[derive(Debug)]
+pub struct SwipeUpdate {
+    event: *mut wlr_event_pointer_swipe_update,
+    device: input::Device
+}
+
+/// Event that triggers when a swipe gesture ends, either because the
+/// fingers were lifted or because the gesture was cancelled.
+#[cfg(feature = "pointer_gestures")]
+#[derive(Debug)]
+pub struct SwipeEnd {
+    event: *mut wlr_event_pointer_swipe_end,
+    device: input::Device
+}
+
+/// Event that triggers when a multi-finger pinch (or rotate) gesture starts
+/// on a touchpad.
+#[cfg(feature = "pointer_gestures")]
+#[derive(Debug)]
+pub struct PinchBegin {
+    event: *mut wlr_event_pointer_pinch_begin,
+    device: input::Device
+}
+
+/// Event that triggers when the fingers of a pinch gesture move.
+#[cfg(feature = "pointer_gestures")]
+#[derive(Debug)]
+pub struct PinchUpdate {
+    event: *mut wlr_event_pointer_pinch_update,
+    device: input::Device
+}
+
+/// Event that triggers when a pinch gesture ends, either because the
+/// fingers were lifted or because the gesture was cancelled.
+#[cfg(feature = "pointer_gestures")]
+#[derive(Debug)]
+pub struct PinchEnd {
+    event: *mut wlr_event_pointer_pinch_end,
+    device: input::Device
+}
+
 impl Button {
     /// Constructs a `Button` from the raw event pointer.
     pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_button) -> Self {
@@ -144,6 +200,15 @@ impl Motion {
     pub fn delta(&self) -> (f64, f64) {
         unsafe { ((*self.event).delta_x, (*self.event).delta_y) }
     }
+
+    /// Get the change from the last positional value, before any pointer
+    /// acceleration was applied.
+    ///
+    /// Returned in (x, y) form.
+    #[cfg(feature = "relative_pointer")]
+    pub fn unaccelerated_delta(&self) -> (f64, f64) {
+        unsafe { ((*self.event).unaccel_dx, (*self.event).unaccel_dy) }
+    }
 }
 
 impl AbsoluteMotion {
@@ -168,3 +233,174 @@ impl AbsoluteMotion {
         &self.device
     }
 }
+
+#[cfg(feature = "pointer_gestures")]
+impl SwipeBegin {
+    /// Constructs a `SwipeBegin` from a raw event pointer.
+    pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_swipe_begin) -> Self {
+        SwipeBegin { device: input::Device::from_ptr((*event).device),
+                     event }
+    }
+
+    /// Get the device this event refers to.
+    pub fn device(&self) -> &input::Device {
+        &self.device
+    }
+
+    /// Get the timestamp of this event.
+    pub fn time_msec(&self) -> u32 {
+        unsafe { (*self.event).time_msec }
+    }
+
+    /// Get the number of fingers used in the gesture.
+    pub fn fingers(&self) -> u32 {
+        unsafe { (*self.event).fingers }
+    }
+}
+
+#[cfg(feature = "pointer_gestures")]
+impl SwipeUpdate {
+    /// Constructs a `SwipeUpdate` from a raw event pointer.
+    pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_swipe_update) -> Self {
+        SwipeUpdate { device: input::Device::from_ptr((*event).device),
+                      event }
+    }
+
+    /// Get the device this event refers to.
+    pub fn device(&self) -> &input::Device {
+        &self.device
+    }
+
+    /// Get the timestamp of this event.
+    pub fn time_msec(&self) -> u32 {
+        unsafe { (*self.event).time_msec }
+    }
+
+    /// Get the number of fingers used in the gesture.
+    pub fn fingers(&self) -> u32 {
+        unsafe { (*self.event).fingers }
+    }
+
+    /// Get the change of the center of the fingers since the last update.
+    ///
+    /// Returned in (x, y) form.
+    pub fn delta(&self) -> (f64, f64) {
+        unsafe { ((*self.event).dx, (*self.event).dy) }
+    }
+}
+
+#[cfg(feature = "pointer_gestures")]
+impl SwipeEnd {
+    /// Constructs a `SwipeEnd` from a raw event pointer.
+    pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_swipe_end) -> Self {
+        SwipeEnd { device: input::Device::from_ptr((*event).device),
+                   event }
+    }
+
+    /// Get the device this event refers to.
+    pub fn device(&self) -> &input::Device {
+        &self.device
+    }
+
+    /// Get the timestamp of this event.
+    pub fn time_msec(&self) -> u32 {
+        unsafe { (*self.event).time_msec }
+    }
+
+    /// Whether the gesture was cancelled instead of completed normally.
+    pub fn cancelled(&self) -> bool {
+        unsafe { (*self.event).cancelled }
+    }
+}
+
+#[cfg(feature = "pointer_gestures")]
+impl PinchBegin {
+    /// Constructs a `PinchBegin` from a raw event pointer.
+    pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_pinch_begin) -> Self {
+        PinchBegin { device: input::Device::from_ptr((*event).device),
+                     event }
+    }
+
+    /// Get the device this event refers to.
+    pub fn device(&self) -> &input::Device {
+        &self.device
+    }
+
+    /// Get the timestamp of this event.
+    pub fn time_msec(&self) -> u32 {
+        unsafe { (*self.event).time_msec }
+    }
+
+    /// Get the number of fingers used in the gesture.
+    pub fn fingers(&self) -> u32 {
+        unsafe { (*self.event).fingers }
+    }
+}
+
+#[cfg(feature = "pointer_gestures")]
+impl PinchUpdate {
+    /// Constructs a `PinchUpdate` from a raw event pointer.
+    pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_pinch_update) -> Self {
+        PinchUpdate { device: input::Device::from_ptr((*event).device),
+                      event }
+    }
+
+    /// Get the device this event refers to.
+    pub fn device(&self) -> &input::Device {
+        &self.device
+    }
+
+    /// Get the timestamp of this event.
+    pub fn time_msec(&self) -> u32 {
+        unsafe { (*self.event).time_msec }
+    }
+
+    /// Get the number of fingers used in the gesture.
+    pub fn fingers(&self) -> u32 {
+        unsafe { (*self.event).fingers }
+    }
+
+    /// Get the change of the center of the fingers since the last update.
+    ///
+    /// Returned in (x, y) form.
+    pub fn delta(&self) -> (f64, f64) {
+        unsafe { ((*self.event).dx, (*self.event).dy) }
+    }
+
+    /// Get the absolute scale compared to the start of the gesture.
+    ///
+    /// A value of 1.0 means the fingers are as far apart as when the
+    /// gesture started.
+    pub fn scale(&self) -> f64 {
+        unsafe { (*self.event).scale }
+    }
+
+    /// Get the relative angle in degrees, clockwise, since the last update.
+    pub fn rotation(&self) -> f64 {
+        unsafe { (*self.event).rotation }
+    }
+}
+
+#[cfg(feature = "pointer_gestures")]
+impl PinchEnd {
+    /// Constructs a `PinchEnd` from a raw event pointer.
+    pub(crate) unsafe fn from_ptr(event: *mut wlr_event_pointer_pinch_end) -> Self {
+        PinchEnd { device: input::Device::from_ptr((*event).device),
+                   event }
+    }
+
+    /// Get the device this event refers to.
+    pub fn device(&self) -> &input::Device {
+        &self.device
+    }
+
+    /// Get the timestamp of this event.
+    pub fn time_msec(&self) -> u32 {
+        unsafe { (*self.event).time_msec }
+    }
+
+    /// Whether the gesture was cancelled instead of completed normally.
+    pub fn cancelled(&self) -> bool {
+        unsafe { (*self.event).cancelled }
+    }
+}
@@ -0,0 +1,53 @@
+//! Support for the fractional scale protocol (`wp_fractional_scale_v1`).
+//!
+//! With an integer buffer scale a client on an output at scale 1.5 has to
+//! render at scale 2 and let the compositor scale its buffers down. Clients
+//! that support this protocol are told the exact scale instead, so they can
+//! render buffers that map 1:1 to output pixels. They use `wp_viewporter` to
+//! give their surface its size in layout coordinates, so
+//! `compositor::Builder::viewporter` should be enabled as well.
+//!
+//! The scale is sent with
+//! [`Manager::send_scale`](./struct.Manager.html#method.send_scale), usually
+//! whenever a surface enters an output or the scale of its output changes.
+
+use wlroots_sys::{wl_display, wlr_fractional_scale_manager_v1,
+                  wlr_fractional_scale_manager_v1_create, wlr_fractional_scale_v1_notify_scale};
+
+use {output::Output, surface::Surface};
+
+/// The version of the protocol that's advertised.
+const FRACTIONAL_SCALE_VERSION: u32 = 1;
+
+/// The fractional scale global.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_fractional_scale_manager_v1
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display) -> Option<Self> {
+        let manager = wlr_fractional_scale_manager_v1_create(display, FRACTIONAL_SCALE_VERSION);
+        if manager.is_null() {
+            None
+        } else {
+            Some(Manager { manager })
+        }
+    }
+
+    /// Tell the client of the surface the scale it should render at.
+    ///
+    /// Nothing is sent if the client didn't ask for the fractional scale of
+    /// the surface, or if the scale didn't change since it was last sent.
+    pub fn send_scale(&self, surface: &Surface, scale: f64) {
+        unsafe { wlr_fractional_scale_v1_notify_scale(surface.as_ptr(), scale) }
+    }
+
+    /// Tell the client of the surface to render at the scale of the output.
+    ///
+    /// If a surface spans several outputs, pass the one with the highest scale.
+    pub fn send_output_scale(&self, surface: &Surface, output: &Output) {
+        self.send_scale(surface, output.scale() as f64)
+    }
+}
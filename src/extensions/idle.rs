@@ -0,0 +1,339 @@
+//! Idle tracking, with support for the idle notification protocols
+//! (`org_kde_kwin_idle`, and `ext_idle_notify_v1` with the `idle_notify`
+//! feature).
+//!
+//! Activity is reported automatically when input is sent to clients through
+//! a `Seat` (`Seat::keyboard_notify_key`, `Seat::pointer_notify_motion`, ...),
+//! and can be reported with
+//! [`Manager::notify_activity`](./struct.Manager.html#method.notify_activity)
+//! for anything else.
+//!
+//! Clients such as swayidle are told when the session has been idle for the
+//! time they asked for. The compositor can register its own timeouts with
+//! [`Manager::add_timeout`](./struct.Manager.html#method.add_timeout); the
+//! `timeout_expired` callback is called when one expires, and
+//! `activity_resumed` when there's activity after that.
+//!
+//! While idle is inhibited, e.g. because `idle_inhibit::Manager::is_inhibited`
+//! says so, no timeout expires.
+
+use std::{panic, ptr, time::Duration};
+
+use libc::{self, c_int};
+use wayland_sys::server::{wl_event_loop, wl_event_source, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_idle, wlr_idle_create, wlr_idle_notify_activity,
+                  wlr_idle_set_enabled, wlr_seat};
+#[cfg(feature = "idle_notify")]
+use wlroots_sys::{wlr_idle_notifier_v1, wlr_idle_notifier_v1_create,
+                  wlr_idle_notifier_v1_notify_activity, wlr_idle_notifier_v1_set_inhibited};
+
+use {compositor, seat::Seat, utils};
+
+/// Identifies a timeout registered with `Manager::add_timeout`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Timeout(u32);
+
+/// Callback that's triggered when there was no activity for the duration of
+/// a timeout.
+pub type TimeoutExpired = fn(compositor_handle: compositor::Handle, timeout: Timeout);
+
+/// Callback that's triggered on the first activity after a timeout expired.
+pub type ActivityResumed = fn(compositor_handle: compositor::Handle, timeout: Timeout);
+
+/// Builder for the idle callbacks, given to `compositor::Builder::idle_manager`.
+#[derive(Debug, Default)]
+pub struct Builder {
+    timeout_expired: Option<TimeoutExpired>,
+    activity_resumed: Option<ActivityResumed>
+}
+
+struct TimeoutState {
+    timeout: Timeout,
+    duration: Duration,
+    source: *mut wl_event_source,
+    expired: bool
+}
+
+struct IdleState {
+    event_loop: *mut wl_event_loop,
+    idle: *mut wlr_idle,
+    #[cfg(feature = "idle_notify")]
+    notifier: *mut wlr_idle_notifier_v1,
+    timeouts: Vec<Box<TimeoutState>>,
+    next_timeout: u32,
+    inhibited: bool,
+    /// The source that reports resumed activity, if it's been scheduled.
+    resumed_source: *mut wl_event_source,
+    timeout_expired: Option<TimeoutExpired>,
+    activity_resumed: Option<ActivityResumed>
+}
+
+/// The idle state, reached from the `Seat` when it sends input to clients.
+static mut IDLE: *mut IdleState = 0 as *mut _;
+
+/// The idle notification globals and the timeouts of the compositor.
+///
+/// Dropping this removes the timeouts.
+pub struct Manager {
+    state: *mut IdleState
+}
+
+impl Builder {
+    /// Uses the provided callback when a timeout expires.
+    pub fn timeout_expired(mut self, timeout_expired: TimeoutExpired) -> Self {
+        self.timeout_expired = Some(timeout_expired);
+        self
+    }
+
+    /// Uses the provided callback when there's activity after a timeout expired.
+    pub fn activity_resumed(mut self, activity_resumed: ActivityResumed) -> Self {
+        self.activity_resumed = Some(activity_resumed);
+        self
+    }
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let idle = wlr_idle_create(display);
+        if idle.is_null() {
+            return None
+        }
+        #[cfg(feature = "idle_notify")]
+        let notifier = wlr_idle_notifier_v1_create(display);
+        #[cfg(feature = "idle_notify")]
+        {
+            if notifier.is_null() {
+                return None
+            }
+        }
+        let event_loop = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_display_get_event_loop,
+                                       display as *mut _);
+        let state = Box::new(IdleState { event_loop,
+                                         idle,
+                                         #[cfg(feature = "idle_notify")]
+                                         notifier,
+                                         timeouts: Vec::new(),
+                                         next_timeout: 0,
+                                         inhibited: false,
+                                         resumed_source: ptr::null_mut(),
+                                         timeout_expired: builder.timeout_expired,
+                                         activity_resumed: builder.activity_resumed });
+        let state = Box::into_raw(state);
+        IDLE = state;
+        Some(Manager { state })
+    }
+
+    /// Register a timeout that expires when there was no activity for the
+    /// duration.
+    ///
+    /// Durations longer than `c_int::MAX` milliseconds (about 24.8 days) are
+    /// shortened to that.
+    ///
+    /// Returns `None` if the timer couldn't be created.
+    pub fn add_timeout(&mut self, duration: Duration) -> Option<Timeout> {
+        unsafe {
+            let state = &mut *self.state;
+            let timeout = Timeout(state.next_timeout);
+            state.next_timeout += 1;
+            let mut timeout_state = Box::new(TimeoutState { timeout,
+                                                            duration,
+                                                            source: ptr::null_mut(),
+                                                            expired: false });
+            let source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_event_loop_add_timer,
+                                       state.event_loop,
+                                       timer_notify,
+                                       &mut *timeout_state as *mut TimeoutState as *mut _);
+            if source.is_null() {
+                return None
+            }
+            timeout_state.source = source;
+            if !state.inhibited {
+                arm(&timeout_state);
+            }
+            state.timeouts.push(timeout_state);
+            Some(timeout)
+        }
+    }
+
+    /// Unregister a timeout. It won't expire or be resumed anymore.
+    pub fn remove_timeout(&mut self, timeout: Timeout) {
+        unsafe {
+            let state = &mut *self.state;
+            state.timeouts.retain(|timeout_state| {
+                if timeout_state.timeout != timeout {
+                    return true
+                }
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, timeout_state.source);
+                false
+            })
+        }
+    }
+
+    /// Whether the timeout expired and there was no activity since.
+    pub fn is_expired(&self, timeout: Timeout) -> bool {
+        unsafe {
+            (*self.state).timeouts.iter()
+                .any(|timeout_state| timeout_state.timeout == timeout && timeout_state.expired)
+        }
+    }
+
+    /// Report activity on the seat, restarting all timeouts.
+    ///
+    /// This is done automatically for input sent to clients through the seat.
+    pub fn notify_activity(&mut self, seat: &Seat) {
+        unsafe { notify_activity(self.state, seat.as_ptr()) }
+    }
+
+    /// Restart all timeouts, as if there was activity, without telling clients.
+    ///
+    /// e.g. to keep the screen on for a while after a notification is shown.
+    pub fn reset(&mut self) {
+        unsafe { reset(self.state) }
+    }
+
+    /// Inhibit or uninhibit idle, for the compositor and for clients.
+    ///
+    /// While idle is inhibited no timeout expires. Timeouts restart from the
+    /// beginning once it's uninhibited.
+    pub fn set_inhibited(&mut self, inhibited: bool) {
+        unsafe {
+            let state = &mut *self.state;
+            if state.inhibited == inhibited {
+                return
+            }
+            state.inhibited = inhibited;
+            #[cfg(feature = "idle_notify")]
+            wlr_idle_notifier_v1_set_inhibited(state.notifier, inhibited);
+            for timeout_state in &state.timeouts {
+                if inhibited {
+                    disarm(timeout_state)
+                } else {
+                    arm(timeout_state)
+                }
+            }
+        }
+    }
+
+    /// Whether idle is inhibited, see `set_inhibited`.
+    pub fn is_inhibited(&self) -> bool {
+        unsafe { (*self.state).inhibited }
+    }
+
+    /// Enable or disable idle notifications to the `org_kde_kwin_idle`
+    /// clients of the seat.
+    ///
+    /// `ext_idle_notify_v1` clients are controlled with `set_inhibited`.
+    pub fn set_seat_enabled(&mut self, seat: &Seat, enabled: bool) {
+        unsafe { wlr_idle_set_enabled((*self.state).idle, seat.as_ptr(), enabled) }
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            if IDLE == self.state {
+                IDLE = ptr::null_mut();
+            }
+            let state = Box::from_raw(self.state);
+            for timeout_state in &state.timeouts {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, timeout_state.source);
+            }
+            if !state.resumed_source.is_null() {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_source_remove, state.resumed_source);
+            }
+        }
+    }
+}
+
+/// Reports activity on the seat if an idle `Manager` exists.
+pub(crate) unsafe fn seat_activity(seat: *mut wlr_seat) {
+    if !IDLE.is_null() {
+        notify_activity(IDLE, seat)
+    }
+}
+
+unsafe fn notify_activity(state: *mut IdleState, seat: *mut wlr_seat) {
+    wlr_idle_notify_activity((*state).idle, seat);
+    #[cfg(feature = "idle_notify")]
+    wlr_idle_notifier_v1_notify_activity((*state).notifier, seat);
+    reset(state)
+}
+
+unsafe fn reset(state: *mut IdleState) {
+    let state = &mut *state;
+    let mut resumed = false;
+    for timeout_state in &mut state.timeouts {
+        resumed |= timeout_state.expired;
+        if !state.inhibited {
+            arm(timeout_state)
+        }
+    }
+    // NOTE This can be reached from `Seat` methods called in a callback, so
+    // the callbacks for resumed timeouts are called from the event loop
+    // instead of re-entering the compositor here.
+    if resumed && state.resumed_source.is_null() {
+        state.resumed_source = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                             wl_event_loop_add_idle,
+                                             state.event_loop,
+                                             resumed_notify,
+                                             state as *mut IdleState as *mut _);
+    }
+}
+
+unsafe fn arm(timeout_state: &TimeoutState) {
+    // A delay of 0 disarms the timer, so wait at least a millisecond. The
+    // delay is a c_int, so it can't be longer than about 24.8 days.
+    let duration = timeout_state.duration;
+    let delay = duration.as_secs()
+                        .saturating_mul(1000)
+                        .saturating_add(u64::from(duration.subsec_millis()))
+                        .max(1)
+                        .min(c_int::MAX as u64) as c_int;
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_event_source_timer_update,
+                  timeout_state.source,
+                  delay);
+}
+
+unsafe fn disarm(timeout_state: &TimeoutState) {
+    ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                  wl_event_source_timer_update,
+                  timeout_state.source,
+                  0);
+}
+
+unsafe extern "C" fn timer_notify(data: *mut libc::c_void) -> c_int {
+    let timeout_state = &mut *(data as *mut TimeoutState);
+    timeout_state.expired = true;
+    let timeout = timeout_state.timeout;
+    let callback = if IDLE.is_null() { None } else { (*IDLE).timeout_expired };
+    if let (Some(f), Some(compositor)) = (callback, compositor::handle()) {
+        utils::handle_unwind(panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            f(compositor, timeout)
+        })));
+    }
+    0
+}
+
+unsafe extern "C" fn resumed_notify(data: *mut libc::c_void) {
+    let state = &mut *(data as *mut IdleState);
+    state.resumed_source = ptr::null_mut();
+    let mut resumed = vec![];
+    for timeout_state in &mut state.timeouts {
+        if timeout_state.expired {
+            timeout_state.expired = false;
+            resumed.push(timeout_state.timeout);
+        }
+    }
+    let (callback, compositor) = match (state.activity_resumed, compositor::handle()) {
+        (Some(f), Some(compositor)) => (f, compositor),
+        _ => return
+    };
+    for timeout in resumed {
+        utils::handle_unwind(panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            callback(compositor.clone(), timeout)
+        })));
+    }
+}
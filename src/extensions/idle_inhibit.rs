@@ -0,0 +1,168 @@
+//! Support for the idle inhibit protocol (`zwp_idle_inhibit_v1`).
+//!
+//! Clients such as video players use this to keep the screen from blanking
+//! while one of their surfaces is visible.
+//!
+//! The compositor decides what "visible" means, so
+//! [`Manager::is_inhibited`](./struct.Manager.html#method.is_inhibited) takes
+//! a predicate. The `inhibitor_added` and `inhibitor_destroyed` callbacks can
+//! be used to re-evaluate the idle state. Where the manager isn't at hand use
+//! `surface::Handle::has_idle_inhibitor`.
+
+use std::ptr;
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_idle_inhibit_manager_v1, wlr_idle_inhibit_v1_create,
+                  wlr_idle_inhibitor_v1, wlr_surface};
+
+use {compositor, surface::{self, Surface}, utils::Handleable};
+
+/// Callback that's triggered when a client starts inhibiting idle while the
+/// surface is visible.
+pub type NewInhibitor = fn(compositor_handle: compositor::Handle,
+                           surface_handle: surface::Handle);
+
+/// Callback that's triggered when an idle inhibitor is destroyed.
+///
+/// The surface may be destroyed as well, in which case the handle can't be
+/// upgraded any more.
+///
+/// NOTE The inhibitor is still returned by `Manager::inhibited_surfaces`
+/// for the duration of this callback.
+pub type InhibitorDestroyed = fn(compositor_handle: compositor::Handle,
+                                 surface_handle: surface::Handle);
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        [inhibitor_destroyed: InhibitorDestroyed]
+        (NewInhibitor, new_inhibitor_listener, inhibitor_added) =>
+            (new_inhibitor_notify, inhibitor_added):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let inhibitor_ptr = data as *mut wlr_idle_inhibitor_v1;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let surface = Surface::from_ptr((*inhibitor_ptr).surface).weak_reference();
+            manager.inhibitor_added.map(|f| f(compositor, surface.clone()));
+            let mut listener = InhibitorListener::new((inhibitor_ptr,
+                                                       surface,
+                                                       manager.inhibitor_destroyed));
+            wl_signal_add(&mut (*inhibitor_ptr).events.destroy as *mut _ as _,
+                          listener.destroy_listener() as _);
+            (*inhibitor_ptr).data = Box::into_raw(listener) as *mut _;
+        };
+    ]
+}
+
+wayland_listener!(pub(crate) InhibitorListener,
+                  (*mut wlr_idle_inhibitor_v1, surface::Handle, Option<InhibitorDestroyed>), [
+    destroy_listener => destroy_notify: |this: &mut InhibitorListener,
+                                         _data: *mut libc::c_void,|
+    unsafe {
+        let (inhibitor_ptr, ref surface, inhibitor_destroyed) = this.data;
+        if let Some(compositor) = compositor::handle() {
+            inhibitor_destroyed.map(|f| f(compositor, surface.clone()));
+        }
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        Box::from_raw((*inhibitor_ptr).data as *mut InhibitorListener);
+    };
+]);
+
+/// The idle inhibit global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_idle_inhibit_manager_v1,
+    internal: &'static mut InternalManager
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_idle_inhibit_v1_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.new_inhibitor as *mut _ as _,
+                      (&mut internal.new_inhibitor_listener) as *mut _ as _);
+        IDLE_INHIBIT = manager;
+        Some(Manager { manager, internal })
+    }
+
+    /// Get handles to all the surfaces that hold an idle inhibitor.
+    pub fn inhibited_surfaces(&self) -> Vec<surface::Handle> {
+        unsafe {
+            let mut result = vec![];
+            wl_list_for_each!((*self.manager).inhibitors, link,
+                              (inhibitor: wlr_idle_inhibitor_v1) => {
+                                  result.push(inhibitor_surface(inhibitor))
+                              });
+            result
+        }
+    }
+
+    /// Whether the surface holds an idle inhibitor.
+    pub fn surface_has_inhibitor(&self, surface: &Surface) -> bool {
+        unsafe { has_inhibitor(self.manager, surface.as_ptr()) }
+    }
+
+    /// Whether idle is inhibited, i.e. any surface the compositor considers
+    /// visible holds an idle inhibitor.
+    pub fn is_inhibited<F>(&self, mut is_visible: F) -> bool
+        where F: FnMut(&surface::Handle) -> bool
+    {
+        self.inhibited_surfaces().iter().any(|surface| is_visible(surface))
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            if IDLE_INHIBIT == self.manager {
+                IDLE_INHIBIT = ptr::null_mut();
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.new_inhibitor_listener.link as *mut _ as _);
+        }
+    }
+}
+
+/// The idle inhibit manager used by `surface::Handle::has_idle_inhibitor`,
+/// if there is one.
+static mut IDLE_INHIBIT: *mut wlr_idle_inhibit_manager_v1 = 0 as *mut _;
+
+/// Whether the surface holds an idle inhibitor.
+///
+/// Returns `false` if there's no idle inhibit manager.
+pub(crate) unsafe fn surface_inhibits(surface: *mut wlr_surface) -> bool {
+    !IDLE_INHIBIT.is_null() && has_inhibitor(IDLE_INHIBIT, surface)
+}
+
+/// Whether the manager has an inhibitor for the surface.
+unsafe fn has_inhibitor(manager: *mut wlr_idle_inhibit_manager_v1,
+                        surface: *mut wlr_surface)
+                        -> bool {
+    let mut found = false;
+    wl_list_for_each!((*manager).inhibitors, link,
+                      (inhibitor: wlr_idle_inhibitor_v1) => {
+                          found = found || (*inhibitor).surface == surface
+                      });
+    found
+}
+
+/// Get the surface of the inhibitor, preferring the handle stored when it
+/// was created as the surface may already be on its way out.
+unsafe fn inhibitor_surface(inhibitor: *mut wlr_idle_inhibitor_v1) -> surface::Handle {
+    let listener = (*inhibitor).data as *mut InhibitorListener;
+    if listener.is_null() {
+        Surface::from_ptr((*inhibitor).surface).weak_reference()
+    } else {
+        (*listener).data.1.clone()
+    }
+}
@@ -0,0 +1,456 @@
+//! Support for the input method protocol (`zwp_input_method_v2`).
+//!
+//! An input method (IME) is a privileged client that composes text for the
+//! text input of the focused client, see `extensions::text_input`.
+//!
+//! The compositor relays between the two:
+//!
+//! * When the focused text input is enabled, call `send_activate` followed by
+//!   [`InputMethod::send_text_input_state`](./struct.InputMethod.html#method.send_text_input_state).
+//! * When the input method commits, call
+//!   [`InputMethod::forward_to`](./struct.InputMethod.html#method.forward_to)
+//!   to deliver the preedit, commit and delete surrounding text events.
+//!
+//! While the input method has a keyboard grab `Seat::keyboard_notify_key` and
+//! `Seat::keyboard_notify_modifiers` send key events to the grab instead of
+//! the focused client. Events from a virtual keyboard created by the input
+//! method itself are the exception: they go to the focused client, otherwise
+//! the text the input method types would be sent right back to it.
+
+use std::{marker::PhantomData, ptr, time::Duration};
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_seat, wlr_seat_get_keyboard, wlr_input_method_manager_v2,
+                  wlr_input_method_manager_v2_create,
+                  wlr_input_method_v2, wlr_input_method_keyboard_grab_v2,
+                  wlr_input_method_v2_send_activate, wlr_input_method_v2_send_deactivate,
+                  wlr_input_method_v2_send_surrounding_text,
+                  wlr_input_method_v2_send_content_type,
+                  wlr_input_method_v2_send_text_change_cause, wlr_input_method_v2_send_done,
+                  wlr_input_method_v2_send_unavailable,
+                  wlr_input_method_keyboard_grab_v2_send_key,
+                  wlr_input_method_keyboard_grab_v2_send_modifiers,
+                  wlr_input_method_keyboard_grab_v2_set_keyboard};
+
+use {KeyboardModifiers,
+     compositor,
+     extensions::text_input::TextInput,
+     input::keyboard::Keyboard,
+     seat::{self, Seat},
+     utils::{ToMs, c_to_rust_string, safe_as_cstring}};
+#[cfg(feature = "virtual_keyboard")]
+use extensions::virtual_keyboard;
+
+/// Callback that's triggered when an input method client binds to a seat.
+///
+/// Return a handler to be notified when the input method commits, grabs the
+/// keyboard or is destroyed.
+pub type NewInputMethod = fn(compositor_handle: compositor::Handle,
+                             input_method: &mut InputMethod)
+                             -> Option<Box<Handler>>;
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when the input method commits new preedit, commit or delete
+    /// surrounding text state.
+    ///
+    /// The state should be forwarded to the focused text input.
+    fn on_commit(&mut self,
+                 compositor_handle: compositor::Handle,
+                 input_method: &mut InputMethod) {}
+
+    /// Called when the input method grabs the keyboard of its seat.
+    ///
+    /// From now on the seat sends key events to the grab instead of the
+    /// focused client, until the input method is destroyed.
+    fn on_grab_keyboard(&mut self,
+                        compositor_handle: compositor::Handle,
+                        input_method: &mut InputMethod) {}
+
+    /// Called when the input method is destroyed.
+    fn destroyed(&mut self,
+                 compositor_handle: compositor::Handle,
+                 input_method: &mut InputMethod) {}
+}
+
+impl Handler for () {}
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (NewInputMethod, new_input_method_listener, input_method_added) =>
+            (new_input_method_notify, input_method_added):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let input_method_ptr = data as *mut wlr_input_method_v2;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let handler = manager.input_method_added
+                .and_then(|f| f(compositor, &mut InputMethod::from_ptr(input_method_ptr)))
+                .unwrap_or_else(|| Box::new(()));
+            let mut listener = InputMethodListener::new((input_method_ptr, handler));
+            wl_signal_add(&mut (*input_method_ptr).events.commit as *mut _ as _,
+                          listener.commit_listener() as _);
+            wl_signal_add(&mut (*input_method_ptr).events.grab_keyboard as *mut _ as _,
+                          listener.grab_keyboard_listener() as _);
+            wl_signal_add(&mut (*input_method_ptr).events.destroy as *mut _ as _,
+                          listener.destroy_listener() as _);
+            // NOTE The listener frees itself when the input method is destroyed.
+            Box::into_raw(listener);
+        };
+    ]
+}
+
+wayland_listener!(pub(crate) InputMethodListener, (*mut wlr_input_method_v2, Box<Handler>), [
+    commit_listener => commit_notify: |this: &mut InputMethodListener, _data: *mut libc::c_void,|
+    unsafe {
+        let (input_method_ptr, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        handler.on_commit(compositor, &mut InputMethod::from_ptr(input_method_ptr));
+    };
+    grab_keyboard_listener => grab_keyboard_notify: |this: &mut InputMethodListener,
+                                                     _data: *mut libc::c_void,|
+    unsafe {
+        let (input_method_ptr, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        handler.on_grab_keyboard(compositor, &mut InputMethod::from_ptr(input_method_ptr));
+    };
+    destroy_listener => destroy_notify: |this: &mut InputMethodListener,
+                                         _data: *mut libc::c_void,|
+    unsafe {
+        let input_method_ptr = this.data.0;
+        {
+            let (_, ref mut handler) = this.data;
+            if let Some(compositor) = compositor::handle() {
+                handler.destroyed(compositor, &mut InputMethod::from_ptr(input_method_ptr));
+            }
+        }
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.commit_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.grab_keyboard_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        Box::from_raw(this as *mut InputMethodListener);
+    };
+]);
+
+/// Text the input method is composing, to be displayed by the client at the
+/// cursor position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preedit {
+    pub text: String,
+    /// Byte offset in `text` where the cursor starts.
+    pub cursor_begin: i32,
+    /// Byte offset in `text` where the cursor ends.
+    pub cursor_end: i32
+}
+
+/// The input method global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_input_method_manager_v2,
+    internal: &'static mut InternalManager
+}
+
+/// An input method bound to a seat.
+pub struct InputMethod<'manager> {
+    input_method: *mut wlr_input_method_v2,
+    phantom: PhantomData<&'manager Manager>
+}
+
+/// A grab of the keyboard by an input method.
+///
+/// While it exists the seat sends key events here instead of to the focused
+/// client, so that the input method can compose text from them.
+pub struct KeyboardGrab<'input_method> {
+    grab: *mut wlr_input_method_keyboard_grab_v2,
+    phantom: PhantomData<&'input_method InputMethod<'input_method>>
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_input_method_manager_v2_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.input_method as *mut _ as _,
+                      (&mut internal.new_input_method_listener) as *mut _ as _);
+        INPUT_METHODS = manager;
+        Some(Manager { manager, internal })
+    }
+
+    /// Get the input method bound to the seat, if there is one.
+    pub fn input_method<'manager>(&'manager mut self, seat: &Seat) -> Option<InputMethod<'manager>> {
+        unsafe {
+            let seat_ptr = seat.as_ptr();
+            let mut result = None;
+            wl_list_for_each!((*self.manager).input_methods, link,
+                              (input_method: wlr_input_method_v2) => {
+                                  if (*input_method).seat == seat_ptr {
+                                      result = Some(InputMethod::from_ptr(input_method))
+                                  }
+                              });
+            result
+        }
+    }
+
+    /// Get the keyboard grab of the input method bound to the seat, if it
+    /// has grabbed the keyboard.
+    pub fn keyboard_grab<'manager>(&'manager mut self,
+                                   seat: &Seat)
+                                   -> Option<KeyboardGrab<'manager>> {
+        unsafe {
+            self.input_method(seat).and_then(|input_method| {
+                let grab = (*input_method.input_method).keyboard_grab;
+                if grab.is_null() {
+                    None
+                } else {
+                    Some(KeyboardGrab { grab, phantom: PhantomData })
+                }
+            })
+        }
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            if INPUT_METHODS == self.manager {
+                INPUT_METHODS = ptr::null_mut();
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.new_input_method_listener.link as *mut _ as _);
+        }
+    }
+}
+
+impl<'manager> InputMethod<'manager> {
+    pub(crate) unsafe fn from_ptr(input_method: *mut wlr_input_method_v2) -> Self {
+        InputMethod { input_method, phantom: PhantomData }
+    }
+
+    /// Get a handle to the seat this input method is bound to.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*self.input_method).seat) }
+    }
+
+    /// Whether the input method has been activated by the compositor.
+    pub fn active(&self) -> bool {
+        unsafe { (*self.input_method).active }
+    }
+
+    /// Get the committed preedit text, if there is any.
+    pub fn preedit(&self) -> Option<Preedit> {
+        unsafe {
+            let preedit = &(*self.input_method).current.preedit;
+            c_to_rust_string(preedit.text).map(|text| {
+                Preedit { text,
+                          cursor_begin: preedit.cursor_begin,
+                          cursor_end: preedit.cursor_end }
+            })
+        }
+    }
+
+    /// Get the committed text to insert, if there is any.
+    pub fn commit_text(&self) -> Option<String> {
+        unsafe { c_to_rust_string((*self.input_method).current.commit_text) }
+    }
+
+    /// Get how many bytes to delete around the cursor, as (before, after).
+    pub fn delete_surrounding_text(&self) -> (u32, u32) {
+        unsafe {
+            let delete = &(*self.input_method).current.delete;
+            (delete.before_length, delete.after_length)
+        }
+    }
+
+    /// Tell the input method a text input was enabled and it should start
+    /// composing text.
+    pub fn send_activate(&mut self) {
+        unsafe { wlr_input_method_v2_send_activate(self.input_method) }
+    }
+
+    /// Tell the input method the text input was disabled or lost focus.
+    pub fn send_deactivate(&mut self) {
+        unsafe { wlr_input_method_v2_send_deactivate(self.input_method) }
+    }
+
+    /// Send the text surrounding the cursor. Takes effect on the next `send_done`.
+    pub fn send_surrounding_text(&mut self, text: &str, cursor: u32, anchor: u32) {
+        let text = safe_as_cstring(text);
+        unsafe {
+            wlr_input_method_v2_send_surrounding_text(self.input_method,
+                                                      text.as_ptr(),
+                                                      cursor,
+                                                      anchor)
+        }
+    }
+
+    /// Send the content type hint and purpose. Takes effect on the next `send_done`.
+    pub fn send_content_type(&mut self, hint: u32, purpose: u32) {
+        unsafe { wlr_input_method_v2_send_content_type(self.input_method, hint, purpose) }
+    }
+
+    /// Send the reason the surrounding text changed. Takes effect on the next `send_done`.
+    pub fn send_text_change_cause(&mut self, cause: u32) {
+        unsafe { wlr_input_method_v2_send_text_change_cause(self.input_method, cause) }
+    }
+
+    /// Apply the state sent since the last call.
+    pub fn send_done(&mut self) {
+        unsafe { wlr_input_method_v2_send_done(self.input_method) }
+    }
+
+    /// Tell the input method it can't be used, e.g. because another input
+    /// method is already bound to the seat.
+    pub fn send_unavailable(&mut self) {
+        unsafe { wlr_input_method_v2_send_unavailable(self.input_method) }
+    }
+
+    /// Send the state committed by the text input to the input method,
+    /// followed by a done event.
+    pub fn send_text_input_state(&mut self, text_input: &TextInput) {
+        if let Some(surrounding) = text_input.surrounding_text() {
+            self.send_surrounding_text(&surrounding.text, surrounding.cursor, surrounding.anchor);
+        }
+        self.send_text_change_cause(text_input.text_change_cause());
+        let (hint, purpose) = text_input.content_type();
+        self.send_content_type(hint, purpose);
+        self.send_done();
+    }
+
+    /// Send the state committed by the input method (preedit, commit and
+    /// delete surrounding text) to the text input, followed by a done event.
+    pub fn forward_to(&self, text_input: &mut TextInput) {
+        match self.preedit() {
+            Some(preedit) => text_input.send_preedit_string(Some(&preedit.text),
+                                                            preedit.cursor_begin,
+                                                            preedit.cursor_end),
+            None => text_input.send_preedit_string(None, 0, 0)
+        }
+        if let Some(text) = self.commit_text() {
+            text_input.send_commit_string(&text);
+        }
+        let (before_length, after_length) = self.delete_surrounding_text();
+        if before_length != 0 || after_length != 0 {
+            text_input.send_delete_surrounding_text(before_length, after_length);
+        }
+        text_input.send_done();
+    }
+
+    /// Get the keyboard grab of this input method, if it grabbed the keyboard.
+    pub fn keyboard_grab<'this>(&'this mut self) -> Option<KeyboardGrab<'this>> {
+        unsafe {
+            let grab = (*self.input_method).keyboard_grab;
+            if grab.is_null() {
+                None
+            } else {
+                Some(KeyboardGrab { grab, phantom: PhantomData })
+            }
+        }
+    }
+}
+
+impl<'input_method> KeyboardGrab<'input_method> {
+    /// Set the keyboard whose keymap and repeat info are sent to the input
+    /// method.
+    ///
+    /// The seat sets its active keyboard before routing key events here, so
+    /// this is only needed when sending events with `send_key` directly.
+    pub fn set_keyboard(&mut self, keyboard: &Keyboard) {
+        unsafe { wlr_input_method_keyboard_grab_v2_set_keyboard(self.grab, keyboard.as_ptr()) }
+    }
+
+    /// Send a key event to the input method instead of the focused client.
+    pub fn send_key(&mut self, time: Duration, key: u32, state: u32) {
+        unsafe { wlr_input_method_keyboard_grab_v2_send_key(self.grab, time.to_ms(), key, state) }
+    }
+
+    /// Send the modifier state to the input method instead of the focused client.
+    pub fn send_modifiers(&mut self, modifiers: &mut KeyboardModifiers) {
+        unsafe { wlr_input_method_keyboard_grab_v2_send_modifiers(self.grab, modifiers) }
+    }
+}
+
+/// The input method manager the seat routes key events through, if there is one.
+static mut INPUT_METHODS: *mut wlr_input_method_manager_v2 = 0 as *mut _;
+
+/// Sends the key to the keyboard grab of the input method bound to the seat.
+///
+/// Returns `false` if there is no grab, in which case the seat should handle
+/// the key itself.
+pub(crate) unsafe fn grab_key(seat: *mut wlr_seat, time: u32, key: u32, state: u32) -> bool {
+    let grab = seat_keyboard_grab(seat);
+    if grab.is_null() {
+        return false
+    }
+    wlr_input_method_keyboard_grab_v2_send_key(grab, time, key, state);
+    true
+}
+
+/// Sends the modifiers to the keyboard grab of the input method bound to the
+/// seat.
+///
+/// Returns `false` if there is no grab, in which case the seat should handle
+/// the modifiers itself.
+pub(crate) unsafe fn grab_modifiers(seat: *mut wlr_seat,
+                                    modifiers: &mut KeyboardModifiers)
+                                    -> bool {
+    let grab = seat_keyboard_grab(seat);
+    if grab.is_null() {
+        return false
+    }
+    wlr_input_method_keyboard_grab_v2_send_modifiers(grab, modifiers);
+    true
+}
+
+/// Get the keyboard grab of the input method bound to the seat, with the
+/// keyboard of the seat set so the keymap matches the key events.
+///
+/// Returns null if the keyboard of the seat is a virtual keyboard of the
+/// client that owns the grab.
+unsafe fn seat_keyboard_grab(seat: *mut wlr_seat) -> *mut wlr_input_method_keyboard_grab_v2 {
+    if INPUT_METHODS.is_null() {
+        return ptr::null_mut()
+    }
+    let mut grab = ptr::null_mut();
+    wl_list_for_each!((*INPUT_METHODS).input_methods, link,
+                      (input_method: wlr_input_method_v2) => {
+                          if (*input_method).seat == seat {
+                              grab = (*input_method).keyboard_grab;
+                          }
+                      });
+    if grab.is_null() {
+        return grab
+    }
+    let keyboard = wlr_seat_get_keyboard(seat);
+    if keyboard.is_null() {
+        return grab
+    }
+    #[cfg(feature = "virtual_keyboard")]
+    {
+        let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                   wl_resource_get_client,
+                                   (*grab).resource as _);
+        if virtual_keyboard::keyboard_client(keyboard) == client as _ {
+            return ptr::null_mut()
+        }
+    }
+    wlr_input_method_keyboard_grab_v2_set_keyboard(grab, keyboard);
+    grab
+}
@@ -0,0 +1,242 @@
+//! Support for the keyboard shortcuts inhibit protocol
+//! (`zwp_keyboard_shortcuts_inhibit_v1`).
+//!
+//! Clients such as remote desktop and virtual machine viewers use this to
+//! ask that the compositor stops handling its own keyboard shortcuts while
+//! one of their surfaces has keyboard focus, so that e.g. Super-key
+//! combinations reach the client.
+//!
+//! Inhibitors have no effect until the compositor activates them. Return
+//! `true` from the `NewInhibitor` callback to activate one immediately.
+//! Before handling a shortcut check
+//! [`Manager::shortcuts_inhibited`](./struct.Manager.html#method.shortcuts_inhibited),
+//! or `Seat::keyboard_shortcuts_inhibited` where the manager isn't at hand.
+
+use std::{ptr, marker::PhantomData};
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_seat, wlr_keyboard_shortcuts_inhibit_manager_v1,
+                  wlr_keyboard_shortcuts_inhibit_v1_create, wlr_keyboard_shortcuts_inhibitor_v1,
+                  wlr_keyboard_shortcuts_inhibitor_v1_activate,
+                  wlr_keyboard_shortcuts_inhibitor_v1_deactivate};
+
+use {compositor,
+     seat::{self, Seat},
+     surface::{self, Surface},
+     utils::Handleable};
+
+/// Callback that's triggered when a client asks to inhibit the keyboard
+/// shortcuts of a seat while its surface is focused.
+///
+/// Return `true` to activate the inhibitor right away. The inhibitor can also
+/// be activated later with `Inhibitor::activate`.
+pub type NewInhibitor = fn(compositor_handle: compositor::Handle,
+                           inhibitor: &mut Inhibitor)
+                           -> bool;
+
+/// Callback that's triggered when an inhibitor is destroyed.
+///
+/// The surface may be destroyed as well, in which case the handle returned by
+/// `Inhibitor::surface` can't be upgraded any more.
+pub type InhibitorDestroyed = fn(compositor_handle: compositor::Handle,
+                                 inhibitor: &mut Inhibitor);
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        [inhibitor_destroyed: InhibitorDestroyed]
+        (NewInhibitor, new_inhibitor_listener, inhibitor_added) =>
+            (new_inhibitor_notify, inhibitor_added):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let inhibitor_ptr = data as *mut wlr_keyboard_shortcuts_inhibitor_v1;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let mut inhibitor = Inhibitor::from_ptr(inhibitor_ptr);
+            if manager.inhibitor_added.map(|f| f(compositor, &mut inhibitor)).unwrap_or(false) {
+                inhibitor.activate();
+            }
+            let mut listener = InhibitorListener::new((inhibitor_ptr,
+                                                       inhibitor.surface(),
+                                                       manager.inhibitor_destroyed));
+            wl_signal_add(&mut (*inhibitor_ptr).events.destroy as *mut _ as _,
+                          listener.destroy_listener() as _);
+            (*inhibitor_ptr).data = Box::into_raw(listener) as *mut _;
+        };
+    ]
+}
+
+wayland_listener!(pub(crate) InhibitorListener,
+                  (*mut wlr_keyboard_shortcuts_inhibitor_v1,
+                   surface::Handle,
+                   Option<InhibitorDestroyed>), [
+    destroy_listener => destroy_notify: |this: &mut InhibitorListener,
+                                         _data: *mut libc::c_void,|
+    unsafe {
+        let (inhibitor_ptr, ref surface, inhibitor_destroyed) = this.data;
+        // NOTE The surface may be on its way out, so don't look it up again.
+        if let Some(compositor) = compositor::handle() {
+            let mut inhibitor = Inhibitor::with_surface(inhibitor_ptr, surface.clone());
+            inhibitor_destroyed.map(|f| f(compositor, &mut inhibitor));
+        }
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        Box::from_raw((*inhibitor_ptr).data as *mut InhibitorListener);
+    };
+]);
+
+/// The keyboard shortcuts inhibit global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_keyboard_shortcuts_inhibit_manager_v1,
+    internal: &'static mut InternalManager
+}
+
+/// A request from a client to inhibit the keyboard shortcuts of a seat
+/// while one of its surfaces is focused.
+pub struct Inhibitor<'manager> {
+    inhibitor: *mut wlr_keyboard_shortcuts_inhibitor_v1,
+    surface: surface::Handle,
+    phantom: PhantomData<&'manager Manager>
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_keyboard_shortcuts_inhibit_v1_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.new_inhibitor as *mut _ as _,
+                      (&mut internal.new_inhibitor_listener) as *mut _ as _);
+        SHORTCUTS_INHIBIT = manager;
+        Some(Manager { manager, internal })
+    }
+
+    /// Get all the inhibitors that currently exist.
+    pub fn inhibitors<'manager>(&'manager mut self) -> Vec<Inhibitor<'manager>> {
+        unsafe {
+            let mut result = vec![];
+            wl_list_for_each!((*self.manager).inhibitors, link,
+                              (inhibitor: wlr_keyboard_shortcuts_inhibitor_v1) => {
+                                  result.push(Inhibitor::from_ptr(inhibitor))
+                              });
+            result
+        }
+    }
+
+    /// Get the inhibitor the client of the surface created for the seat,
+    /// if there is one.
+    pub fn inhibitor_for_surface<'manager>(&'manager mut self,
+                                           seat: &Seat,
+                                           surface: &Surface)
+                                           -> Option<Inhibitor<'manager>> {
+        unsafe {
+            let (seat_ptr, surface_ptr) = (seat.as_ptr(), surface.as_ptr());
+            self.inhibitors().into_iter()
+                .find(|inhibitor| (*inhibitor.inhibitor).seat == seat_ptr &&
+                      (*inhibitor.inhibitor).surface == surface_ptr)
+        }
+    }
+
+    /// Whether the keyboard shortcuts of the seat are inhibited, i.e. there
+    /// is an active inhibitor for the surface with keyboard focus.
+    pub fn shortcuts_inhibited(&mut self, seat: &Seat) -> bool {
+        unsafe { inhibited(self.manager, seat.as_ptr()) }
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            if SHORTCUTS_INHIBIT == self.manager {
+                SHORTCUTS_INHIBIT = ptr::null_mut();
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.new_inhibitor_listener.link as *mut _ as _);
+        }
+    }
+}
+
+/// The keyboard shortcuts inhibit manager used by
+/// `Seat::keyboard_shortcuts_inhibited`, if there is one.
+static mut SHORTCUTS_INHIBIT: *mut wlr_keyboard_shortcuts_inhibit_manager_v1 = 0 as *mut _;
+
+/// Whether the keyboard shortcuts of the seat are inhibited.
+///
+/// Returns `false` if there's no keyboard shortcuts inhibit manager.
+pub(crate) unsafe fn seat_inhibited(seat: *mut wlr_seat) -> bool {
+    !SHORTCUTS_INHIBIT.is_null() && inhibited(SHORTCUTS_INHIBIT, seat)
+}
+
+/// Whether the manager has an active inhibitor for the seat and the surface
+/// with its keyboard focus.
+unsafe fn inhibited(manager: *mut wlr_keyboard_shortcuts_inhibit_manager_v1,
+                    seat: *mut wlr_seat)
+                    -> bool {
+    let focused_surface = (*seat).keyboard_state.focused_surface;
+    if focused_surface.is_null() {
+        return false
+    }
+    let mut found = false;
+    wl_list_for_each!((*manager).inhibitors, link,
+                      (inhibitor: wlr_keyboard_shortcuts_inhibitor_v1) => {
+                          found = found || ((*inhibitor).seat == seat &&
+                                            (*inhibitor).surface == focused_surface &&
+                                            (*inhibitor).active)
+                      });
+    found
+}
+
+impl<'manager> Inhibitor<'manager> {
+    /// Wraps the inhibitor, using the surface handle stored when it was
+    /// created if there is one.
+    pub(crate) unsafe fn from_ptr(inhibitor: *mut wlr_keyboard_shortcuts_inhibitor_v1) -> Self {
+        let listener = (*inhibitor).data as *mut InhibitorListener;
+        let surface = if listener.is_null() {
+            Surface::from_ptr((*inhibitor).surface).weak_reference()
+        } else {
+            (*listener).data.1.clone()
+        };
+        Inhibitor::with_surface(inhibitor, surface)
+    }
+
+    unsafe fn with_surface(inhibitor: *mut wlr_keyboard_shortcuts_inhibitor_v1,
+                           surface: surface::Handle)
+                           -> Self {
+        Inhibitor { inhibitor, surface, phantom: PhantomData }
+    }
+
+    /// Get a handle to the seat whose shortcuts would be inhibited.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*self.inhibitor).seat) }
+    }
+
+    /// Get a handle to the surface that must have keyboard focus for the
+    /// inhibitor to apply.
+    pub fn surface(&self) -> surface::Handle {
+        self.surface.clone()
+    }
+
+    /// Whether the compositor activated the inhibitor.
+    pub fn active(&self) -> bool {
+        unsafe { (*self.inhibitor).active }
+    }
+
+    /// Activate the inhibitor, letting keyboard shortcuts through to the
+    /// client while its surface is focused.
+    pub fn activate(&mut self) {
+        unsafe { wlr_keyboard_shortcuts_inhibitor_v1_activate(self.inhibitor) }
+    }
+
+    /// Deactivate the inhibitor, e.g. because the user pressed an escape
+    /// combination.
+    pub fn deactivate(&mut self) {
+        unsafe { wlr_keyboard_shortcuts_inhibitor_v1_deactivate(self.inhibitor) }
+    }
+}
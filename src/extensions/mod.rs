@@ -1 +1,47 @@
+//! Wayland protocol extensions, most of them behind a cargo feature.
+//!
+//! An extension is enabled by passing its `Builder` to the `compositor::Builder`,
+//! and its `Manager` is then kept in the `Compositor`.
+//!
+//! wlroots destroys the globals of the extensions along with the display, so
+//! a `Manager` doesn't own its global. Dropping it only detaches the
+//! compositor: its callbacks are no longer called. The one exception is
+//! `server_decoration::Manager`, which destroys its global when dropped.
+//!
+//! Objects that clients create through an extension, like a
+//! `pointer_constraints::Constraint`, are destroyed whenever the client
+//! decides. They are only lent out for the duration of a callback or of a
+//! borrow of their `Manager`.
+
+#[cfg(feature = "fractional_scale")]
+pub mod fractional_scale;
+pub mod idle;
+#[cfg(feature = "idle_inhibit")]
+pub mod idle_inhibit;
+#[cfg(feature = "input_method")]
+pub mod input_method;
+#[cfg(feature = "keyboard_shortcuts_inhibit")]
+pub mod keyboard_shortcuts_inhibit;
+#[cfg(feature = "output_management")]
+pub mod output_management;
+#[cfg(feature = "output_power_management")]
+pub mod output_power_management;
+#[cfg(feature = "pointer_constraints")]
+pub mod pointer_constraints;
+#[cfg(feature = "pointer_gestures")]
+pub mod pointer_gestures;
+#[cfg(feature = "presentation_time")]
+pub mod presentation;
+#[cfg(feature = "relative_pointer")]
+pub mod relative_pointer;
 pub mod server_decoration;
+#[cfg(feature = "tearing_control")]
+pub mod tearing_control;
+#[cfg(feature = "input_method")]
+pub mod text_input;
+#[cfg(feature = "viewporter")]
+pub mod viewporter;
+#[cfg(feature = "virtual_keyboard")]
+pub mod virtual_keyboard;
+#[cfg(feature = "virtual_pointer")]
+pub mod virtual_pointer;
@@ -0,0 +1,336 @@
+//! Support for the output management protocol (`zwlr_output_management_v1`).
+//!
+//! Tools such as kanshi and wlr-randr use this to query the outputs of the
+//! compositor and to change their mode, position, transform, scale and
+//! whether they are enabled.
+//!
+//! The compositor advertises its outputs with
+//! [`Manager::update`](./struct.Manager.html#method.update), which must be
+//! called again whenever an output is added, removed or changed.
+//!
+//! Requests from clients are handed to the compositor as an
+//! [`OutputConfiguration`](./struct.OutputConfiguration.html). If no callback
+//! is set the configuration is validated and applied as is with an
+//! `output::Config`: if any output fails to take its new state then the
+//! outputs changed before it are reverted and the client is told the
+//! configuration failed.
+//!
+//! Outputs are moved in the layout given to
+//! [`Manager::set_layout`](./struct.Manager.html#method.set_layout). Without
+//! one, configurations that move an output are refused.
+
+use std::cell::RefCell;
+
+use libc::{self, c_float};
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_output, wlr_output_configuration_head_v1,
+                  wlr_output_configuration_head_v1_create, wlr_output_configuration_v1,
+                  wlr_output_configuration_v1_create, wlr_output_configuration_v1_destroy,
+                  wlr_output_configuration_v1_send_failed,
+                  wlr_output_configuration_v1_send_succeeded, wlr_output_head_v1,
+                  wlr_output_manager_v1, wlr_output_manager_v1_create,
+                  wlr_output_manager_v1_set_configuration};
+
+use {compositor,
+     area::{Origin, Size},
+     output::{self, layout, ConfigError, ModeSetting, Output, Transform},
+     utils::Handleable};
+
+/// Callback that's triggered when a client asks whether a configuration
+/// would be applied successfully.
+///
+/// Return `true` if it would. Nothing should be changed.
+pub type TestConfiguration = fn(compositor_handle: compositor::Handle,
+                                configuration: &OutputConfiguration)
+                                -> bool;
+
+/// Callback that's triggered when a client asks to apply a configuration.
+///
+/// Return `true` if it was applied. If it wasn't, the outputs should be left
+/// as they were, which `OutputConfiguration::apply` takes care of.
+pub type ApplyConfiguration = fn(compositor_handle: compositor::Handle,
+                                 configuration: &OutputConfiguration)
+                                 -> bool;
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (TestConfiguration, test_listener, configuration_tested) =>
+            (test_notify, configuration_tested):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let config_ptr = data as *mut wlr_output_configuration_v1;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let configuration = OutputConfiguration::from_ptr(config_ptr);
+            let ok = match manager.configuration_tested {
+                Some(f) => f(compositor, &configuration),
+                None => configuration.test(layout()).is_ok()
+            };
+            finish_configuration(config_ptr, ok);
+        };
+        (ApplyConfiguration, apply_listener, configuration_applied) =>
+            (apply_notify, configuration_applied):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let config_ptr = data as *mut wlr_output_configuration_v1;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let configuration = OutputConfiguration::from_ptr(config_ptr);
+            let ok = match manager.configuration_applied {
+                Some(f) => f(compositor, &configuration),
+                None => match configuration.apply(layout()) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        wlr_log!(WLR_ERROR, "Could not apply output configuration: {}", err);
+                        false
+                    }
+                }
+            };
+            let manager_ptr = (*config_ptr).manager;
+            finish_configuration(config_ptr, ok);
+            if ok {
+                readvertise(manager_ptr);
+            }
+        };
+    ]
+}
+
+thread_local! {
+    /// The layout the outputs are moved in, see `Manager::set_layout`.
+    static LAYOUT: RefCell<Option<layout::Handle>> = RefCell::new(None);
+}
+
+fn layout() -> Option<layout::Handle> {
+    LAYOUT.with(|layout| layout.borrow().clone())
+}
+
+/// Tells the client how the configuration went and frees it.
+unsafe fn finish_configuration(config: *mut wlr_output_configuration_v1, succeeded: bool) {
+    if succeeded {
+        wlr_output_configuration_v1_send_succeeded(config)
+    } else {
+        wlr_output_configuration_v1_send_failed(config)
+    }
+    wlr_output_configuration_v1_destroy(config)
+}
+
+/// Advertises the current state of the outputs the manager already knows about.
+unsafe fn readvertise(manager: *mut wlr_output_manager_v1) {
+    let mut outputs = vec![];
+    wl_list_for_each!((*manager).heads, link, (head: wlr_output_head_v1) => {
+        outputs.push((*head).state.output)
+    });
+    set_configuration(manager, outputs)
+}
+
+unsafe fn set_configuration<I>(manager: *mut wlr_output_manager_v1, outputs: I)
+    where I: IntoIterator<Item = *mut wlr_output>
+{
+    let config = wlr_output_configuration_v1_create();
+    for output in outputs {
+        let head = wlr_output_configuration_head_v1_create(config, output);
+        if head.is_null() {
+            continue
+        }
+        (*head).state.x = (*output).lx;
+        (*head).state.y = (*output).ly;
+    }
+    wlr_output_manager_v1_set_configuration(manager, config)
+}
+
+/// The output management global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_output_manager_v1,
+    internal: &'static mut InternalManager
+}
+
+/// The state requested for a single output.
+#[derive(Debug, Clone)]
+pub struct HeadConfiguration {
+    /// The output this state is for.
+    pub output: output::Handle,
+    /// Whether the output should be enabled.
+    ///
+    /// When this is `false` the rest of the state should be ignored.
+    pub enabled: bool,
+    /// The mode to set, or `None` if it should be left as it is.
+    pub mode: Option<ModeSetting>,
+    /// The position of the output in layout coordinates.
+    pub position: Origin,
+    /// The transform of the output.
+    pub transform: Transform,
+    /// The scale of the output.
+    pub scale: c_float
+}
+
+/// A configuration a client asked to test or apply.
+///
+/// It describes the state of every output advertised to the client.
+#[derive(Debug, Clone)]
+pub struct OutputConfiguration {
+    serial: u32,
+    heads: Vec<HeadConfiguration>
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_output_manager_v1_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.test as *mut _ as _,
+                      (&mut internal.test_listener) as *mut _ as _);
+        wl_signal_add(&mut (*manager).events.apply as *mut _ as _,
+                      (&mut internal.apply_listener) as *mut _ as _);
+        Some(Manager { manager, internal })
+    }
+
+    /// Advertise the current state of the outputs to clients.
+    ///
+    /// Pass every output of the compositor, including disabled ones. Outputs
+    /// that aren't passed are removed from the clients' view.
+    pub fn update<'output, I>(&mut self, outputs: I)
+        where I: IntoIterator<Item = &'output Output>
+    {
+        unsafe {
+            let outputs = outputs.into_iter()
+                .map(|output| output.as_ptr())
+                .collect::<Vec<_>>();
+            set_configuration(self.manager, outputs)
+        }
+    }
+
+    /// Set the layout the outputs are in.
+    ///
+    /// When no `configuration_applied` callback is set, outputs are moved
+    /// in this layout. Without a layout, configurations that move an output
+    /// fail.
+    pub fn set_layout(&mut self, layout: Option<layout::Handle>) {
+        LAYOUT.with(|current| *current.borrow_mut() = layout)
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.test_listener.link as *mut _ as _);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.apply_listener.link as *mut _ as _);
+        }
+    }
+}
+
+impl HeadConfiguration {
+    unsafe fn from_ptr(head: *mut wlr_output_configuration_head_v1) -> Self {
+        let state = &(*head).state;
+        let mode = if !state.mode.is_null() {
+            Some(ModeSetting::Preset { size: Size::new((*state.mode).width,
+                                                       (*state.mode).height),
+                                       refresh: (*state.mode).refresh })
+        } else if state.custom_mode.width > 0 && state.custom_mode.height > 0 {
+            Some(ModeSetting::Custom { size: Size::new(state.custom_mode.width,
+                                                       state.custom_mode.height),
+                                       refresh: state.custom_mode.refresh })
+        } else {
+            None
+        };
+        HeadConfiguration { output: output::Handle::from_ptr(state.output),
+                            enabled: state.enabled,
+                            mode,
+                            position: Origin::new(state.x, state.y),
+                            transform: state.transform,
+                            scale: state.scale }
+    }
+}
+
+impl OutputConfiguration {
+    unsafe fn from_ptr(config: *mut wlr_output_configuration_v1) -> Self {
+        let mut heads = vec![];
+        wl_list_for_each!((*config).heads, link, (head: wlr_output_configuration_head_v1) => {
+            heads.push(HeadConfiguration::from_ptr(head))
+        });
+        OutputConfiguration { serial: (*config).serial, heads }
+    }
+
+    /// Get the serial of the state the client based the configuration on.
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    /// Get the requested state of each output.
+    pub fn heads(&self) -> &[HeadConfiguration] {
+        &self.heads
+    }
+
+    /// Turn the configuration into an `output::Config`.
+    ///
+    /// The config has no layout, so positions are only set on the outputs.
+    pub fn to_config(&self) -> output::Config {
+        self.heads.iter().fold(output::Config::new(), |config, head| {
+            let config = config.enable(&head.output, head.enabled);
+            if !head.enabled {
+                return config
+            }
+            let config = match head.mode {
+                Some(ModeSetting::Preset { size, refresh }) => {
+                    config.mode(&head.output, size, refresh)
+                }
+                Some(ModeSetting::Custom { size, refresh }) => {
+                    config.custom_mode(&head.output, size, refresh)
+                }
+                None => config
+            };
+            config.transform(&head.output, head.transform)
+                  .scale(&head.output, head.scale)
+                  .position(&head.output, head.position)
+        })
+    }
+
+    /// Check that the configuration could be applied with `apply`, without
+    /// changing any output.
+    pub fn test(&self, layout: Option<layout::Handle>) -> Result<(), ConfigError> {
+        if layout.is_none() {
+            self.check_unmoved()?;
+        }
+        self.to_config().validate()
+    }
+
+    /// Apply the configuration to all of its outputs, or to none of them if
+    /// any output doesn't accept its new state.
+    ///
+    /// The outputs are moved in the layout. Without a layout any head that
+    /// moves its output makes this fail with `ConfigError::NoLayout`.
+    pub fn apply(&self, layout: Option<layout::Handle>) -> Result<(), ConfigError> {
+        match layout {
+            Some(layout) => self.to_config().layout(layout).commit(),
+            None => {
+                self.check_unmoved()?;
+                self.to_config().commit()
+            }
+        }
+    }
+
+    /// Check that no enabled head moves its output.
+    fn check_unmoved(&self) -> Result<(), ConfigError> {
+        for head in self.heads.iter().filter(|head| head.enabled) {
+            head.output.run(|output| {
+                let (x, y) = output.layout_space_pos();
+                if (x, y) != (head.position.x, head.position.y) {
+                    return Err(ConfigError::NoLayout { output: output.name() })
+                }
+                Ok(())
+            }).map_err(ConfigError::OutputUnavailable)??;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+//! Support for the output power management protocol
+//! (`zwlr_output_power_management_v1`).
+//!
+//! Tools such as swayidle use this to turn the screens off when the session
+//! is idle, and back on when there's activity again.
+//!
+//! Requests are applied with `Output::enable`. The `mode_set` callback is
+//! called afterwards, e.g. to damage the whole output once it's back on.
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_output_enable, wlr_output_power_manager_v1,
+                  wlr_output_power_manager_v1_create, wlr_output_power_v1_set_mode_event,
+                  zwlr_output_power_v1_mode::*};
+
+use {compositor, output};
+
+/// The power mode a client asked an output to be in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PowerMode {
+    Off,
+    On
+}
+
+/// Callback that's triggered after a client turned an output on or off.
+pub type ModeSet = fn(compositor_handle: compositor::Handle,
+                      output_handle: output::Handle,
+                      mode: PowerMode);
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (ModeSet, set_mode_listener, mode_set) => (set_mode_notify, mode_set):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let event = data as *mut wlr_output_power_v1_set_mode_event;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let mode = match (*event).mode {
+                ZWLR_OUTPUT_POWER_V1_MODE_OFF => PowerMode::Off,
+                ZWLR_OUTPUT_POWER_V1_MODE_ON => PowerMode::On
+            };
+            if !wlr_output_enable((*event).output, mode == PowerMode::On) {
+                wlr_log!(WLR_ERROR, "Could not set the power mode of an output to {:?}", mode);
+                return
+            }
+            let output = output::Handle::from_ptr((*event).output);
+            manager.mode_set.map(|f| f(compositor, output, mode));
+        };
+    ]
+}
+
+/// The output power management global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_output_power_manager_v1,
+    internal: &'static mut InternalManager
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_output_power_manager_v1_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.set_mode as *mut _ as _,
+                      (&mut internal.set_mode_listener) as *mut _ as _);
+        Some(Manager { manager, internal })
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.set_mode_listener.link as *mut _ as _);
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! Support for the pointer constraints protocol (`zwp_pointer_constraints_v1`).
+//!
+//! Clients use this protocol to ask for the pointer to be locked in place or
+//! confined to a region of one of their surfaces, e.g. for games or remote
+//! desktop viewers.
+//!
+//! The compositor decides when a constraint becomes active. Usually this is
+//! when the constrained surface gains pointer focus: look it up with
+//! [`Manager::constraint_for_surface`](./struct.Manager.html#method.constraint_for_surface),
+//! call `send_activated` and then pass the result of
+//! [`Constraint::cursor_constraint`](./struct.Constraint.html#method.cursor_constraint)
+//! to `Cursor::set_constraint`.
+
+use std::marker::PhantomData;
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_pointer_constraint_v1, wlr_pointer_constraint_v1_type,
+                  wlr_pointer_constraints_v1, wlr_pointer_constraints_v1_create,
+                  wlr_pointer_constraints_v1_constraint_for_surface,
+                  wlr_pointer_constraint_v1_send_activated,
+                  wlr_pointer_constraint_v1_send_deactivated,
+                  pixman_region32_copy, pixman_region32_translate};
+pub use wlroots_sys::wlr_pointer_constraint_v1_type::*;
+
+use {compositor,
+     cursor,
+     seat::{self, Seat},
+     surface::{self, Surface},
+     utils::{Handleable, region::OwnedPixmanRegion32}};
+
+/// The kind of constraint a client requested.
+pub type ConstraintType = wlr_pointer_constraint_v1_type;
+
+/// Callback that's triggered when a client requests a new pointer constraint.
+///
+/// Return a handler to be notified of changes to the constraint.
+///
+/// If the surface already has pointer focus the constraint can be activated
+/// here with `Constraint::send_activated`.
+pub type NewConstraint = fn(compositor_handle: compositor::Handle,
+                            constraint: &mut Constraint)
+                            -> Option<Box<Handler>>;
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when the client changes the region of the constraint.
+    ///
+    /// If the constraint is active the cursor constraint should be updated.
+    fn on_set_region(&mut self,
+                     compositor_handle: compositor::Handle,
+                     constraint: &mut Constraint) {}
+
+    /// Called when the constraint is destroyed.
+    ///
+    /// If the constraint is active the cursor constraint should be removed.
+    ///
+    /// The surface of the constraint may already be destroyed, so the handle
+    /// returned by `Constraint::surface` may no longer be valid.
+    fn destroyed(&mut self,
+                 compositor_handle: compositor::Handle,
+                 constraint: &mut Constraint) {}
+}
+
+impl Handler for () {}
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (NewConstraint, new_constraint_listener, constraint_added) =>
+            (new_constraint_notify, constraint_added):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let constraint_ptr = data as *mut wlr_pointer_constraint_v1;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let mut constraint = Constraint::from_ptr(constraint_ptr);
+            let surface = constraint.surface();
+            let handler = manager.constraint_added
+                .and_then(|f| f(compositor, &mut constraint))
+                .unwrap_or_else(|| Box::new(()));
+            let mut listener = ConstraintListener::new((constraint_ptr, surface, handler));
+            wl_signal_add(&mut (*constraint_ptr).events.set_region as *mut _ as _,
+                          listener.set_region_listener() as _);
+            wl_signal_add(&mut (*constraint_ptr).events.destroy as *mut _ as _,
+                          listener.destroy_listener() as _);
+            (*constraint_ptr).data = Box::into_raw(listener) as *mut _;
+        };
+    ]
+}
+
+wayland_listener!(pub(crate) ConstraintListener,
+                  (*mut wlr_pointer_constraint_v1, surface::Handle, Box<Handler>), [
+    set_region_listener => set_region_notify: |this: &mut ConstraintListener,
+                                               _data: *mut libc::c_void,|
+    unsafe {
+        let (constraint_ptr, ref surface, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        let mut constraint = Constraint::with_surface(constraint_ptr, surface.clone());
+        handler.on_set_region(compositor, &mut constraint);
+    };
+    destroy_listener => destroy_notify: |this: &mut ConstraintListener,
+                                         _data: *mut libc::c_void,|
+    unsafe {
+        let constraint_ptr = this.data.0;
+        {
+            // NOTE Use the surface handle from creation, the surface may
+            // already be gone.
+            let (_, ref surface, ref mut handler) = this.data;
+            if let Some(compositor) = compositor::handle() {
+                let mut constraint = Constraint::with_surface(constraint_ptr, surface.clone());
+                handler.destroyed(compositor, &mut constraint);
+            }
+        }
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.set_region_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        Box::from_raw((*constraint_ptr).data as *mut ConstraintListener);
+    };
+]);
+
+/// The pointer constraints global.
+#[allow(dead_code)]
+pub struct Manager {
+    constraints: *mut wlr_pointer_constraints_v1,
+    manager: &'static mut InternalManager
+}
+
+/// A pointer constraint requested by a client.
+pub struct Constraint<'manager> {
+    constraint: *mut wlr_pointer_constraint_v1,
+    surface: surface::Handle,
+    phantom: PhantomData<&'manager Manager>
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let constraints = wlr_pointer_constraints_v1_create(display);
+        if constraints.is_null() {
+            return None
+        }
+        let manager = InternalManager::build(builder);
+        wl_signal_add(&mut (*constraints).events.new_constraint as *mut _ as _,
+                      (&mut manager.new_constraint_listener) as *mut _ as _);
+        Some(Manager { constraints, manager })
+    }
+
+    /// Get the constraint a client has placed on the pointer of the seat
+    /// while it is over the given surface, if there is one.
+    pub fn constraint_for_surface<'manager>(&'manager self,
+                                            surface: &Surface,
+                                            seat: &Seat)
+                                            -> Option<Constraint<'manager>> {
+        unsafe {
+            let constraint = wlr_pointer_constraints_v1_constraint_for_surface(self.constraints,
+                                                                               surface.as_ptr(),
+                                                                               seat.as_ptr());
+            if constraint.is_null() {
+                None
+            } else {
+                Some(Constraint::from_ptr(constraint))
+            }
+        }
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.manager.new_constraint_listener.link as *mut _ as _);
+        }
+    }
+}
+
+impl<'manager> Constraint<'manager> {
+    /// Wraps a constraint whose surface is still alive.
+    pub(crate) unsafe fn from_ptr(constraint: *mut wlr_pointer_constraint_v1) -> Self {
+        let surface = Surface::from_ptr((*constraint).surface).weak_reference();
+        Constraint::with_surface(constraint, surface)
+    }
+
+    unsafe fn with_surface(constraint: *mut wlr_pointer_constraint_v1,
+                           surface: surface::Handle)
+                           -> Self {
+        Constraint { constraint, surface, phantom: PhantomData }
+    }
+
+    /// Whether the client wants the pointer locked in place or confined to a region.
+    pub fn constraint_type(&self) -> ConstraintType {
+        unsafe { (*self.constraint).type_ }
+    }
+
+    /// Get a handle to the surface the constraint applies to.
+    pub fn surface(&self) -> surface::Handle {
+        self.surface.clone()
+    }
+
+    /// Get a handle to the seat whose pointer is constrained.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*self.constraint).seat) }
+    }
+
+    /// Get a copy of the region the pointer is constrained to.
+    ///
+    /// The region is in surface-local coordinates.
+    pub fn region(&self) -> OwnedPixmanRegion32 {
+        unsafe {
+            let mut region = OwnedPixmanRegion32::new();
+            pixman_region32_copy(&mut region.region, &mut (*self.constraint).region);
+            region
+        }
+    }
+
+    /// Converts this constraint into one that can be applied to a `Cursor`.
+    ///
+    /// `(lx, ly)` is the position of the constrained surface in layout
+    /// coordinates.
+    pub fn cursor_constraint(&self, lx: i32, ly: i32) -> cursor::Constraint {
+        match self.constraint_type() {
+            WLR_POINTER_CONSTRAINT_V1_LOCKED => cursor::Constraint::Locked,
+            WLR_POINTER_CONSTRAINT_V1_CONFINED => {
+                let mut region = self.region();
+                unsafe { pixman_region32_translate(&mut region.region, lx, ly) }
+                cursor::Constraint::Confined(region)
+            }
+        }
+    }
+
+    /// Tell the client that the constraint is now in effect.
+    pub fn send_activated(&mut self) {
+        unsafe { wlr_pointer_constraint_v1_send_activated(self.constraint) }
+    }
+
+    /// Tell the client that the constraint is no longer in effect.
+    pub fn send_deactivated(&mut self) {
+        unsafe { wlr_pointer_constraint_v1_send_deactivated(self.constraint) }
+    }
+}
@@ -0,0 +1,105 @@
+//! Support for the pointer gestures protocol (`zwp_pointer_gestures_v1`).
+//!
+//! Touchpad gestures are delivered to the compositor through the
+//! `on_pointer_swipe_*` and `on_pointer_pinch_*` callbacks of `cursor::Handler`.
+//! The compositor can either consume them (e.g. to switch workspaces) or
+//! forward them to the client with pointer focus using this manager.
+
+use wlroots_sys::{wl_display, wlr_pointer_gestures_v1, wlr_pointer_gestures_v1_create,
+                  wlr_pointer_gestures_v1_send_swipe_begin,
+                  wlr_pointer_gestures_v1_send_swipe_update,
+                  wlr_pointer_gestures_v1_send_swipe_end,
+                  wlr_pointer_gestures_v1_send_pinch_begin,
+                  wlr_pointer_gestures_v1_send_pinch_update,
+                  wlr_pointer_gestures_v1_send_pinch_end};
+
+use {input::pointer, seat::Seat};
+
+#[derive(Debug)]
+pub struct Manager {
+    gestures: *mut wlr_pointer_gestures_v1
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display) -> Option<Self> {
+        let gestures = wlr_pointer_gestures_v1_create(display);
+
+        if !gestures.is_null() {
+            Some(Manager { gestures })
+        } else {
+            None
+        }
+    }
+
+    /// Forward the start of a swipe gesture to the client with pointer focus
+    /// on the seat.
+    pub fn send_swipe_begin(&mut self, seat: &Seat, event: &pointer::event::SwipeBegin) {
+        unsafe {
+            wlr_pointer_gestures_v1_send_swipe_begin(self.gestures,
+                                                     seat.as_ptr(),
+                                                     event.time_msec(),
+                                                     event.fingers())
+        }
+    }
+
+    /// Forward an update of a swipe gesture to the client with pointer focus
+    /// on the seat.
+    pub fn send_swipe_update(&mut self, seat: &Seat, event: &pointer::event::SwipeUpdate) {
+        let (dx, dy) = event.delta();
+        unsafe {
+            wlr_pointer_gestures_v1_send_swipe_update(self.gestures,
+                                                      seat.as_ptr(),
+                                                      event.time_msec(),
+                                                      dx,
+                                                      dy)
+        }
+    }
+
+    /// Forward the end of a swipe gesture to the client with pointer focus
+    /// on the seat.
+    pub fn send_swipe_end(&mut self, seat: &Seat, event: &pointer::event::SwipeEnd) {
+        unsafe {
+            wlr_pointer_gestures_v1_send_swipe_end(self.gestures,
+                                                   seat.as_ptr(),
+                                                   event.time_msec(),
+                                                   event.cancelled())
+        }
+    }
+
+    /// Forward the start of a pinch gesture to the client with pointer focus
+    /// on the seat.
+    pub fn send_pinch_begin(&mut self, seat: &Seat, event: &pointer::event::PinchBegin) {
+        unsafe {
+            wlr_pointer_gestures_v1_send_pinch_begin(self.gestures,
+                                                     seat.as_ptr(),
+                                                     event.time_msec(),
+                                                     event.fingers())
+        }
+    }
+
+    /// Forward an update of a pinch gesture to the client with pointer focus
+    /// on the seat.
+    pub fn send_pinch_update(&mut self, seat: &Seat, event: &pointer::event::PinchUpdate) {
+        let (dx, dy) = event.delta();
+        unsafe {
+            wlr_pointer_gestures_v1_send_pinch_update(self.gestures,
+                                                      seat.as_ptr(),
+                                                      event.time_msec(),
+                                                      dx,
+                                                      dy,
+                                                      event.scale(),
+                                                      event.rotation())
+        }
+    }
+
+    /// Forward the end of a pinch gesture to the client with pointer focus
+    /// on the seat.
+    pub fn send_pinch_end(&mut self, seat: &Seat, event: &pointer::event::PinchEnd) {
+        unsafe {
+            wlr_pointer_gestures_v1_send_pinch_end(self.gestures,
+                                                   seat.as_ptr(),
+                                                   event.time_msec(),
+                                                   event.cancelled())
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Support for the presentation time protocol (`wp_presentation`).
+//!
+//! Frame callbacks only tell clients a frame was drawn, with millisecond
+//! precision. Media players and games use this protocol instead to learn
+//! exactly when their content was shown, the refresh interval of the output
+//! and the sequence number of the frame.
+//!
+//! While rendering a frame, call
+//! [`Manager::surface_rendered`](./struct.Manager.html#method.surface_rendered)
+//! for each surface drawn on the output. Feedback is then sent to those
+//! surfaces when the buffers of the output are swapped, before
+//! `output::Handler::on_buffers_swapped` is called.
+//!
+//! The presentation time is the time of the swap, on `CLOCK_MONOTONIC`, and the
+//! refresh interval is computed from `Output::refresh_rate`.
+
+use wlroots_sys::{timespec, wl_display, wlr_backend, wlr_presentation,
+                  wlr_presentation_create, wlr_presentation_event,
+                  wlr_presentation_send_surface_presented};
+
+use {output::Output, surface::Surface, utils::{self, Handleable}};
+
+bitflags! {
+    /// How the content was presented, sent along with the feedback.
+    pub struct FeedbackFlags: u32 {
+        /// The presentation was synchronized to the vertical retrace.
+        const WP_PRESENTATION_FEEDBACK_KIND_VSYNC = 0x1;
+        /// The timestamp comes from the display hardware.
+        const WP_PRESENTATION_FEEDBACK_KIND_HW_CLOCK = 0x2;
+        /// The display hardware signalled that it started using the new content.
+        const WP_PRESENTATION_FEEDBACK_KIND_HW_COMPLETION = 0x4;
+        /// The client buffer was scanned out directly, without a copy.
+        const WP_PRESENTATION_FEEDBACK_KIND_ZERO_COPY = 0x8;
+    }
+}
+
+/// The presentation global, if one was created, and the flags its feedback
+/// is sent with.
+///
+/// This is read when the buffers of any output are swapped, so it can't live
+/// in the `Manager` owned by the `Compositor`.
+static mut PRESENTATION: Option<(*mut wlr_presentation, FeedbackFlags)> = None;
+
+/// The presentation time global.
+#[derive(Debug)]
+pub struct Manager {
+    presentation: *mut wlr_presentation
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, backend: *mut wlr_backend) -> Option<Self> {
+        let presentation = wlr_presentation_create(display, backend);
+        if presentation.is_null() {
+            return None
+        }
+        PRESENTATION = Some((presentation, FeedbackFlags::WP_PRESENTATION_FEEDBACK_KIND_VSYNC));
+        Some(Manager { presentation })
+    }
+
+    /// Record that the surface is rendered in the frame being drawn on the
+    /// output, so it gets feedback when the frame is presented.
+    ///
+    /// Surfaces that aren't recorded get no feedback for the frame. If they
+    /// commit again before being rendered their feedback is discarded.
+    pub fn surface_rendered(&mut self, surface: &Surface, output: &mut Output) {
+        unsafe { output.add_presented_surface(surface.weak_reference()) }
+    }
+
+    /// Set how frames are presented, reported to clients with the feedback.
+    ///
+    /// By default only `WP_PRESENTATION_FEEDBACK_KIND_VSYNC` is set, which is
+    /// right for backends that swap buffers on the vertical retrace. The
+    /// timestamp is never from the hardware, so don't set
+    /// `WP_PRESENTATION_FEEDBACK_KIND_HW_CLOCK`.
+    pub fn set_flags(&mut self, flags: FeedbackFlags) {
+        unsafe { PRESENTATION = Some((self.presentation, flags)) }
+    }
+
+    /// Get the flags feedback is sent with.
+    pub fn flags(&self) -> FeedbackFlags {
+        unsafe {
+            PRESENTATION.map(|(_, flags)| flags).unwrap_or(FeedbackFlags::empty())
+        }
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe { PRESENTATION = None }
+    }
+}
+
+/// Sends feedback to the surfaces rendered on the output since its buffers
+/// were last swapped.
+///
+/// `when` is the time of the swap reported by wlroots, if any.
+pub(crate) unsafe fn buffers_swapped(output: &mut Output, when: *mut timespec) {
+    let (surfaces, seq) = output.take_presented_surfaces();
+    let (presentation, flags) = match PRESENTATION {
+        Some(presentation) => presentation,
+        None => return
+    };
+    if surfaces.is_empty() {
+        return
+    }
+    let (tv_sec, tv_nsec) = if when.is_null() {
+        let now = utils::current_time();
+        (now.as_secs(), now.subsec_nanos())
+    } else {
+        ((*when).tv_sec as u64, (*when).tv_nsec as u32)
+    };
+    // The refresh rate is in mHz, the refresh interval in ns.
+    let refresh = match output.refresh_rate() {
+        rate if rate > 0 => (1_000_000_000_000 / rate as u64) as u32,
+        _ => 0
+    };
+    let mut event = wlr_presentation_event { output: output.as_ptr(),
+                                             tv_sec,
+                                             tv_nsec,
+                                             refresh,
+                                             seq,
+                                             flags: flags.bits() };
+    for surface in surfaces {
+        // NOTE Surfaces destroyed since they were rendered are skipped.
+        let _ = surface.run(|surface| {
+            wlr_presentation_send_surface_presented(presentation, surface.as_ptr(), &mut event)
+        });
+    }
+}
@@ -0,0 +1,28 @@
+//! Support for the relative pointer protocol (`zwp_relative_pointer_v1`).
+//!
+//! This lets clients receive unaccelerated, unconstrained pointer motion.
+//! Events are sent with `Seat::send_relative_motion`.
+
+use wlroots_sys::{wl_display, wlr_relative_pointer_manager_v1,
+                  wlr_relative_pointer_manager_v1_create};
+
+#[derive(Debug)]
+pub struct Manager {
+    manager: *mut wlr_relative_pointer_manager_v1
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display) -> Option<Self> {
+        let manager_raw = wlr_relative_pointer_manager_v1_create(display);
+
+        if !manager_raw.is_null() {
+            Some(Manager { manager: manager_raw })
+        } else {
+            None
+        }
+    }
+
+    pub(crate) unsafe fn as_ptr(&self) -> *mut wlr_relative_pointer_manager_v1 {
+        self.manager
+    }
+}
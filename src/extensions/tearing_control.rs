@@ -0,0 +1,60 @@
+//! Support for the tearing control protocol (`wp_tearing_control_v1`).
+//!
+//! Clients such as fullscreen games use this to hint that their content
+//! should be presented as soon as it's ready, even if that causes tearing,
+//! rather than waiting for the vertical retrace.
+//!
+//! Hints are only hints: the compositor decides when to honour them, usually
+//! only when the surface is fullscreen. Query the hint of a surface with
+//! [`Manager::presentation_hint`](./struct.Manager.html#method.presentation_hint)
+//! when deciding how to present a frame.
+
+use wlroots_sys::{wl_display, wlr_tearing_control_manager_v1,
+                  wlr_tearing_control_manager_v1_create,
+                  wlr_tearing_control_manager_v1_surface_hint_from_surface,
+                  wp_tearing_control_v1_presentation_hint};
+
+use surface::Surface;
+
+/// The version of the protocol that's advertised.
+const TEARING_CONTROL_VERSION: u32 = 1;
+
+/// How a client would like the content of its surface to be presented.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PresentationHint {
+    /// Wait for the vertical retrace, the default.
+    Vsync,
+    /// Present as soon as possible, even if it tears.
+    Async
+}
+
+/// The tearing control global.
+#[derive(Debug)]
+pub struct Manager {
+    manager: *mut wlr_tearing_control_manager_v1
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display) -> Option<Self> {
+        let manager = wlr_tearing_control_manager_v1_create(display, TEARING_CONTROL_VERSION);
+        if manager.is_null() {
+            None
+        } else {
+            Some(Manager { manager })
+        }
+    }
+
+    /// Get how the client of the surface would like it to be presented.
+    ///
+    /// Surfaces that never set a hint are `PresentationHint::Vsync`.
+    pub fn presentation_hint(&self, surface: &Surface) -> PresentationHint {
+        use self::wp_tearing_control_v1_presentation_hint::*;
+        unsafe {
+            match wlr_tearing_control_manager_v1_surface_hint_from_surface(self.manager,
+                                                                           surface.as_ptr()) {
+                WP_TEARING_CONTROL_V1_PRESENTATION_HINT_VSYNC => PresentationHint::Vsync,
+                WP_TEARING_CONTROL_V1_PRESENTATION_HINT_ASYNC => PresentationHint::Async
+            }
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Support for the text input protocol (`zwp_text_input_v3`).
+//!
+//! Text inputs are created by regular clients that want to receive composed
+//! text from an input method (e.g. for CJK input). The compositor is
+//! responsible for relaying between the text input of the focused client and
+//! the input method, see `extensions::input_method`.
+//!
+//! Text inputs follow the keyboard focus of their seat: the seat sends them
+//! enter and leave events from `Seat::keyboard_notify_enter` and
+//! `Seat::keyboard_clear_focus`.
+
+use std::{marker::PhantomData, ptr};
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_display, wlr_seat, wlr_surface, wlr_text_input_manager_v3,
+                  wlr_text_input_manager_v3_create, wlr_text_input_v3, wlr_text_input_v3_send_enter,
+                  wlr_text_input_v3_send_leave, wlr_text_input_v3_send_preedit_string,
+                  wlr_text_input_v3_send_commit_string,
+                  wlr_text_input_v3_send_delete_surrounding_text, wlr_text_input_v3_send_done};
+
+use {compositor,
+     area::{Area, Origin, Size},
+     seat::{self, Seat},
+     surface::{self, Surface},
+     utils::{Handleable, c_to_rust_string, safe_as_cstring}};
+
+/// Callback that's triggered when a client creates a text input.
+///
+/// Return a handler to be notified when the text input is enabled,
+/// committed, disabled or destroyed.
+pub type NewTextInput = fn(compositor_handle: compositor::Handle,
+                           text_input: &mut TextInput)
+                           -> Option<Box<Handler>>;
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when the client enables the text input, i.e. a text field
+    /// gained focus in the client.
+    ///
+    /// The input method of the seat should be activated.
+    fn on_enable(&mut self,
+                 compositor_handle: compositor::Handle,
+                 text_input: &mut TextInput) {}
+
+    /// Called when the client commits new state (surrounding text, content
+    /// type, cursor rectangle).
+    ///
+    /// The new state should be forwarded to the input method.
+    fn on_commit(&mut self,
+                 compositor_handle: compositor::Handle,
+                 text_input: &mut TextInput) {}
+
+    /// Called when the client disables the text input.
+    ///
+    /// The input method of the seat should be deactivated.
+    fn on_disable(&mut self,
+                  compositor_handle: compositor::Handle,
+                  text_input: &mut TextInput) {}
+
+    /// Called when the text input is destroyed.
+    fn destroyed(&mut self,
+                 compositor_handle: compositor::Handle,
+                 text_input: &mut TextInput) {}
+}
+
+impl Handler for () {}
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (NewTextInput, new_text_input_listener, text_input_added) =>
+            (new_text_input_notify, text_input_added):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let text_input_ptr = data as *mut wlr_text_input_v3;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let handler = manager.text_input_added
+                .and_then(|f| f(compositor, &mut TextInput::from_ptr(text_input_ptr)))
+                .unwrap_or_else(|| Box::new(()));
+            // The client may create its text input after its surface got
+            // keyboard focus.
+            let seat_ptr = (*text_input_ptr).seat;
+            let focused = (*seat_ptr).keyboard_state.focused_surface;
+            if !focused.is_null() && same_client(text_input_ptr, focused) {
+                wlr_text_input_v3_send_enter(text_input_ptr, focused);
+            }
+            let mut listener = TextInputListener::new((text_input_ptr, handler));
+            wl_signal_add(&mut (*text_input_ptr).events.enable as *mut _ as _,
+                          listener.enable_listener() as _);
+            wl_signal_add(&mut (*text_input_ptr).events.commit as *mut _ as _,
+                          listener.commit_listener() as _);
+            wl_signal_add(&mut (*text_input_ptr).events.disable as *mut _ as _,
+                          listener.disable_listener() as _);
+            wl_signal_add(&mut (*text_input_ptr).events.destroy as *mut _ as _,
+                          listener.destroy_listener() as _);
+            // NOTE The listener frees itself when the text input is destroyed.
+            Box::into_raw(listener);
+        };
+    ]
+}
+
+wayland_listener!(pub(crate) TextInputListener, (*mut wlr_text_input_v3, Box<Handler>), [
+    enable_listener => enable_notify: |this: &mut TextInputListener, _data: *mut libc::c_void,|
+    unsafe {
+        let (text_input_ptr, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        handler.on_enable(compositor, &mut TextInput::from_ptr(text_input_ptr));
+    };
+    commit_listener => commit_notify: |this: &mut TextInputListener, _data: *mut libc::c_void,|
+    unsafe {
+        let (text_input_ptr, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        handler.on_commit(compositor, &mut TextInput::from_ptr(text_input_ptr));
+    };
+    disable_listener => disable_notify: |this: &mut TextInputListener, _data: *mut libc::c_void,|
+    unsafe {
+        let (text_input_ptr, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        handler.on_disable(compositor, &mut TextInput::from_ptr(text_input_ptr));
+    };
+    destroy_listener => destroy_notify: |this: &mut TextInputListener, _data: *mut libc::c_void,|
+    unsafe {
+        let text_input_ptr = this.data.0;
+        {
+            let (_, ref mut handler) = this.data;
+            if let Some(compositor) = compositor::handle() {
+                handler.destroyed(compositor, &mut TextInput::from_ptr(text_input_ptr));
+            }
+        }
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.enable_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.commit_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.disable_listener()).link as *mut _ as _);
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        Box::from_raw(this as *mut TextInputListener);
+    };
+]);
+
+/// The text surrounding the cursor in the focused text field of the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SurroundingText {
+    pub text: String,
+    /// Byte offset of the cursor in `text`.
+    pub cursor: u32,
+    /// Byte offset of the selection anchor in `text`.
+    ///
+    /// This is the same as `cursor` if there is no selection.
+    pub anchor: u32
+}
+
+/// The text input global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_text_input_manager_v3,
+    internal: &'static mut InternalManager
+}
+
+/// A text input created by a client.
+pub struct TextInput<'manager> {
+    text_input: *mut wlr_text_input_v3,
+    phantom: PhantomData<&'manager Manager>
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_text_input_manager_v3_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.text_input as *mut _ as _,
+                      (&mut internal.new_text_input_listener) as *mut _ as _);
+        TEXT_INPUTS = manager;
+        Some(Manager { manager, internal })
+    }
+
+    /// Get all the text inputs that currently exist.
+    pub fn text_inputs<'manager>(&'manager mut self) -> Vec<TextInput<'manager>> {
+        unsafe {
+            let mut result = vec![];
+            wl_list_for_each!((*self.manager).text_inputs, link,
+                              (text_input: wlr_text_input_v3) => {
+                                  result.push(TextInput::from_ptr(text_input))
+                              });
+            result
+        }
+    }
+
+    /// Get the text input of the seat that is focused on the surface, if
+    /// there is one.
+    ///
+    /// This is the text input the input method of the seat should talk to.
+    pub fn focused_text_input<'manager>(&'manager mut self,
+                                        seat: &Seat)
+                                        -> Option<TextInput<'manager>> {
+        unsafe {
+            let seat_ptr = seat.as_ptr();
+            self.text_inputs().into_iter()
+                .find(|text_input| (*text_input.text_input).seat == seat_ptr &&
+                      !(*text_input.text_input).focused_surface.is_null())
+        }
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            if TEXT_INPUTS == self.manager {
+                TEXT_INPUTS = ptr::null_mut();
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.new_text_input_listener.link as *mut _ as _);
+        }
+    }
+}
+
+impl<'manager> TextInput<'manager> {
+    pub(crate) unsafe fn from_ptr(text_input: *mut wlr_text_input_v3) -> Self {
+        TextInput { text_input, phantom: PhantomData }
+    }
+
+    /// Get a handle to the seat this text input belongs to.
+    pub fn seat(&self) -> seat::Handle {
+        unsafe { seat::Handle::from_ptr((*self.text_input).seat) }
+    }
+
+    /// Get a handle to the surface this text input is focused on, if any.
+    pub fn focused_surface(&self) -> Option<surface::Handle> {
+        unsafe {
+            let surface = (*self.text_input).focused_surface;
+            if surface.is_null() {
+                None
+            } else {
+                Some(Surface::from_ptr(surface).weak_reference())
+            }
+        }
+    }
+
+    /// Whether the client has enabled the text input.
+    pub fn enabled(&self) -> bool {
+        unsafe { (*self.text_input).current_enabled }
+    }
+
+    /// Get the committed surrounding text, if the client provided it.
+    pub fn surrounding_text(&self) -> Option<SurroundingText> {
+        unsafe {
+            let surrounding = &(*self.text_input).current.surrounding;
+            c_to_rust_string(surrounding.text).map(|text| {
+                SurroundingText { text,
+                                  cursor: surrounding.cursor,
+                                  anchor: surrounding.anchor }
+            })
+        }
+    }
+
+    /// Get the reason the surrounding text last changed.
+    pub fn text_change_cause(&self) -> u32 {
+        unsafe { (*self.text_input).current.text_change_cause }
+    }
+
+    /// Get the committed content type, as (hint, purpose).
+    pub fn content_type(&self) -> (u32, u32) {
+        unsafe {
+            let content_type = &(*self.text_input).current.content_type;
+            (content_type.hint, content_type.purpose)
+        }
+    }
+
+    /// Get the area of the text cursor, in surface-local coordinates.
+    ///
+    /// This can be used to place the candidate popup of the input method.
+    pub fn cursor_rectangle(&self) -> Area {
+        unsafe {
+            let rect = &(*self.text_input).current.cursor_rectangle;
+            Area::new(Origin::new(rect.x, rect.y), Size::new(rect.width, rect.height))
+        }
+    }
+
+    /// Tell the client the text input is now focused on the surface.
+    ///
+    /// The seat already does this when its keyboard focus changes.
+    pub fn send_enter(&mut self, surface: &mut Surface) {
+        unsafe { wlr_text_input_v3_send_enter(self.text_input, surface.as_ptr()) }
+    }
+
+    /// Tell the client the text input is no longer focused on its surface.
+    ///
+    /// The seat already does this when its keyboard focus changes.
+    pub fn send_leave(&mut self) {
+        unsafe { wlr_text_input_v3_send_leave(self.text_input) }
+    }
+
+    /// Send the text being composed, or clear it with `None`.
+    ///
+    /// The cursor positions are byte offsets into the text. Takes effect on
+    /// the next `send_done`.
+    pub fn send_preedit_string(&mut self, text: Option<&str>, cursor_begin: i32, cursor_end: i32) {
+        let text = text.map(safe_as_cstring);
+        unsafe {
+            wlr_text_input_v3_send_preedit_string(self.text_input,
+                                                  text.as_ref()
+                                                      .map(|text| text.as_ptr())
+                                                      .unwrap_or(ptr::null()),
+                                                  cursor_begin as _,
+                                                  cursor_end as _)
+        }
+    }
+
+    /// Send text to insert at the cursor. Takes effect on the next `send_done`.
+    pub fn send_commit_string(&mut self, text: &str) {
+        let text = safe_as_cstring(text);
+        unsafe { wlr_text_input_v3_send_commit_string(self.text_input, text.as_ptr()) }
+    }
+
+    /// Ask the client to delete text around the cursor, in bytes.
+    /// Takes effect on the next `send_done`.
+    pub fn send_delete_surrounding_text(&mut self, before_length: u32, after_length: u32) {
+        unsafe {
+            wlr_text_input_v3_send_delete_surrounding_text(self.text_input,
+                                                           before_length,
+                                                           after_length)
+        }
+    }
+
+    /// Apply the preedit, commit and delete events sent since the last call.
+    pub fn send_done(&mut self) {
+        unsafe { wlr_text_input_v3_send_done(self.text_input) }
+    }
+}
+
+/// The text input manager the seat moves focus for, if there is one.
+static mut TEXT_INPUTS: *mut wlr_text_input_manager_v3 = 0 as *mut _;
+
+/// Moves the text input focus of the seat to the surface, or clears it if
+/// the surface is null.
+///
+/// Text inputs of the seat that were focused on another surface are sent a
+/// leave event, and text inputs of the seat that belong to the client of the
+/// surface are sent an enter event.
+pub(crate) unsafe fn seat_focus(seat: *mut wlr_seat, surface: *mut wlr_surface) {
+    if TEXT_INPUTS.is_null() {
+        return
+    }
+    wl_list_for_each!((*TEXT_INPUTS).text_inputs, link, (text_input: wlr_text_input_v3) => {
+        if (*text_input).seat == seat {
+            let focused = (*text_input).focused_surface;
+            if !focused.is_null() && focused != surface {
+                wlr_text_input_v3_send_leave(text_input);
+            }
+            if !surface.is_null() && (*text_input).focused_surface.is_null() &&
+                same_client(text_input, surface) {
+                wlr_text_input_v3_send_enter(text_input, surface);
+            }
+        }
+    });
+}
+
+unsafe fn same_client(text_input: *mut wlr_text_input_v3, surface: *mut wlr_surface) -> bool {
+    let text_input_client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                          wl_resource_get_client,
+                                          (*text_input).resource as _);
+    let surface_client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_resource_get_client,
+                                       (*surface).resource as _);
+    text_input_client == surface_client
+}
@@ -0,0 +1,28 @@
+//! Support for the viewporter protocol (`wp_viewporter`).
+//!
+//! Clients use this to crop their buffers and to scale them to a size that
+//! doesn't depend on the buffer size, e.g. video players showing part of a
+//! frame or clients rendering at a fractional scale. The crop and size of a
+//! surface are in `surface::State::source_box` and
+//! `surface::State::destination_size`, and
+//! `Surface::buffer_source_box` gives the part of the texture to render.
+
+use wlroots_sys::{wl_display, wlr_viewporter, wlr_viewporter_create};
+
+/// The viewporter global.
+#[derive(Debug)]
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_viewporter
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display) -> Option<Self> {
+        let manager = wlr_viewporter_create(display);
+        if manager.is_null() {
+            None
+        } else {
+            Some(Manager { manager })
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Support for the virtual keyboard protocol (`zwp_virtual_keyboard_v1`).
+//!
+//! This lets clients such as on-screen keyboards or remote desktop agents
+//! inject key events. Each virtual keyboard is a real keyboard device: it is
+//! passed to the `keyboard_added` callback of the `input::manager::Builder`
+//! just like a hardware keyboard.
+//!
+//! Use [`Builder::allow_keyboard`](./struct.Builder.html#method.allow_keyboard)
+//! to decide which clients may create virtual keyboards. If it is not set
+//! every client is allowed.
+
+use std::ptr;
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_client, wl_display, wlr_keyboard, wlr_virtual_keyboard_manager_v1,
+                  wlr_virtual_keyboard_manager_v1_create, wlr_virtual_keyboard_v1};
+
+use {compositor, input, seat, utils::safe_as_cstring};
+
+/// The code of the protocol error a denied client is disconnected with.
+///
+/// It's posted on the new `zwp_virtual_keyboard_v1`, whose protocol only
+/// defines `no_keymap` (0), so this is the next free code. The manager's
+/// `unauthorized` error can't be used because wlroots doesn't keep the
+/// resource of the manager.
+pub const ERROR_UNAUTHORIZED: u32 = 1;
+
+/// Callback that's triggered when a client creates a virtual keyboard on the
+/// given seat.
+///
+/// Return `false` to deny the client. It is disconnected with the
+/// [`ERROR_UNAUTHORIZED`](./constant.ERROR_UNAUTHORIZED.html) protocol error
+/// and the keyboard is never added to the compositor.
+pub type AllowKeyboard = fn(compositor_handle: compositor::Handle,
+                            client: *mut wl_client,
+                            seat_handle: seat::Handle)
+                            -> bool;
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (AllowKeyboard, new_virtual_keyboard_listener, allow_keyboard) =>
+            (new_virtual_keyboard_notify, allow_keyboard):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let keyboard_ptr = data as *mut wlr_virtual_keyboard_v1;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_resource_get_client,
+                                       (*keyboard_ptr).resource as _);
+            let seat = seat::Handle::from_ptr((*keyboard_ptr).seat);
+            let allowed = manager.allow_keyboard
+                .map(|f| f(compositor, client as _, seat))
+                .unwrap_or(true);
+            if !allowed {
+                wlr_log!(WLR_INFO, "Denied virtual keyboard for client {:p}", client);
+                let message = safe_as_cstring("virtual keyboard denied by the compositor");
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_resource_post_error,
+                              (*keyboard_ptr).resource as _,
+                              ERROR_UNAUTHORIZED,
+                              message.as_ptr());
+                return
+            }
+            input::manager::add_device(&mut (*keyboard_ptr).input_device);
+        };
+    ]
+}
+
+/// The virtual keyboard global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_virtual_keyboard_manager_v1,
+    internal: &'static mut InternalManager
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_virtual_keyboard_manager_v1_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.new_virtual_keyboard as *mut _ as _,
+                      (&mut internal.new_virtual_keyboard_listener) as *mut _ as _);
+        VIRTUAL_KEYBOARDS = manager;
+        Some(Manager { manager, internal })
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            if VIRTUAL_KEYBOARDS == self.manager {
+                VIRTUAL_KEYBOARDS = ptr::null_mut();
+            }
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.new_virtual_keyboard_listener.link as *mut _ as _);
+        }
+    }
+}
+
+/// The virtual keyboard manager used to look up the client of a keyboard,
+/// if there is one.
+static mut VIRTUAL_KEYBOARDS: *mut wlr_virtual_keyboard_manager_v1 = 0 as *mut _;
+
+/// Get the client that created the keyboard, if it is a virtual keyboard.
+///
+/// Returns null for any other keyboard.
+pub(crate) unsafe fn keyboard_client(keyboard: *mut wlr_keyboard) -> *mut wl_client {
+    if VIRTUAL_KEYBOARDS.is_null() || keyboard.is_null() {
+        return ptr::null_mut()
+    }
+    let mut client = ptr::null_mut();
+    wl_list_for_each!((*VIRTUAL_KEYBOARDS).virtual_keyboards, link,
+                      (virtual_keyboard: wlr_virtual_keyboard_v1) => {
+                          if (*virtual_keyboard).input_device.__bindgen_anon_1.keyboard == keyboard {
+                              client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                                     wl_resource_get_client,
+                                                     (*virtual_keyboard).resource as _) as _;
+                          }
+                      });
+    client
+}
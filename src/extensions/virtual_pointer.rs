@@ -0,0 +1,114 @@
+//! Support for the virtual pointer protocol (`zwlr_virtual_pointer_v1`).
+//!
+//! This lets clients such as remote desktop agents inject pointer events.
+//! Each virtual pointer is a real pointer device: it is passed to the
+//! `pointer_added` callback of the `input::manager::Builder` just like a
+//! hardware pointer.
+//!
+//! Use [`Builder::allow_pointer`](./struct.Builder.html#method.allow_pointer)
+//! to decide which clients may create virtual pointers. If it is not set
+//! every client is allowed.
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wl_client, wl_display, wlr_virtual_pointer_manager_v1,
+                  wlr_virtual_pointer_manager_v1_create,
+                  wlr_virtual_pointer_v1_new_pointer_event};
+
+use {compositor, input, seat, output::{self, Output}, utils::{Handleable, safe_as_cstring}};
+
+/// The code of the protocol error a denied client is disconnected with.
+///
+/// It's posted on the new `zwlr_virtual_pointer_v1`. The protocol has no
+/// error for this, it only defines `invalid_axis` (0) and
+/// `invalid_axis_source` (1), so this is the next free code.
+pub const ERROR_UNAUTHORIZED: u32 = 2;
+
+/// Callback that's triggered when a client creates a virtual pointer.
+///
+/// The client may suggest a seat and an output the pointer should be used
+/// with. These are only hints, the compositor is free to ignore them.
+///
+/// Return `false` to deny the client. It is disconnected with the
+/// [`ERROR_UNAUTHORIZED`](./constant.ERROR_UNAUTHORIZED.html) protocol error
+/// and the pointer is never added to the compositor.
+pub type AllowPointer = fn(compositor_handle: compositor::Handle,
+                           client: *mut wl_client,
+                           suggested_seat: Option<seat::Handle>,
+                           suggested_output: Option<output::Handle>)
+                           -> bool;
+
+wayland_listener_static! {
+    static mut MANAGER;
+    (InternalManager, Builder): [
+        (AllowPointer, new_virtual_pointer_listener, allow_pointer) =>
+            (new_virtual_pointer_notify, allow_pointer):
+        |manager: &mut InternalManager, data: *mut libc::c_void,|
+        unsafe {
+            let event = data as *mut wlr_virtual_pointer_v1_new_pointer_event;
+            let pointer_ptr = (*event).new_pointer;
+            let compositor = match compositor::handle() {
+                Some(handle) => handle,
+                None => return
+            };
+            let client = ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                                       wl_resource_get_client,
+                                       (*pointer_ptr).resource as _);
+            let seat = if (*event).suggested_seat.is_null() {
+                None
+            } else {
+                Some(seat::Handle::from_ptr((*event).suggested_seat))
+            };
+            let output = (*event).suggested_output;
+            let output = if output.is_null() || (*output).data.is_null() {
+                None
+            } else {
+                Some(Output::from_ptr(output).weak_reference())
+            };
+            let allowed = manager.allow_pointer
+                .map(|f| f(compositor, client as _, seat, output))
+                .unwrap_or(true);
+            if !allowed {
+                wlr_log!(WLR_INFO, "Denied virtual pointer for client {:p}", client);
+                let message = safe_as_cstring("virtual pointer denied by the compositor");
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_resource_post_error,
+                              (*pointer_ptr).resource as _,
+                              ERROR_UNAUTHORIZED,
+                              message.as_ptr());
+                return
+            }
+            input::manager::add_device(&mut (*pointer_ptr).input_device);
+        };
+    ]
+}
+
+/// The virtual pointer global.
+#[allow(dead_code)]
+pub struct Manager {
+    manager: *mut wlr_virtual_pointer_manager_v1,
+    internal: &'static mut InternalManager
+}
+
+impl Manager {
+    pub(crate) unsafe fn new(display: *mut wl_display, builder: Builder) -> Option<Self> {
+        let manager = wlr_virtual_pointer_manager_v1_create(display);
+        if manager.is_null() {
+            return None
+        }
+        let internal = InternalManager::build(builder);
+        wl_signal_add(&mut (*manager).events.new_virtual_pointer as *mut _ as _,
+                      (&mut internal.new_virtual_pointer_listener) as *mut _ as _);
+        Some(Manager { manager, internal })
+    }
+}
+
+impl Drop for Manager {
+    fn drop(&mut self) {
+        unsafe {
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                          wl_list_remove,
+                          &mut self.internal.new_virtual_pointer_listener.link as *mut _ as _);
+        }
+    }
+}
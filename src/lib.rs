@@ -11,6 +11,10 @@ pub extern crate wlroots_sys;
 extern crate wlroots_dehandle;
 #[cfg(feature = "unstable")]
 pub extern crate xkbcommon;
+#[cfg(feature = "testing")]
+extern crate wayland_client;
+#[cfg(feature = "testing")]
+extern crate wayland_protocols;
 
 #[cfg(feature = "unstable")]
 pub use wlroots_dehandle::wlroots_dehandle;
@@ -35,6 +39,8 @@ pub mod utils;
 pub mod xwayland;
 #[cfg(feature = "unstable")]
 pub mod backend;
+#[cfg(feature = "testing")]
+pub mod testing;
 
 pub use types::*;
 
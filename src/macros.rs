@@ -94,6 +94,9 @@ macro_rules! wlr_log {
 /// To highlight this fact, the body of the function must be prefixed with
 /// `unsafe`.
 ///
+/// Attributes written before a `$listener`, such as `#[cfg(...)]`, apply to
+/// its field and to both of its methods.
+///
 /// # Example
 /// ```rust,no_run,ignore
 /// #[macro_use] extern crate wlroots;
@@ -146,13 +149,13 @@ macro_rules! wlr_log {
 /// Passing a pointer of unsized data to C is UB, don't do it.
 macro_rules! wayland_listener {
     ($pub: vis $struct_name: ident, $data: ty, $([
-        $($listener: ident => $listener_func: ident :
+        $($(#[$attr: meta])* $listener: ident => $listener_func: ident :
           |$($func_arg:ident: $func_type:ty,)*| unsafe $body: block;)*])+) => {
         #[repr(C)]
         #[doc(hidden)]
         $pub struct $struct_name {
             data: $data,
-            $($($listener: $crate::wlroots_sys::wl_listener),*)*
+            $($($(#[$attr])* $listener: $crate::wlroots_sys::wl_listener),*)*
         }
 
         impl $struct_name {
@@ -160,7 +163,7 @@ macro_rules! wayland_listener {
                 use $crate::wlroots_sys::server::WAYLAND_SERVER_HANDLE;
                 Box::new($struct_name {
                     data,
-                    $($($listener: unsafe {
+                    $($($(#[$attr])* $listener: unsafe {
                         // NOTE Rationale for zeroed memory:
                         // * Need to pass a pointer to wl_list_init
                         // * The list is initialized by Wayland, which doesn't "drop"
@@ -175,12 +178,14 @@ macro_rules! wayland_listener {
                 })
             }
 
-            $($(pub(crate) unsafe extern "C" fn $listener(&mut self)
+            $($($(#[$attr])*
+                pub(crate) unsafe extern "C" fn $listener(&mut self)
                                                    -> *mut $crate::wlroots_sys::wl_listener {
                 &mut self.$listener as *mut _
             })*)*
 
-            $($(pub(crate) unsafe extern "C" fn $listener_func(listener:
+            $($($(#[$attr])*
+                pub(crate) unsafe extern "C" fn $listener_func(listener:
                                                         *mut $crate::wlroots_sys::wl_listener,
                                                         data: *mut $crate::libc::c_void) {
                 let manager: &mut $struct_name = &mut (*container_of!(listener,
@@ -201,6 +206,7 @@ macro_rules! wayland_listener_static {
      $(($manager: ident, $builder: ident):
        $([
            $(
+               $({$($(#[$field_attr: meta])* $field_name: ident: $field_type: ty),+})*
                $([$($extra_callback_name: ident: $extra_callback_type: ty),+])*
                ($fn_type: ty, $listener: ident, $builder_func: ident) => ($notify: ident, $callback: ident):
                |$($func_arg: ident: $func_type: ty,)*| unsafe $body: block;
@@ -218,6 +224,7 @@ macro_rules! wayland_listener_static {
             /// structure in order to utilize them (usually it's `compositor::Builder`).
             pub struct $builder {
                 $($(pub(crate) $callback: ::std::option::Option<$fn_type>,)*
+                  $($($(pub(crate) $field_name: ::std::option::Option<$field_type>,)*)*)*
                   $($($($extra_callback_name: ::std::option::Option<$extra_callback_type>,)*)*)*)*
             }
 
@@ -229,6 +236,13 @@ macro_rules! wayland_listener_static {
                         self.$callback = ::std::option::Option::Some($callback);
                         self
                     }
+                    $($(
+                        $(#[$field_attr])*
+                        pub fn $field_name(mut self, $field_name: $field_type) -> Self {
+                            self.$field_name = ::std::option::Option::Some($field_name);
+                            self
+                        }
+                    )*)*
                     $($(
                         /// Uses the provided callback as the receiver for the
                         /// event the type signature describes.
@@ -247,6 +261,7 @@ macro_rules! wayland_listener_static {
                 $($(
                     pub(crate) $listener: $crate::wlroots_sys::wl_listener,
                     $callback: ::std::option::Option<$fn_type>,
+                    $($($field_name: ::std::option::Option<$field_type>,)*)*
                     $($($extra_callback_name: ::std::option::Option<$extra_callback_type>),*)*
                 )*)*
             }
@@ -260,6 +275,7 @@ macro_rules! wayland_listener_static {
                                 next: ::std::ptr::null_mut()}},
                         notify: ::std::option::Option::None },
                     $callback: ::std::option::Option::None,
+                    $($($field_name: ::std::option::Option::None,)*)*
                     $($($extra_callback_name: ::std::option::Option::None),*)*
                 )*)*
             };
@@ -287,6 +303,9 @@ macro_rules! wayland_listener_static {
                             listener
                         };
                         $static_manager.$callback = builder.$callback;
+                        $($(
+                            $static_manager.$field_name = builder.$field_name;
+                        )*)*
                         $($(
                             $static_manager.$extra_callback_name = builder.$extra_callback_name;
                         )*)*
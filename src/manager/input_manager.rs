@@ -4,7 +4,7 @@
 
 use libc;
 
-use std::{env, panic, process::abort};
+use std::{env, panic, cell::Cell, process::abort};
 
 use wayland_sys::server::signal::wl_signal_add;
 use wlroots_sys::{wlr_input_device, wlr_input_device_type, wlr_keyboard_set_keymap,
@@ -14,11 +14,13 @@ use wlroots_sys::{wlr_input_device, wlr_input_device_type, wlr_keyboard_set_keym
 
 use {compositor,
      input::{self,
+             config::Rules,
              keyboard::{self, Keyboard, KeyboardWrapper},
              pointer::{self, Pointer, PointerWrapper},
              tablet_pad::{self, TabletPad, TabletPadWrapper},
              tablet_tool::{self, TabletTool, TabletToolWrapper},
              touch::{self, Touch, TouchWrapper}},
+     output,
      utils::{Handleable, safe_as_cstring}};
 
 /// Callback triggered when an input device is added.
@@ -73,6 +75,12 @@ pub type TabletPadAdded = fn (compositor_handle: compositor::Handle,
 wayland_listener_static! {
     static mut MANAGER;
     (Manager, Builder): [
+        {
+            /// The rules applied to each input device when it's added.
+            ///
+            /// See `input::config` for what they can do.
+            config: Rules
+        }
         // NOTE
         // This is a macro hack to add these as arguments to the builder.
         // The callbacks will be storted in the manager, but they'll have no
@@ -87,6 +95,9 @@ wayland_listener_static! {
         ]
         (InputAdded, add_listener, input_added) => (add_notify, input_added):
         |manager: &mut Manager, data: *mut libc::c_void,| unsafe {
+            // NOTE The rules are part of the manager, so `with_config` must
+            // not hand them out while it is borrowed here.
+            let rules_borrow = RulesBorrow::new();
             let compositor = match compositor::handle() {
                 Some(handle) => handle,
                 None => return
@@ -230,7 +241,12 @@ wayland_listener_static! {
                         }
                     }
                 }
-                manager.input_added.map(|f| f(compositor, &mut dev))
+                manager.input_added.map(|f| f(compositor, &mut dev));
+                // NOTE The rules come last so they can map the devices the
+                // callbacks attached to a cursor.
+                if let (Some(_), Some(config)) = (rules_borrow.as_ref(), manager.config.as_mut()) {
+                    config.device_added(&dev)
+                }
             }));
             match res {
                 Ok(_) => {},
@@ -247,6 +263,79 @@ wayland_listener_static! {
     ]
 }
 
+/// Run a function with the input rules given to `Builder::config`, e.g. to
+/// change them and then call `Rules::reapply`.
+///
+/// Returns `None` if no rules were given to the builder, or if they are
+/// already borrowed: by an outer call to this function, or by the manager
+/// while it calls the callbacks for a new input device.
+pub fn with_config<F, R>(runner: F) -> Option<R>
+    where F: FnOnce(&mut Rules) -> R
+{
+    let _borrow = RulesBorrow::new()?;
+    unsafe { MANAGER.config.as_mut().map(runner) }
+}
+
+/// Forgets the input devices that have been destroyed.
+///
+/// If the rules are borrowed they forget them the next time a device is added.
+pub(crate) unsafe fn device_removed() {
+    let _borrow = match RulesBorrow::new() {
+        Some(borrow) => borrow,
+        None => return
+    };
+    if let Some(ref mut config) = MANAGER.config {
+        config.devices_removed()
+    }
+}
+
+/// Sets up an input device that no backend announced, e.g. a virtual device
+/// created by a client, exactly like one a backend found.
+#[cfg(any(feature = "virtual_keyboard", feature = "virtual_pointer"))]
+pub(crate) unsafe fn add_device(device: *mut wlr_input_device) {
+    add_notify(&mut MANAGER.add_listener, device as *mut libc::c_void)
+}
+
+/// Maps the devices that the rules map to the output, now that it's there.
+///
+/// If the rules are borrowed the devices are mapped the next time the rules
+/// are applied to them.
+pub(crate) unsafe fn output_added(output: &output::Handle) {
+    let _borrow = match RulesBorrow::new() {
+        Some(borrow) => borrow,
+        None => return
+    };
+    if let Some(ref config) = MANAGER.config {
+        config.output_added(output)
+    }
+}
+
+thread_local! {
+    /// Whether the rules are borrowed, either by `with_config` or by the
+    /// manager while it is dispatching.
+    static RULES_BORROWED: Cell<bool> = Cell::new(false);
+}
+
+/// Marks the rules as borrowed until it is dropped.
+struct RulesBorrow;
+
+impl RulesBorrow {
+    /// Returns `None` if the rules are already borrowed.
+    fn new() -> Option<Self> {
+        if RULES_BORROWED.with(|borrowed| borrowed.replace(true)) {
+            None
+        } else {
+            Some(RulesBorrow)
+        }
+    }
+}
+
+impl Drop for RulesBorrow {
+    fn drop(&mut self) {
+        RULES_BORROWED.with(|borrowed| borrowed.set(false))
+    }
+}
+
 pub(crate) unsafe fn add_keyboard(dev: &mut input::Device) {
     // Set the XKB settings
     let rules = safe_as_cstring(env::var("XKB_DEFAULT_RULES").unwrap_or("".into()));
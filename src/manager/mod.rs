@@ -5,6 +5,7 @@ pub(crate) mod keyboard_handler;
 pub(crate) mod pointer_handler;
 pub(crate) mod touch_handler;
 pub(crate) mod output_handler;
+pub(crate) mod session_handler;
 pub(crate) mod xdg_shell_v6_manager;
 pub(crate) mod xdg_shell_v6_handler;
 pub(crate) mod xdg_shell_manager;
@@ -2,11 +2,14 @@
 
 use libc;
 use wayland_sys::server::WAYLAND_SERVER_HANDLE;
-use wlroots_sys::wlr_output;
+use wlroots_sys::{wlr_output, wlr_output_event_swap_buffers};
 
 use {compositor,
+     manager::output_manager,
      output::{self, Output, OutputState},
      utils::{HandleErr, Handleable}};
+#[cfg(feature = "presentation_time")]
+use extensions::presentation;
 
 #[allow(unused_variables)]
 pub trait Handler {
@@ -35,6 +38,17 @@ pub trait Handler {
                     compositor_handle: compositor::Handle,
                     output_handle: output::Handle) {}
 
+    /// Called before the next frame after `Output::set_adaptive_sync`, or
+    /// when adaptive sync was enabled or disabled on the output by the
+    /// backend, e.g. when the mode changes.
+    ///
+    /// Check `Output::adaptive_sync_status` for the result, it stays
+    /// `Disabled` if the output couldn't enable it.
+    #[cfg(feature = "adaptive_sync")]
+    fn on_adaptive_sync_change(&mut self,
+                               compositor_handle: compositor::Handle,
+                               output_handle: output::Handle) {}
+
     /// Called every time the buffers are swapped on an output.
     fn on_buffers_swapped(&mut self,
                           compositor_handle: compositor::Handle,
@@ -73,6 +87,8 @@ wayland_listener!(pub(crate) UserOutput, (Output, Box<Handler>), [
                     }
                 }
             }
+            output_manager::output_removed(&output.weak_reference());
+            output::mirror_output_removed(output_ptr);
         }
         ffi_dispatch!(WAYLAND_SERVER_HANDLE,
                       wl_list_remove,
@@ -102,12 +118,23 @@ wayland_listener!(pub(crate) UserOutput, (Output, Box<Handler>), [
         Box::from_raw((*output_data).output as *mut UserOutput);
     };
     frame_listener => frame_notify: |this: &mut UserOutput, _output: *mut libc::c_void,| unsafe {
-        let (ref output, ref mut manager) = this.data;
+        let (ref mut output, ref mut manager) = this.data;
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
         };
 
+        // NOTE wlroots has no event for this, and calling the handler from
+        // `Output::set_adaptive_sync` could re-enter it, so it's checked here.
+        #[cfg(feature = "adaptive_sync")]
+        {
+            if output.update_adaptive_sync_status() {
+                manager.on_adaptive_sync_change(compositor.clone(), output.weak_reference());
+            }
+        }
+        if output::render_mirror_target(compositor.clone(), output) {
+            return
+        }
         manager.on_frame(compositor, output.weak_reference());
     };
     mode_listener => mode_notify: |this: &mut UserOutput, _output: *mut libc::c_void,|
@@ -149,15 +176,17 @@ wayland_listener!(pub(crate) UserOutput, (Output, Box<Handler>), [
         manager.on_transform(compositor, output.weak_reference());
     };
     swap_buffers_listener => swap_buffers_notify: |this: &mut UserOutput,
-                                                   _output: *mut libc::c_void,|
+                                                   data: *mut libc::c_void,|
     unsafe {
-
-        let (ref output, ref mut manager) = this.data;
+        let event = data as *mut wlr_output_event_swap_buffers;
+        let (ref mut output, ref mut manager) = this.data;
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
         };
 
+        #[cfg(feature = "presentation_time")]
+        presentation::buffers_swapped(output, (*event).when);
         manager.on_buffers_swapped(compositor, output.weak_reference());
     };
     need_swap_listener => need_swap_notify: |this: &mut UserOutput, _output: *mut libc::c_void,|
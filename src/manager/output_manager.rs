@@ -1,13 +1,14 @@
 //! Manager that is called when an output is created or destroyed.
 
-use std::{marker::PhantomData, panic};
+use std::{cell::{Cell, RefCell}, marker::PhantomData, panic};
 
 use libc;
 use wayland_sys::server::signal::wl_signal_add;
 use wlroots_sys::wlr_output;
 
 use {compositor,
-     output::{self, Output, OutputState, UserOutput},
+     manager::input_manager,
+     output::{self, Output, OutputState, UserOutput, profiles::Profiles},
      utils::Handleable};
 
 
@@ -58,8 +59,18 @@ pub type OutputAdded = fn(compositor_handle: compositor::Handle,
 wayland_listener_static! {
     static mut MANAGER;
     (Manager, Builder): [
+        {
+            /// The output profiles to apply whenever an output is added or
+            /// removed.
+            ///
+            /// See `output::profiles` for how a profile is chosen.
+            profiles: Profiles
+        }
         (OutputAdded, add_listener, output_added) => (add_notify, add_callback):
         |manager: &mut Manager, data: *mut libc::c_void,| unsafe {
+            // NOTE The profiles are part of the manager, so `with_profiles`
+            // must not hand them out while it is borrowed here.
+            let profiles_borrow = ProfilesBorrow::new();
             let data = data as *mut wlr_output;
             let output = Output::new(data as *mut wlr_output);
             // NOTE
@@ -112,7 +123,76 @@ wayland_listener_static! {
                               output.on_destroy_listener() as _);
                 let output_data = (*data).data as *mut OutputState;
                 (*output_data).output = Box::into_raw(output);
+                let handle = output::Handle::from_ptr(data);
+                OUTPUTS.with(|outputs| outputs.borrow_mut().push(handle.clone()));
+                input_manager::output_added(&handle);
+                if let (Some(_), Some(profiles)) = (profiles_borrow, manager.profiles.as_mut()) {
+                    profiles.output_added(handle);
+                }
             }
         };
     ]
 }
+
+thread_local! {
+    /// The outputs built by the `output_added` callback that still exist.
+    static OUTPUTS: RefCell<Vec<output::Handle>> = RefCell::new(Vec::new());
+
+    /// Whether the profiles are borrowed, either by `with_profiles` or by
+    /// the manager while it is dispatching.
+    static PROFILES_BORROWED: Cell<bool> = Cell::new(false);
+}
+
+/// Marks the profiles as borrowed until it is dropped.
+struct ProfilesBorrow;
+
+impl ProfilesBorrow {
+    /// Returns `None` if the profiles are already borrowed.
+    fn new() -> Option<Self> {
+        if PROFILES_BORROWED.with(|borrowed| borrowed.replace(true)) {
+            None
+        } else {
+            Some(ProfilesBorrow)
+        }
+    }
+}
+
+impl Drop for ProfilesBorrow {
+    fn drop(&mut self) {
+        PROFILES_BORROWED.with(|borrowed| borrowed.set(false))
+    }
+}
+
+/// Get handles to all the outputs built by the `output_added` callback that
+/// haven't been destroyed.
+pub(crate) fn outputs() -> Vec<output::Handle> {
+    OUTPUTS.with(|outputs| outputs.borrow().clone())
+}
+
+/// Run a function with the output profiles given to `Builder::profiles`,
+/// e.g. to save the current setup as a new profile.
+///
+/// Returns `None` if no profiles were given to the builder, or if they are
+/// already borrowed: by an outer call to this function, or by the manager
+/// while it calls the `output_added` callback or applies a profile.
+pub fn with_profiles<F, R>(runner: F) -> Option<R>
+    where F: FnOnce(&mut Profiles) -> R
+{
+    let _borrow = ProfilesBorrow::new()?;
+    unsafe { MANAGER.profiles.as_mut().map(runner) }
+}
+
+/// Forgets the output that's being destroyed and re-applies the output
+/// profiles without it.
+pub(crate) unsafe fn output_removed(output: &output::Handle) {
+    OUTPUTS.with(|outputs| outputs.borrow_mut().retain(|handle| handle != output));
+    // NOTE If the output is destroyed while the profiles are borrowed they
+    // keep the dead handle, which is skipped when the next profile is applied.
+    let _borrow = match ProfilesBorrow::new() {
+        Some(borrow) => borrow,
+        None => return
+    };
+    if let Some(ref mut profiles) = MANAGER.profiles {
+        profiles.output_removed(output)
+    }
+}
@@ -0,0 +1,46 @@
+//! Handler for the session becoming active or inactive, e.g. when switching
+//! virtual terminals.
+
+use libc;
+use wlroots_sys::wlr_session;
+
+use {compositor, manager::output_manager};
+
+#[allow(unused_variables)]
+pub trait Handler {
+    /// Called when the session becomes active again, e.g. when switching back
+    /// to the virtual terminal of the compositor.
+    ///
+    /// Every output has already been damaged, so it's redrawn on its next frame.
+    fn activated(&mut self, compositor_handle: compositor::Handle) {}
+
+    /// Called when the session becomes inactive, e.g. when switching to
+    /// another virtual terminal.
+    ///
+    /// Until the session is activated again nothing can be rendered and no
+    /// input is received, so keys and buttons that are held down won't be
+    /// released. Forget about them here.
+    fn deactivated(&mut self, compositor_handle: compositor::Handle) {}
+}
+
+impl Handler for () {}
+
+wayland_listener!(pub(crate) SessionListener, (*mut wlr_session, Box<Handler>), [
+    active_listener => active_notify: |this: &mut SessionListener, _data: *mut libc::c_void,|
+    unsafe {
+        let (session, ref mut handler) = this.data;
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+        if (*session).active {
+            // NOTE Whatever was on screen belongs to whoever had it before.
+            for output in output_manager::outputs() {
+                let _ = output.run(|output| output.damage().add_whole());
+            }
+            handler.activated(compositor)
+        } else {
+            handler.deactivated(compositor)
+        }
+    };
+]);
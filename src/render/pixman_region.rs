@@ -1,7 +1,7 @@
 use std::mem;
 
 use libc::{c_int, c_uint};
-use wlroots_sys::{pixman_region32_fini, pixman_region32_init,
+use wlroots_sys::{pixman_region32_fini, pixman_region32_init, pixman_region32_not_empty,
                   pixman_region32_t, pixman_region32_union_rect};
 
 /// A pixman region, used for damage tracking.
@@ -28,6 +28,11 @@ impl PixmanRegion {
             pixman_region32_union_rect(region_ptr, region_ptr, x, y, width, height);
         }
     }
+
+    /// Determines if the region covers nothing.
+    pub fn is_empty(&self) -> bool {
+        unsafe { pixman_region32_not_empty(&self.region as *const _ as *mut _) == 0 }
+    }
 }
 
 impl Drop for PixmanRegion {
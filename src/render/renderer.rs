@@ -8,9 +8,15 @@ use wlroots_sys::{wl_shm_format, wlr_backend, wlr_backend_get_renderer,
                   wlr_render_ellipse_with_matrix, wlr_render_quad_with_matrix, wlr_render_rect,
                   wlr_render_texture, wlr_render_texture_with_matrix, wlr_renderer,
                   wlr_renderer_begin, wlr_renderer_clear, wlr_renderer_destroy, wlr_renderer_end,
-                  wlr_texture_from_pixels, wlr_texture_destroy, wlr_renderer_scissor};
+                  wlr_renderer_read_pixels, wlr_texture_from_pixels, wlr_texture_destroy,
+                  wlr_renderer_scissor,
+                  wlr_renderer_read_pixels_flags::WLR_RENDERER_READ_PIXELS_Y_INVERT};
+#[cfg(feature = "viewporter")]
+use wlroots_sys::{wlr_fbox, wlr_render_subtexture_with_matrix};
 
-use {area::Area, output::Output, render::{PixmanRegion, texture::Texture}};
+use {area::Area, output::{self, Output}, render::{PixmanRegion, texture::Texture}};
+#[cfg(feature = "viewporter")]
+use area::FloatArea;
 
 /// A generic interface for rendering to the screen.
 ///
@@ -119,6 +125,44 @@ impl<'output> Renderer<'output> {
         }
     }
 
+    /// Reads the pixels of the buffer being rendered to into `data`, starting
+    /// at (`src_x`, `src_y`).
+    ///
+    /// `data` must hold at least `stride * height` bytes.
+    ///
+    /// Returns whether the pixels were read upside down, or `None` if they
+    /// could not be read.
+    pub fn read_pixels(&mut self,
+                       format: wl_shm_format,
+                       stride: u32,
+                       width: u32,
+                       height: u32,
+                       src_x: u32,
+                       src_y: u32,
+                       data: &mut [u8])
+                       -> Option<bool> {
+        if data.len() < stride as usize * height as usize {
+            return None
+        }
+        unsafe {
+            let mut flags = 0;
+            if !wlr_renderer_read_pixels(self.renderer,
+                                         format,
+                                         &mut flags,
+                                         stride,
+                                         width,
+                                         height,
+                                         src_x,
+                                         src_y,
+                                         0,
+                                         0,
+                                         data.as_mut_ptr() as _) {
+                return None
+            }
+            Some(flags & WLR_RENDERER_READ_PIXELS_Y_INVERT as u32 != 0)
+        }
+    }
+
     pub fn clear(&mut self, float: [f32; 4]) {
         unsafe { wlr_renderer_clear(self.renderer, float.as_ptr()) }
     }
@@ -163,6 +207,28 @@ impl<'output> Renderer<'output> {
         }
     }
 
+    /// Renders part of the texture using the provided matrix.
+    ///
+    /// Only the `source` box of the texture, in buffer pixels, is sampled and
+    /// stretched over the area the matrix maps to. Use this with
+    /// `Surface::buffer_source_box` for surfaces cropped with `wp_viewporter`.
+    #[cfg(feature = "viewporter")]
+    pub fn render_subtexture_with_matrix(&mut self,
+                                         texture: &Texture,
+                                         source: FloatArea,
+                                         matrix: [f32; 9],
+                                         alpha: c_float)
+                                         -> bool {
+        unsafe {
+            let source: wlr_fbox = source.into();
+            wlr_render_subtexture_with_matrix(self.renderer,
+                                              texture.as_ptr(),
+                                              &source,
+                                              matrix.as_ptr(),
+                                              alpha)
+        }
+    }
+
     /// Defines a scissor box. Only pixels that lie within the scissor box can be
     /// modified by drawing functions.
     ///
@@ -194,6 +260,7 @@ impl<'output> Renderer<'output> {
 impl<'output> Drop for Renderer<'output> {
     fn drop(&mut self) {
         unsafe {
+            output::capture_mirror_source(self);
             if let Some((mut damage, when)) = self.damage.take() {
                 self.output.swap_buffers(Some(when), Some(&mut damage));
             } else {
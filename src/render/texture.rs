@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use libc::c_int;
-use wlroots_sys::{wl_shm_format, wlr_texture, wlr_texture_get_size};
+use wlroots_sys::{wl_shm_format, wlr_texture, wlr_texture_get_size, wlr_texture_write_pixels};
 
 /// Wrapper around wl_shm_format, to make it easier and nicer to type.
 #[repr(u32)]
@@ -104,4 +104,37 @@ impl <'surface> Texture<'surface> {
             (width, height)
         }
     }
+
+    /// Writes pixels into the texture at (`dst_x`, `dst_y`), starting at
+    /// (`src_x`, `src_y`) in `data`. They must be in the format the texture
+    /// was created with.
+    ///
+    /// `data` must hold at least `stride * height` bytes.
+    ///
+    /// Returns `false` if the pixels could not be written.
+    pub fn write_pixels(&mut self,
+                        stride: u32,
+                        width: u32,
+                        height: u32,
+                        src_x: u32,
+                        src_y: u32,
+                        dst_x: u32,
+                        dst_y: u32,
+                        data: &[u8])
+                        -> bool {
+        if data.len() < stride as usize * height as usize {
+            return false
+        }
+        unsafe {
+            wlr_texture_write_pixels(self.texture,
+                                     stride,
+                                     width,
+                                     height,
+                                     src_x,
+                                     src_y,
+                                     dst_x,
+                                     dst_y,
+                                     data.as_ptr() as _)
+        }
+    }
 }
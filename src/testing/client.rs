@@ -0,0 +1,297 @@
+//! A minimal Wayland client for driving a test compositor.
+
+use std::{env, fs, process, cell::RefCell, io::Write, os::unix::io::AsRawFd, rc::Rc,
+          sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}}};
+
+use wayland_client::{Display, EventQueue, GlobalManager, Proxy,
+                     protocol::{wl_buffer::{self, RequestsTrait as BufferRequests, WlBuffer},
+                                wl_compositor::{RequestsTrait as CompositorRequests,
+                                                WlCompositor},
+                                wl_shm::{self, RequestsTrait as ShmRequests, WlShm},
+                                wl_shm_pool::RequestsTrait as ShmPoolRequests,
+                                wl_surface::{RequestsTrait as SurfaceRequests, WlSurface}}};
+use wayland_protocols::xdg_shell::client::{xdg_surface::{self,
+                                                         RequestsTrait as XdgSurfaceRequests,
+                                                         XdgSurface},
+                                           xdg_toplevel::{self,
+                                                          RequestsTrait as XdgToplevelRequests,
+                                                          XdgToplevel},
+                                           xdg_wm_base::{self,
+                                                         RequestsTrait as XdgWmBaseRequests,
+                                                         XdgWmBase}};
+
+use testing::is_readable;
+
+/// Used to give each shm file of this process a unique name.
+static SHM_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// The connection of a client, shared with the `Harness` so it can dispatch it.
+pub(crate) struct Connection {
+    display: Display,
+    event_queue: RefCell<EventQueue>
+}
+
+/// A Wayland client connected to the test compositor, created with
+/// `Harness::connect`.
+///
+/// Requests are only sent, and events only processed, by `Harness::step`.
+/// Dropping the client disconnects it.
+pub struct Client {
+    pub(crate) connection: Rc<Connection>,
+    globals: GlobalManager,
+    compositor: Option<Proxy<WlCompositor>>,
+    shm: Option<Proxy<WlShm>>,
+    wm_base: Option<Proxy<XdgWmBase>>
+}
+
+/// A configure sequence the compositor sent for a toplevel.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Configure {
+    /// The serial to pass to `Toplevel::ack_configure`.
+    pub serial: u32,
+    /// The suggested width, or 0 if the client should decide.
+    pub width: i32,
+    /// The suggested height, or 0 if the client should decide.
+    pub height: i32,
+    /// The states of the toplevel, e.g. activated or maximized.
+    pub states: Vec<xdg_toplevel::State>
+}
+
+#[derive(Debug, Default)]
+struct ToplevelState {
+    /// The last `xdg_toplevel.configure`, which only applies once the
+    /// `xdg_surface.configure` that ends the sequence is received.
+    pending: Option<(i32, i32, Vec<xdg_toplevel::State>)>,
+    configures: Vec<Configure>,
+    close_requested: bool
+}
+
+/// An xdg toplevel created by a test client.
+pub struct Toplevel {
+    surface: Proxy<WlSurface>,
+    xdg_surface: Proxy<XdgSurface>,
+    toplevel: Proxy<XdgToplevel>,
+    state: Arc<Mutex<ToplevelState>>
+}
+
+impl Connection {
+    /// Send the buffered requests to the compositor.
+    pub(crate) fn flush(&self) {
+        // NOTE If the compositor disconnected the client the error shows up
+        // when the test uses the client, there's nothing to do about it here.
+        let _ = self.display.flush();
+    }
+
+    /// Read and process the events the compositor sent, without waiting.
+    ///
+    /// Returns how many events were processed.
+    pub(crate) fn dispatch(&self) -> u32 {
+        let mut event_queue = self.event_queue.borrow_mut();
+        if let Some(guard) = event_queue.prepare_read() {
+            if is_readable(self.display.get_connection_fd()) {
+                let _ = guard.read_events();
+            }
+        }
+        event_queue.dispatch_pending().unwrap_or(0)
+    }
+}
+
+impl Client {
+    pub(crate) fn connect(socket_name: &str) -> Self {
+        let (display, event_queue) = Display::connect_to_name(socket_name)
+            .unwrap_or_else(|err| panic!("Could not connect to {}: {:?}", socket_name, err));
+        let globals = GlobalManager::new(&display);
+        let connection = Rc::new(Connection { display,
+                                              event_queue: RefCell::new(event_queue) });
+        Client { connection,
+                 globals,
+                 compositor: None,
+                 shm: None,
+                 wm_base: None }
+    }
+
+    /// Get the names and versions of the globals the compositor advertised.
+    pub fn globals(&self) -> Vec<(String, u32)> {
+        self.globals.list().into_iter()
+            .map(|(_, interface, version)| (interface, version))
+            .collect()
+    }
+
+    /// Create a new xdg toplevel.
+    ///
+    /// The surface is committed without a buffer, so the compositor will send
+    /// the initial configure on the next `Harness::step`.
+    ///
+    /// # Panicking
+    /// Panics if the compositor doesn't advertise `xdg_wm_base`, i.e. it was
+    /// built without `compositor::Builder::xdg_shell_manager`.
+    pub fn create_toplevel(&mut self) -> Toplevel {
+        let surface = self.compositor()
+            .create_surface(|surface| surface.implement(|_, _| {}))
+            .expect("Could not create surface");
+        let state = Arc::new(Mutex::new(ToplevelState::default()));
+        let xdg_surface_state = state.clone();
+        let xdg_surface = self.wm_base()
+            .get_xdg_surface(&surface, |xdg_surface| {
+                xdg_surface.implement(move |event, _| {
+                    let xdg_surface::Event::Configure { serial } = event;
+                    let mut state = xdg_surface_state.lock().unwrap();
+                    let (width, height, states) = state.pending.take()
+                        .unwrap_or((0, 0, Vec::new()));
+                    state.configures.push(Configure { serial, width, height, states })
+                })
+            })
+            .expect("Could not create xdg surface");
+        let toplevel_state = state.clone();
+        let toplevel = xdg_surface
+            .get_toplevel(|toplevel| {
+                toplevel.implement(move |event, _| {
+                    let mut state = toplevel_state.lock().unwrap();
+                    match event {
+                        xdg_toplevel::Event::Configure { width, height, states } => {
+                            let states = states.chunks(4)
+                                .filter(|bytes| bytes.len() == 4)
+                                .map(|bytes| {
+                                         bytes[0] as u32 | (bytes[1] as u32) << 8 |
+                                         (bytes[2] as u32) << 16 | (bytes[3] as u32) << 24
+                                     })
+                                .filter_map(xdg_toplevel::State::from_raw)
+                                .collect();
+                            state.pending = Some((width, height, states))
+                        }
+                        xdg_toplevel::Event::Close => state.close_requested = true
+                    }
+                })
+            })
+            .expect("Could not create xdg toplevel");
+        surface.commit();
+        Toplevel { surface, xdg_surface, toplevel, state }
+    }
+
+    fn compositor(&mut self) -> &Proxy<WlCompositor> {
+        if self.compositor.is_none() {
+            self.compositor = Some(self.globals
+                                       .instantiate_auto(|compositor| {
+                                                             compositor.implement(|_, _| {})
+                                                         })
+                                       .expect("The compositor has no wl_compositor global"));
+        }
+        self.compositor.as_ref().unwrap()
+    }
+
+    fn shm(&mut self) -> &Proxy<WlShm> {
+        if self.shm.is_none() {
+            self.shm = Some(self.globals
+                                .instantiate_auto(|shm| shm.implement(|_, _| {}))
+                                .expect("The compositor has no wl_shm global, \
+                                         enable it with compositor::Builder::wl_shm"));
+        }
+        self.shm.as_ref().unwrap()
+    }
+
+    fn wm_base(&mut self) -> &Proxy<XdgWmBase> {
+        if self.wm_base.is_none() {
+            self.wm_base = Some(self.globals
+                                    .instantiate_auto(|wm_base| {
+                                        wm_base.implement(|event, wm_base: Proxy<XdgWmBase>| {
+                                            let xdg_wm_base::Event::Ping { serial } = event;
+                                            wm_base.pong(serial)
+                                        })
+                                    })
+                                    .expect("The compositor has no xdg_wm_base global, enable \
+                                             it with compositor::Builder::xdg_shell_manager"));
+        }
+        self.wm_base.as_ref().unwrap()
+    }
+
+    /// Create a buffer of the size filled with a single ARGB8888 color.
+    fn create_buffer(&mut self, width: i32, height: i32, color: u32) -> Proxy<WlBuffer> {
+        let stride = width * 4;
+        let size = stride * height;
+        let path = env::temp_dir().join(format!("wlroots-rs-test-shm-{}-{}",
+                                                process::id(),
+                                                SHM_FILE_COUNT.fetch_add(1, Ordering::SeqCst)));
+        let mut file = fs::OpenOptions::new().read(true)
+                                             .write(true)
+                                             .create_new(true)
+                                             .open(&path)
+                                             .expect("Could not create shm file");
+        // The fd is all the compositor needs, it doesn't need a name.
+        let _ = fs::remove_file(&path);
+        let pixel = [color as u8, (color >> 8) as u8, (color >> 16) as u8, (color >> 24) as u8];
+        let pixels = pixel.iter().cloned().cycle().take(size as usize).collect::<Vec<_>>();
+        file.write_all(&pixels).expect("Could not write to shm file");
+        file.flush().expect("Could not write to shm file");
+        let pool = self.shm()
+            .create_pool(file.as_raw_fd(), size, |pool| pool.implement(|_, _| {}))
+            .expect("Could not create shm pool");
+        let buffer = pool.create_buffer(0,
+                                        width,
+                                        height,
+                                        stride,
+                                        wl_shm::Format::Argb8888,
+                                        |buffer| {
+                                            buffer.implement(|event, buffer: Proxy<WlBuffer>| {
+                                                let wl_buffer::Event::Release = event;
+                                                buffer.destroy()
+                                            })
+                                        })
+            .expect("Could not create shm buffer");
+        // The buffer keeps the memory mapped, the pool isn't needed anymore.
+        pool.destroy();
+        buffer
+    }
+}
+
+impl Toplevel {
+    /// Get all the configure sequences the compositor sent so far, oldest first.
+    pub fn configures(&self) -> Vec<Configure> {
+        self.state.lock().unwrap().configures.clone()
+    }
+
+    /// Whether the compositor asked the toplevel to close.
+    pub fn close_requested(&self) -> bool {
+        self.state.lock().unwrap().close_requested
+    }
+
+    /// Acknowledge the configure sequence with the serial.
+    ///
+    /// The state is applied by the compositor with the next commit.
+    pub fn ack_configure(&mut self, serial: u32) {
+        self.xdg_surface.ack_configure(serial)
+    }
+
+    /// Set the title of the toplevel.
+    pub fn set_title(&mut self, title: &str) {
+        self.toplevel.set_title(title.into())
+    }
+
+    /// Set the application id of the toplevel.
+    pub fn set_app_id(&mut self, app_id: &str) {
+        self.toplevel.set_app_id(app_id.into())
+    }
+
+    /// Attach a new shm buffer of the size, filled with a single ARGB8888
+    /// color, damage all of it and commit.
+    ///
+    /// This maps the toplevel if the initial configure was acknowledged.
+    pub fn attach_buffer(&mut self, client: &mut Client, width: i32, height: i32, color: u32) {
+        let buffer = client.create_buffer(width, height, color);
+        self.surface.attach(Some(&buffer), 0, 0);
+        self.surface.damage(0, 0, width, height);
+        self.surface.commit()
+    }
+
+    /// Commit the pending state of the surface, e.g. after an `ack_configure`.
+    pub fn commit(&mut self) {
+        self.surface.commit()
+    }
+}
+
+impl Drop for Toplevel {
+    fn drop(&mut self) {
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy()
+    }
+}
@@ -0,0 +1,227 @@
+//! Utilities for testing compositor logic without a display or real clients.
+//!
+//! [`build`](./fn.build.html) creates a headless `Compositor` whose Wayland
+//! socket lives in a private runtime directory, and [`run`](./fn.run.html)
+//! runs a test against it. The test drives the compositor and any number of
+//! in-process [`Client`](./struct.Client.html)s with
+//! [`Harness::step`](./struct.Harness.html#method.step) instead of
+//! entering the event loop.
+//!
+//! Only one test compositor can exist in a process at a time, so `build`
+//! waits for the compositor of any other test to be dropped first. Tests
+//! using the harness can therefore run on several threads, but they won't
+//! run in parallel.
+//!
+//! ```rust,ignore
+//! let compositor = testing::build(|builder| {
+//!                                     builder.wl_shm(true)
+//!                                            .xdg_shell_manager(xdg_shell_builder)
+//!                                 },
+//!                                 ());
+//! testing::run(compositor, |harness| {
+//!     let mut client = harness.connect();
+//!     let mut toplevel = client.create_toplevel();
+//!     harness.step();
+//!     let configure = toplevel.configures().pop().expect("No configure was sent");
+//!     toplevel.ack_configure(configure.serial);
+//!     toplevel.attach_buffer(&mut client, 100, 100, 0xff_00_00_ff);
+//!     harness.step();
+//! });
+//! ```
+//!
+//! Only available with the `testing` feature.
+
+mod client;
+
+pub use self::client::*;
+
+use std::{cell::RefCell, env, fs, io, process, thread, any::Any,
+          os::unix::fs::PermissionsExt, path::PathBuf, rc::{Rc, Weak},
+          sync::atomic::{AtomicBool, Ordering}, time::Duration};
+
+use libc;
+use wayland_sys::server::{wl_display, wl_event_loop, WAYLAND_SERVER_HANDLE};
+
+use {backend::{Backend, Headless},
+     compositor::{self, Compositor}};
+
+/// How many rounds `Harness::step` runs before it decides the compositor and
+/// its clients will never become idle.
+const MAX_STEP_ROUNDS: usize = 1000;
+
+/// Whether a test compositor exists. There can only be one compositor in a
+/// process, and `XDG_RUNTIME_DIR` is process wide as well.
+static HARNESS_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    /// The lock on `HARNESS_ACTIVE` held by the test running on this thread,
+    /// from `build` until the end of `run`.
+    static HARNESS_LOCK: RefCell<Option<HarnessLock>> = RefCell::new(None);
+}
+
+/// Releases `HARNESS_ACTIVE` when dropped, including when a test panics.
+struct HarnessLock;
+
+impl HarnessLock {
+    fn acquire() -> Self {
+        while HARNESS_ACTIVE.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                            .is_err() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        HarnessLock
+    }
+}
+
+impl Drop for HarnessLock {
+    fn drop(&mut self) {
+        HARNESS_ACTIVE.store(false, Ordering::Release);
+    }
+}
+
+/// Handle to a compositor running a test.
+///
+/// Passed to the test function given to [`run`](./fn.run.html).
+pub struct Harness {
+    display: *mut wl_display,
+    event_loop: *mut wl_event_loop,
+    socket_name: String,
+    headless: Headless,
+    connections: Vec<Weak<Connection>>
+}
+
+/// Builds a headless compositor listening on a private socket, with the
+/// `compositor::Builder` set up by `configure`.
+///
+/// If another test is using a compositor this waits until it's done, as the
+/// builder can only be created once there's no other compositor.
+///
+/// The socket is created in a fresh runtime directory for this process
+/// (`XDG_RUNTIME_DIR` is changed to point to it), so tests never connect to
+/// or disturb a running session.
+///
+/// # Panicking
+/// Panics if the runtime directory can't be created, if this thread already
+/// built a test compositor that wasn't run, or for any of the reasons
+/// `compositor::Builder::build_headless` would.
+pub fn build<F, D>(configure: F, data: D) -> Compositor
+    where F: FnOnce(compositor::Builder) -> compositor::Builder,
+          D: Any + 'static
+{
+    HARNESS_LOCK.with(|lock| {
+        let mut lock = lock.borrow_mut();
+        assert!(lock.is_none(), "A test compositor was already built on this thread");
+        *lock = Some(HarnessLock::acquire());
+    });
+    let runtime_dir = runtime_dir();
+    create_runtime_dir(&runtime_dir)
+        .unwrap_or_else(|err| panic!("Could not create {}: {}", runtime_dir.display(), err));
+    env::set_var("XDG_RUNTIME_DIR", &runtime_dir);
+    configure(compositor::Builder::new()).build_headless(data)
+}
+
+/// Starts the compositor and runs the test against it.
+///
+/// The compositor is dropped and its runtime directory removed once the
+/// test returns, after which the next test can build its compositor. Panics
+/// in the test or in the compositor callbacks are propagated.
+///
+/// # Panicking
+/// Panics if the compositor wasn't built with [`build`](./fn.build.html)
+/// (or otherwise with the headless backend).
+pub fn run<F>(compositor: Compositor, test: F)
+    where F: FnOnce(&mut Harness)
+{
+    compositor.run_with(|compositor| {
+        let headless = match *compositor.backend() {
+            Backend::Headless(ref headless) => Headless { backend: headless.backend },
+            _ => panic!("The test compositor must use the headless backend")
+        };
+        let mut harness = Harness { display: compositor.display,
+                                    event_loop: compositor.event_loop,
+                                    socket_name: compositor.socket_name().into(),
+                                    headless,
+                                    connections: Vec::new() };
+        test(&mut harness);
+        // Let the compositor see the clients the test dropped disconnect.
+        harness.step_server();
+    });
+    let _ = fs::remove_dir_all(runtime_dir());
+    HARNESS_LOCK.with(|lock| lock.borrow_mut().take());
+}
+
+impl Harness {
+    /// Connect a new client to the compositor.
+    ///
+    /// The globals of the compositor have been announced to the client by the
+    /// time this returns.
+    pub fn connect(&mut self) -> Client {
+        let client = Client::connect(&self.socket_name);
+        self.connections.push(Rc::downgrade(&client.connection));
+        self.step();
+        client
+    }
+
+    /// Get the headless backend, e.g. to add outputs or fake input devices.
+    pub fn headless(&self) -> &Headless {
+        &self.headless
+    }
+
+    /// Dispatch the compositor and all connected clients until neither has
+    /// anything left to do.
+    ///
+    /// Requests made by the clients since the last step are processed by the
+    /// compositor, and the events it sends are processed by the clients.
+    ///
+    /// # Panicking
+    /// Panics if the compositor and the clients keep each other busy, e.g.
+    /// because a client re-commits on every frame callback.
+    pub fn step(&mut self) {
+        self.connections.retain(|connection| connection.upgrade().is_some());
+        for _ in 0..MAX_STEP_ROUNDS {
+            let connections = self.connections.iter()
+                .filter_map(|connection| connection.upgrade())
+                .collect::<Vec<_>>();
+            for connection in &connections {
+                connection.flush();
+            }
+            let mut progressed = self.step_server();
+            for connection in &connections {
+                progressed |= connection.dispatch() > 0;
+            }
+            if !progressed {
+                return
+            }
+        }
+        panic!("The compositor did not become idle after {} rounds", MAX_STEP_ROUNDS)
+    }
+
+    /// Dispatch the compositor once, without waiting.
+    ///
+    /// Returns whether there was anything to dispatch.
+    fn step_server(&mut self) -> bool {
+        unsafe {
+            let fd = ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_loop_get_fd, self.event_loop);
+            let ready = is_readable(fd);
+            // NOTE Always dispatch so idle sources run too.
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_event_loop_dispatch, self.event_loop, 0);
+            ffi_dispatch!(WAYLAND_SERVER_HANDLE, wl_display_flush_clients, self.display);
+            ready
+        }
+    }
+}
+
+/// Whether there is something to read on the file descriptor, without waiting.
+pub(crate) fn is_readable(fd: libc::c_int) -> bool {
+    let mut poll_fd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    unsafe { libc::poll(&mut poll_fd, 1, 0) > 0 && poll_fd.revents & libc::POLLIN != 0 }
+}
+
+fn runtime_dir() -> PathBuf {
+    env::temp_dir().join(format!("wlroots-rs-test-{}", process::id()))
+}
+
+fn create_runtime_dir(path: &PathBuf) -> io::Result<()> {
+    fs::create_dir_all(path)?;
+    // The XDG base directory spec requires the runtime directory to be private.
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))
+}
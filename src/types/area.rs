@@ -5,6 +5,8 @@ use libc::{c_double, c_float, c_int};
 
 use wlroots_sys::{wl_output_transform, wlr_box, wlr_box_closest_point, wlr_box_contains_point,
                   wlr_box_empty, wlr_box_intersection, wlr_box_rotated_bounds, wlr_box_transform};
+#[cfg(feature = "viewporter")]
+use wlroots_sys::wlr_fbox;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 /// Result of applying an intersection of two `Area`s.
@@ -68,6 +70,54 @@ impl Into<wlr_box> for Area {
     }
 }
 
+#[cfg(feature = "viewporter")]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+/// Like `Area`, but with sub-pixel precision. Used for the `wlr_fbox` type.
+///
+/// e.g. the part of a buffer a surface shows can start in the middle of a
+/// pixel when it's cropped with `wp_viewporter`.
+pub struct FloatArea {
+    pub x: c_double,
+    pub y: c_double,
+    pub width: c_double,
+    pub height: c_double
+}
+
+#[cfg(feature = "viewporter")]
+impl FloatArea {
+    pub fn new(x: c_double, y: c_double, width: c_double, height: c_double) -> Self {
+        FloatArea { x, y, width, height }
+    }
+
+    /// Construct a `FloatArea` from a `wlr_fbox`.
+    pub fn from_fbox(fbox: wlr_fbox) -> Self {
+        FloatArea { x: fbox.x,
+                    y: fbox.y,
+                    width: fbox.width,
+                    height: fbox.height }
+    }
+}
+
+#[cfg(feature = "viewporter")]
+impl Into<wlr_fbox> for FloatArea {
+    fn into(self) -> wlr_fbox {
+        wlr_fbox { x: self.x,
+                   y: self.y,
+                   width: self.width,
+                   height: self.height }
+    }
+}
+
+#[cfg(feature = "viewporter")]
+impl From<Area> for FloatArea {
+    fn from(area: Area) -> Self {
+        FloatArea { x: area.origin.x as c_double,
+                    y: area.origin.y as c_double,
+                    width: area.size.width as c_double,
+                    height: area.size.height as c_double }
+    }
+}
+
 impl Area {
     pub fn new(origin: Origin, size: Size) -> Self {
         Area { origin, size }
@@ -19,11 +19,13 @@ use {area::Area,
      output::{self, Output, layout::Layout},
      surface::Surface,
      cursor::xcursor,
-     utils::{HandleErr, HandleResult, Handleable}};
+     utils::{HandleErr, HandleResult, Handleable, region::OwnedPixmanRegion32}};
 
 #[derive(Debug)]
 pub(crate) struct CursorState {
     output_layout: Option<output::layout::Handle>,
+    /// The constraint applied to relative motion, if any.
+    constraint: Option<Constraint>,
     /// A counter that will always have a strong count of 1.
     ///
     /// Once the cursor is destroyed, this will signal to the `cursor::Handle`s that
@@ -33,6 +35,18 @@ pub(crate) struct CursorState {
     cursor: *mut Cursor
 }
 
+/// A constraint on the relative motion of the cursor.
+///
+/// Usually this comes from a client through the pointer constraints protocol,
+/// see `extensions::pointer_constraints`.
+#[derive(Debug)]
+pub enum Constraint {
+    /// The cursor does not move at all.
+    Locked,
+    /// The cursor can only move within the region, in layout coordinates.
+    Confined(OwnedPixmanRegion32)
+}
+
 // NOTE We can't use `utils::Handle` because we own the cursor.
 // So this is special cased, just like `output::Layout`.
 #[derive(Debug, Clone)]
@@ -109,6 +123,48 @@ pub trait Handler {
                              cursor_handle: Handle,
                              event: &tablet_tool::event::Button) {
     }
+
+    /// Callback that is triggered when a swipe gesture begins.
+    #[cfg(feature = "pointer_gestures")]
+    fn on_pointer_swipe_begin(&mut self,
+                              compositor_handle: compositor::Handle,
+                              cursor_handle: Handle,
+                              event: &pointer::event::SwipeBegin) {}
+
+    /// Callback that is triggered when the fingers of a swipe gesture move.
+    #[cfg(feature = "pointer_gestures")]
+    fn on_pointer_swipe_update(&mut self,
+                               compositor_handle: compositor::Handle,
+                               cursor_handle: Handle,
+                               event: &pointer::event::SwipeUpdate) {}
+
+    /// Callback that is triggered when a swipe gesture ends or is cancelled.
+    #[cfg(feature = "pointer_gestures")]
+    fn on_pointer_swipe_end(&mut self,
+                            compositor_handle: compositor::Handle,
+                            cursor_handle: Handle,
+                            event: &pointer::event::SwipeEnd) {}
+
+    /// Callback that is triggered when a pinch gesture begins.
+    #[cfg(feature = "pointer_gestures")]
+    fn on_pointer_pinch_begin(&mut self,
+                              compositor_handle: compositor::Handle,
+                              cursor_handle: Handle,
+                              event: &pointer::event::PinchBegin) {}
+
+    /// Callback that is triggered when the fingers of a pinch gesture move.
+    #[cfg(feature = "pointer_gestures")]
+    fn on_pointer_pinch_update(&mut self,
+                               compositor_handle: compositor::Handle,
+                               cursor_handle: Handle,
+                               event: &pointer::event::PinchUpdate) {}
+
+    /// Callback that is triggered when a pinch gesture ends or is cancelled.
+    #[cfg(feature = "pointer_gestures")]
+    fn on_pointer_pinch_end(&mut self,
+                            compositor_handle: compositor::Handle,
+                            cursor_handle: Handle,
+                            event: &pointer::event::PinchEnd) {}
 }
 
 wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::layout::Handle>), [
@@ -309,6 +365,114 @@ wayland_listener!(pub Cursor, (*mut wlr_cursor, Box<Handler>, Option<output::lay
 
         Box::into_raw(cursor);
     };
+    #[cfg(feature = "pointer_gestures")]
+    pointer_swipe_begin_listener => pointer_swipe_begin_notify:
+    |this: &mut Cursor, event: *mut libc::c_void,|
+    unsafe {
+        let (cursor_ptr, ref mut cursor_handler, _) = this.data;
+        let cursor = Cursor::from_ptr(cursor_ptr);
+        let event = pointer::event::SwipeBegin::from_ptr(event as _);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        cursor_handler.on_pointer_swipe_begin(compositor,
+                                              cursor.weak_reference(),
+                                              &event);
+
+        Box::into_raw(cursor);
+    };
+    #[cfg(feature = "pointer_gestures")]
+    pointer_swipe_update_listener => pointer_swipe_update_notify:
+    |this: &mut Cursor, event: *mut libc::c_void,|
+    unsafe {
+        let (cursor_ptr, ref mut cursor_handler, _) = this.data;
+        let cursor = Cursor::from_ptr(cursor_ptr);
+        let event = pointer::event::SwipeUpdate::from_ptr(event as _);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        cursor_handler.on_pointer_swipe_update(compositor,
+                                               cursor.weak_reference(),
+                                               &event);
+
+        Box::into_raw(cursor);
+    };
+    #[cfg(feature = "pointer_gestures")]
+    pointer_swipe_end_listener => pointer_swipe_end_notify:
+    |this: &mut Cursor, event: *mut libc::c_void,|
+    unsafe {
+        let (cursor_ptr, ref mut cursor_handler, _) = this.data;
+        let cursor = Cursor::from_ptr(cursor_ptr);
+        let event = pointer::event::SwipeEnd::from_ptr(event as _);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        cursor_handler.on_pointer_swipe_end(compositor,
+                                            cursor.weak_reference(),
+                                            &event);
+
+        Box::into_raw(cursor);
+    };
+    #[cfg(feature = "pointer_gestures")]
+    pointer_pinch_begin_listener => pointer_pinch_begin_notify:
+    |this: &mut Cursor, event: *mut libc::c_void,|
+    unsafe {
+        let (cursor_ptr, ref mut cursor_handler, _) = this.data;
+        let cursor = Cursor::from_ptr(cursor_ptr);
+        let event = pointer::event::PinchBegin::from_ptr(event as _);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        cursor_handler.on_pointer_pinch_begin(compositor,
+                                              cursor.weak_reference(),
+                                              &event);
+
+        Box::into_raw(cursor);
+    };
+    #[cfg(feature = "pointer_gestures")]
+    pointer_pinch_update_listener => pointer_pinch_update_notify:
+    |this: &mut Cursor, event: *mut libc::c_void,|
+    unsafe {
+        let (cursor_ptr, ref mut cursor_handler, _) = this.data;
+        let cursor = Cursor::from_ptr(cursor_ptr);
+        let event = pointer::event::PinchUpdate::from_ptr(event as _);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        cursor_handler.on_pointer_pinch_update(compositor,
+                                               cursor.weak_reference(),
+                                               &event);
+
+        Box::into_raw(cursor);
+    };
+    #[cfg(feature = "pointer_gestures")]
+    pointer_pinch_end_listener => pointer_pinch_end_notify:
+    |this: &mut Cursor, event: *mut libc::c_void,|
+    unsafe {
+        let (cursor_ptr, ref mut cursor_handler, _) = this.data;
+        let cursor = Cursor::from_ptr(cursor_ptr);
+        let event = pointer::event::PinchEnd::from_ptr(event as _);
+        let compositor = match compositor::handle() {
+            Some(handle) => handle,
+            None => return
+        };
+
+        cursor_handler.on_pointer_pinch_end(compositor,
+                                            cursor.weak_reference(),
+                                            &event);
+
+        Box::into_raw(cursor);
+    };
 ]);
 
 impl fmt::Debug for Cursor {
@@ -349,11 +513,27 @@ impl Cursor {
                           cursor.tablet_tool_tip_listener() as *mut _ as _);
             wl_signal_add(&mut (*cursor_ptr).events.tablet_tool_button as *mut _ as _,
                           cursor.tablet_tool_button_listener() as *mut _ as _);
+            #[cfg(feature = "pointer_gestures")]
+            {
+                wl_signal_add(&mut (*cursor_ptr).events.swipe_begin as *mut _ as _,
+                              cursor.pointer_swipe_begin_listener() as *mut _ as _);
+                wl_signal_add(&mut (*cursor_ptr).events.swipe_update as *mut _ as _,
+                              cursor.pointer_swipe_update_listener() as *mut _ as _);
+                wl_signal_add(&mut (*cursor_ptr).events.swipe_end as *mut _ as _,
+                              cursor.pointer_swipe_end_listener() as *mut _ as _);
+                wl_signal_add(&mut (*cursor_ptr).events.pinch_begin as *mut _ as _,
+                              cursor.pointer_pinch_begin_listener() as *mut _ as _);
+                wl_signal_add(&mut (*cursor_ptr).events.pinch_update as *mut _ as _,
+                              cursor.pointer_pinch_update_listener() as *mut _ as _);
+                wl_signal_add(&mut (*cursor_ptr).events.pinch_end as *mut _ as _,
+                              cursor.pointer_pinch_end_listener() as *mut _ as _);
+            }
             let counter = Rc::new(Cell::new(false));
             let handle = Rc::downgrade(&counter);
             let state = Box::new(CursorState { counter,
                                                cursor: Box::into_raw(cursor),
-                                               output_layout: None });
+                                               output_layout: None,
+                                               constraint: None });
             (*cursor_ptr).data = Box::into_raw(state) as *mut libc::c_void;
             Handle { cursor: cursor_ptr,
                            handle }
@@ -442,10 +622,16 @@ impl Cursor {
     ///
     /// `dev` may be passed to respect device mapping constraints. If `dev` is None,
     /// device mapping constraints will be ignored.
+    ///
+    /// The motion is limited by the constraint set with `set_constraint`, if any.
     pub fn move_to<'this, O>(&'this mut self, dev: O, delta_x: f64, delta_y: f64)
         where O: Into<Option<&'this input::Device>>
     {
         self.assert_layout();
+        let (delta_x, delta_y) = match self.constrain_motion(delta_x, delta_y) {
+            Some(delta) => delta,
+            None => return
+        };
         unsafe {
             let dev_ptr = dev.into().map(|dev| dev.as_ptr())
                              .unwrap_or(ptr::null_mut());
@@ -453,9 +639,61 @@ impl Cursor {
         }
     }
 
+    /// Constrain the relative motion of the cursor, or remove the constraint
+    /// by passing `None`.
+    ///
+    /// This only affects `move_to`, warping the cursor ignores the constraint.
+    pub fn set_constraint<T>(&mut self, constraint: T)
+        where T: Into<Option<Constraint>>
+    {
+        unsafe {
+            let data = (*self.data.0).data as *mut CursorState;
+            (*data).constraint = constraint.into();
+        }
+    }
+
+    /// Whether the cursor currently has a constraint on its motion.
+    pub fn is_constrained(&self) -> bool {
+        unsafe {
+            let data = (*self.data.0).data as *mut CursorState;
+            (*data).constraint.is_some()
+        }
+    }
+
+    /// Applies the current constraint to the motion, returning the delta
+    /// to actually move by or `None` if the cursor should not move.
+    fn constrain_motion(&mut self, delta_x: f64, delta_y: f64) -> Option<(f64, f64)> {
+        let (x, y) = self.coords();
+        unsafe {
+            let data = (*self.data.0).data as *mut CursorState;
+            match (*data).constraint {
+                None => Some((delta_x, delta_y)),
+                Some(Constraint::Locked) => None,
+                Some(Constraint::Confined(ref mut region)) => {
+                    // NOTE If the cursor starts outside of the region it can't
+                    // be confined, so let it move freely until it enters it.
+                    match region.confine(x, y, x + delta_x, y + delta_y) {
+                        Ok((confined_x, confined_y)) => Some((confined_x - x, confined_y - y)),
+                        Err(()) => Some((delta_x, delta_y))
+                    }
+                }
+            }
+        }
+    }
+
     //TODO USE IMAGE
     /// Sets the image of the cursor to the image.
     pub fn set_cursor_image(&mut self, image: &xcursor::Image) {
+        self.set_cursor_image_scaled(image, 1.0)
+    }
+
+    /// Sets the image of the cursor on the outputs at `scale` to the image.
+    /// Pass a `scale` of 0 to set the image on every output.
+    ///
+    /// The image is drawn pixel for pixel with its own hotspot, so it should
+    /// be loaded at the scale of those outputs, e.g. with
+    /// `xcursor::Manager::get_xcursor_nearest`.
+    pub fn set_cursor_image_scaled(&mut self, image: &xcursor::Image, scale: f32) {
         unsafe {
             // NOTE Rationale for why lifetime isn't attached:
             //
@@ -469,7 +707,7 @@ impl Cursor {
                                  image.height,
                                  image.hotspot_x as _,
                                  image.hotspot_y as _,
-                                 1.0)
+                                 scale)
         }
     }
 
@@ -668,6 +906,27 @@ impl Drop for Cursor {
             ffi_dispatch!(WAYLAND_SERVER_HANDLE,
                           wl_list_remove,
                           &mut (*self.tablet_tool_button_listener()).link as *mut _ as _);
+            #[cfg(feature = "pointer_gestures")]
+            {
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*self.pointer_swipe_begin_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*self.pointer_swipe_update_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*self.pointer_swipe_end_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*self.pointer_pinch_begin_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*self.pointer_pinch_update_listener()).link as *mut _ as _);
+                ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                              wl_list_remove,
+                              &mut (*self.pointer_pinch_end_listener()).link as *mut _ as _);
+            }
             let data = Box::from_raw((*cursor_ptr).data as *mut CursorState);
             let _ = Box::from_raw(data.cursor);
             assert_eq!(Rc::strong_count(&data.counter),
@@ -17,6 +17,9 @@ pub struct ManagerTheme<'manager> {
 /// xcursor::Manager dynamically loads xcursor themes at sizes necessary for use on outputs at
 /// arbitrary scale factors. You should call `load` for each output you will show your cursor on,
 /// with the scale factor parameter set to that output's scale factor.
+///
+/// For outputs with a fractional scale use `load_nearest` and
+/// `get_xcursor_nearest` instead.
 #[derive(Debug)]
 pub struct Manager {
     manager: *mut wlr_xcursor_manager
@@ -82,6 +85,57 @@ impl Manager {
         }
     }
 
+    /// Retrieves a `XCursor` for the given cursor name from the loaded theme
+    /// whose scale is nearest to the given scale, along with the scale of
+    /// that theme.
+    ///
+    /// Themes at a higher scale are preferred over ones equally far below it,
+    /// so the cursor is drawn too large rather than too small.
+    pub fn get_xcursor_nearest<'manager, T>(&'manager self,
+                                            name: T,
+                                            scale: f32)
+                                            -> Option<(XCursor<'manager>, f32)>
+        where T: Into<Option<String>>
+    {
+        let nearest = self.nearest_scale(scale)?;
+        self.get_xcursor(name, nearest).map(|xcursor| (xcursor, nearest))
+    }
+
+    /// Get the scale of the loaded theme nearest to the scale, or `None` if no
+    /// theme has been loaded.
+    pub fn nearest_scale(&self, scale: f32) -> Option<f32> {
+        self.scaled_themes().iter()
+            .map(|theme| theme.scale())
+            .fold(None, |nearest: Option<f32>, theme_scale| match nearest {
+                Some(nearest) => {
+                    let (distance, nearest_distance) = ((theme_scale - scale).abs(),
+                                                        (nearest - scale).abs());
+                    if distance < nearest_distance ||
+                       distance == nearest_distance && theme_scale > nearest {
+                        Some(theme_scale)
+                    } else {
+                        Some(nearest)
+                    }
+                }
+                None => Some(theme_scale)
+            })
+    }
+
+    /// Ensures an xcursor theme is loaded at the integer scale the fractional
+    /// scale rounds up to, and returns that scale.
+    ///
+    /// Xcursor themes only come in a few sizes, so loading a theme at e.g. 1.5
+    /// usually gives the images of the nearest size anyway, with hotspots that
+    /// don't match the scale. Returns `None` if the theme couldn't be loaded.
+    pub fn load_nearest(&self, scale: f32) -> Option<f32> {
+        let scale = scale.ceil().max(1.0);
+        if self.load(scale) {
+            None
+        } else {
+            Some(scale)
+        }
+    }
+
     /// Get a list of all the scaled `xcursor::ManagerTheme`s managed by this
     /// manager.
     pub fn scaled_themes<'manager>(&'manager self) -> Vec<ManagerTheme<'manager>> {
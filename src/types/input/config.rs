@@ -0,0 +1,502 @@
+//! Declarative configuration of input devices.
+//!
+//! A [`Rule`](./struct.Rule.html) matches devices by name, vendor and
+//! product ids and type, and lists the actions taken on the devices it
+//! matches. Give the rules to `input::manager::Builder::config` and they are
+//! applied to each device when it's added, after the `*_added` callbacks were
+//! called:
+//!
+//! ```rust,ignore
+//! use wlroots::input::{self, config::{Action, KeymapNames, LibinputOption, Match, Rule,
+//!                                     Rules}};
+//!
+//! let mut rules = Rules::new();
+//! rules.push(Rule::new(Match::any().device_type(WLR_INPUT_DEVICE_POINTER)
+//!                                  .name("*Touchpad*"))
+//!                .action(Action::Libinput(LibinputOption::NaturalScroll(true))));
+//! rules.push(Rule::new(Match::any().vendor(0x046d).product(0xc52b))
+//!                .action(Action::Keymap(KeymapNames { layout: Some("us".into()),
+//!                                                     variant: Some("dvorak".into()),
+//!                                                     ..KeymapNames::default() })));
+//! rules.push(Rule::new(Match::any().device_type(WLR_INPUT_DEVICE_TABLET_TOOL))
+//!                .action(Action::MapToOutput("HDMI-A-1".into())));
+//! let builder = input::manager::Builder::default().config(rules);
+//! ```
+//!
+//! Rules are applied in order, so later rules override earlier ones. After
+//! changing them at runtime through `input::manager::with_config`, call
+//! `Rules::reapply` to apply them to the devices that are already there.
+//!
+//! Mapping to an output or a region needs the cursor the device is attached
+//! to, see `Rules::set_cursor`. A device mapped to an output that isn't there
+//! yet is mapped when the output is added.
+
+use std::{error::Error, fmt, ptr};
+
+use libc;
+use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_input_device, wlr_input_device_type, wlr_keyboard_set_keymap,
+                  wlr_keyboard_set_repeat_info, xkb_context_new, xkb_context_unref,
+                  xkb_keymap_new_from_names, xkb_keymap_unref, xkb_rule_names,
+                  xkb_context_flags::*, xkb_keymap_compile_flags::*,
+                  wlr_input_device_type::*};
+
+use {area::Area,
+     cursor,
+     input::{self, AccelProfile, ClickMethod, LibinputConfigError, ScrollMethod,
+             SendEventsMode},
+     manager::{input_manager, output_manager},
+     output,
+     utils::{HandleErr, safe_as_cstring}};
+
+/// Which devices a rule applies to.
+///
+/// Criteria that aren't set match every device.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Match {
+    name: Option<String>,
+    vendor: Option<u32>,
+    product: Option<u32>,
+    device_type: Option<wlr_input_device_type>
+}
+
+/// The XKB rule names a keymap is compiled from. Names that aren't set use
+/// the `XKB_DEFAULT_*` environment variables, like the default keymap.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct KeymapNames {
+    pub rules: Option<String>,
+    pub model: Option<String>,
+    pub layout: Option<String>,
+    pub variant: Option<String>,
+    pub options: Option<String>
+}
+
+/// An option set with `input::LibinputConfig`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LibinputOption {
+    Tap(bool),
+    TapDrag(bool),
+    NaturalScroll(bool),
+    AccelSpeed(f64),
+    AccelProfile(AccelProfile),
+    LeftHanded(bool),
+    ScrollMethod(ScrollMethod),
+    ScrollButton(u32),
+    Dwt(bool),
+    MiddleEmulation(bool),
+    ClickMethod(ClickMethod),
+    SendEvents(SendEventsMode),
+    CalibrationMatrix([f32; 6])
+}
+
+/// What is done to a device a rule matches.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Compile a keymap for the keyboard.
+    Keymap(KeymapNames),
+    /// Set the key repeat rate, in keys per second, and the delay before
+    /// repeating, in milliseconds.
+    Repeat { rate: i32, delay: i32 },
+    /// Set a libinput option.
+    Libinput(LibinputOption),
+    /// Map the device to the output with this name, e.g. `HDMI-A-1`.
+    MapToOutput(String),
+    /// Map the device to a region of the output layout.
+    MapToRegion(Area)
+}
+
+/// A set of actions for the devices that match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    criteria: Match,
+    actions: Vec<Action>
+}
+
+/// Why an action couldn't be applied to a device.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApplyError {
+    /// The action is for keyboards and the device isn't one.
+    NotKeyboard,
+    /// The action is a libinput option and the device isn't from libinput.
+    NotLibinput,
+    /// The libinput option couldn't be set.
+    Libinput(LibinputConfigError),
+    /// The keymap couldn't be compiled.
+    Keymap(KeymapNames),
+    /// The action maps the device and `Rules::set_cursor` wasn't called.
+    NoCursor,
+    /// There's no output with the name.
+    NoOutput(String),
+    /// The cursor or the output couldn't be used.
+    Handle(HandleErr)
+}
+
+/// The rules for the input devices of the compositor.
+#[derive(Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+    cursor: Option<cursor::Handle>,
+    devices: Vec<Box<DeviceListener>>
+}
+
+wayland_listener!(pub(crate) DeviceListener, *mut wlr_input_device, [
+    destroy_listener => destroy_notify: |this: &mut DeviceListener, _data: *mut libc::c_void,|
+    unsafe {
+        // NOTE The rules may be borrowed by `with_config` right now, so the
+        // listener only marks itself as destroyed and is dropped later.
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        this.data = ptr::null_mut();
+        input_manager::device_removed();
+    };
+]);
+
+impl Match {
+    /// Criteria that match every device.
+    pub fn any() -> Self {
+        Match::default()
+    }
+
+    /// Match devices whose name matches the glob, where `*` matches any
+    /// number of characters and `?` matches one.
+    pub fn name<S: Into<String>>(mut self, glob: S) -> Self {
+        self.name = Some(glob.into());
+        self
+    }
+
+    pub fn vendor(mut self, vendor: u32) -> Self {
+        self.vendor = Some(vendor);
+        self
+    }
+
+    pub fn product(mut self, product: u32) -> Self {
+        self.product = Some(product);
+        self
+    }
+
+    pub fn device_type(mut self, device_type: wlr_input_device_type) -> Self {
+        self.device_type = Some(device_type);
+        self
+    }
+
+    /// Whether the device meets all of the criteria.
+    pub fn matches(&self, device: &input::Device) -> bool {
+        let name_matches = match self.name {
+            Some(ref glob) => glob_matches(glob.as_bytes(),
+                                           device.name().unwrap_or_default().as_bytes()),
+            None => true
+        };
+        name_matches &&
+            self.vendor.map_or(true, |vendor| vendor == device.vendor()) &&
+            self.product.map_or(true, |product| product == device.product()) &&
+            self.device_type.map_or(true, |device_type| device_type == device.dev_type())
+    }
+}
+
+impl Rule {
+    pub fn new(criteria: Match) -> Self {
+        Rule { criteria, actions: Vec::new() }
+    }
+
+    /// Add an action, taken after the ones added before it.
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn criteria(&self) -> &Match {
+        &self.criteria
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    pub fn actions_mut(&mut self) -> &mut Vec<Action> {
+        &mut self.actions
+    }
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Rules::default()
+    }
+
+    /// Add a rule, applied after the ones added before it.
+    pub fn push(&mut self, rule: Rule) {
+        self.rules.push(rule)
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn rules_mut(&mut self) -> &mut Vec<Rule> {
+        &mut self.rules
+    }
+
+    /// Set the cursor devices are attached to, which is needed to map them
+    /// to an output or a region.
+    pub fn set_cursor(&mut self, cursor: cursor::Handle) {
+        self.cursor = Some(cursor)
+    }
+
+    /// Apply the rules that match to the device.
+    ///
+    /// Every action is attempted, the errors of those that failed are
+    /// returned.
+    pub fn apply(&self, device: &input::Device) -> Vec<ApplyError> {
+        let mut errors = Vec::new();
+        for rule in self.rules.iter().filter(|rule| rule.criteria.matches(device)) {
+            for action in &rule.actions {
+                if let Err(err) = self.apply_action(device, action) {
+                    errors.push(err)
+                }
+            }
+        }
+        errors
+    }
+
+    /// Apply the rules again to every device, e.g. after they were changed.
+    pub fn reapply(&self) {
+        for listener in self.devices.iter().filter(|listener| !listener.data.is_null()) {
+            let device = unsafe { input::Device::from_ptr(listener.data) };
+            self.apply_logged(&device);
+        }
+    }
+
+    /// Applies the rules to a new device, and keeps track of it so the rules
+    /// can be reapplied.
+    pub(crate) unsafe fn device_added(&mut self, device: &input::Device) {
+        let mut listener = DeviceListener::new(device.as_ptr());
+        wl_signal_add(&mut (*device.as_ptr()).events.destroy as *mut _ as _,
+                      listener.destroy_listener() as _);
+        self.devices_removed();
+        self.devices.push(listener);
+        self.apply_logged(device);
+    }
+
+    /// Maps the devices to the new output if that's where the rules map them.
+    ///
+    /// Only the last mapping that applies to a device counts, like when the
+    /// rules are applied to it.
+    pub(crate) fn output_added(&self, output: &output::Handle) {
+        let name = match output.run(|output| output.name()) {
+            Ok(name) => name,
+            Err(_) => return
+        };
+        for listener in self.devices.iter().filter(|listener| !listener.data.is_null()) {
+            let device = unsafe { input::Device::from_ptr(listener.data) };
+            let mapping = self.rules.iter()
+                .filter(|rule| rule.criteria.matches(&device))
+                .flat_map(|rule| rule.actions.iter())
+                .filter(|action| match **action {
+                            Action::MapToOutput(_) | Action::MapToRegion(_) => true,
+                            _ => false
+                        })
+                .last();
+            if let Some(action) = mapping {
+                match *action {
+                    Action::MapToOutput(ref target) if *target == name => {
+                        if let Err(err) = self.apply_action(&device, action) {
+                            wlr_log!(WLR_DEBUG, "Could not map {:?} to {}: {}",
+                                     device.name(), name, err);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Forgets about the devices that have been destroyed.
+    pub(crate) fn devices_removed(&mut self) {
+        // NOTE This can run at the end of the notify function of a listener,
+        // which doesn't touch it after this.
+        self.devices.retain(|listener| !listener.data.is_null());
+    }
+
+    fn apply_logged(&self, device: &input::Device) {
+        for err in self.apply(device) {
+            wlr_log!(WLR_DEBUG, "Could not apply input rule to {:?}: {}", device.name(), err);
+        }
+    }
+
+    fn apply_action(&self, device: &input::Device, action: &Action) -> Result<(), ApplyError> {
+        unsafe {
+            match *action {
+                Action::Keymap(ref names) => {
+                    if device.dev_type() != WLR_INPUT_DEVICE_KEYBOARD {
+                        return Err(ApplyError::NotKeyboard)
+                    }
+                    set_keymap(device, names)
+                }
+                Action::Repeat { rate, delay } => {
+                    if device.dev_type() != WLR_INPUT_DEVICE_KEYBOARD {
+                        return Err(ApplyError::NotKeyboard)
+                    }
+                    wlr_keyboard_set_repeat_info(device.dev_union().keyboard, rate, delay);
+                    Ok(())
+                }
+                Action::Libinput(option) => {
+                    let mut config = device.libinput_config().ok_or(ApplyError::NotLibinput)?;
+                    set_libinput_option(&mut config, option).map_err(ApplyError::Libinput)
+                }
+                Action::MapToOutput(ref name) => {
+                    let cursor = self.cursor.as_ref().ok_or(ApplyError::NoCursor)?;
+                    let output = output_manager::outputs().into_iter()
+                        .find(|output| output.run(|output| output.name() == *name)
+                                             .unwrap_or(false))
+                        .ok_or_else(|| ApplyError::NoOutput(name.clone()))?;
+                    cursor.run(|cursor| {
+                        output.run(|output| cursor.map_input_to_output(device, &*output))
+                    }).and_then(|result| result).map_err(ApplyError::Handle)
+                }
+                Action::MapToRegion(area) => {
+                    let cursor = self.cursor.as_ref().ok_or(ApplyError::NoCursor)?;
+                    cursor.run(|cursor| cursor.map_input_to_region(device, area))
+                        .map_err(ApplyError::Handle)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Rules")
+            .field("rules", &self.rules)
+            .field("devices", &self.devices.len())
+            .finish()
+    }
+}
+
+impl fmt::Display for ApplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ApplyError::Libinput(ref err) => write!(f, "{}", err),
+            ApplyError::NoOutput(ref name) => write!(f, "There is no output named {}", name),
+            ApplyError::Handle(ref err) => write!(f, "{}", err),
+            _ => f.write_str(self.description())
+        }
+    }
+}
+
+impl Error for ApplyError {
+    fn description(&self) -> &str {
+        match *self {
+            ApplyError::NotKeyboard => "The device is not a keyboard",
+            ApplyError::NotLibinput => "The device is not from libinput",
+            ApplyError::Libinput(ref err) => err.description(),
+            ApplyError::Keymap(_) => "Could not compile the keymap",
+            ApplyError::NoCursor => "No cursor was set to map the device with",
+            ApplyError::NoOutput(_) => "There is no output with the name",
+            ApplyError::Handle(ref err) => err.description()
+        }
+    }
+}
+
+unsafe fn set_keymap(device: &input::Device, names: &KeymapNames) -> Result<(), ApplyError> {
+    let name = |name: &Option<String>, var: &str| {
+        safe_as_cstring(name.clone().or_else(|| ::std::env::var(var).ok()).unwrap_or_default())
+    };
+    let rules = name(&names.rules, "XKB_DEFAULT_RULES");
+    let model = name(&names.model, "XKB_DEFAULT_MODEL");
+    let layout = name(&names.layout, "XKB_DEFAULT_LAYOUT");
+    let variant = name(&names.variant, "XKB_DEFAULT_VARIANT");
+    let options = name(&names.options, "XKB_DEFAULT_OPTIONS");
+    let rule_names = xkb_rule_names { rules: rules.as_ptr(),
+                                      model: model.as_ptr(),
+                                      layout: layout.as_ptr(),
+                                      variant: variant.as_ptr(),
+                                      options: options.as_ptr() };
+    let context = xkb_context_new(XKB_CONTEXT_NO_FLAGS);
+    if context.is_null() {
+        return Err(ApplyError::Keymap(names.clone()))
+    }
+    let keymap = xkb_keymap_new_from_names(context, &rule_names, XKB_KEYMAP_COMPILE_NO_FLAGS);
+    xkb_context_unref(context);
+    if keymap.is_null() {
+        return Err(ApplyError::Keymap(names.clone()))
+    }
+    wlr_keyboard_set_keymap(device.dev_union().keyboard, keymap);
+    xkb_keymap_unref(keymap);
+    Ok(())
+}
+
+fn set_libinput_option(config: &mut input::LibinputConfig,
+                       option: LibinputOption)
+                       -> Result<(), LibinputConfigError> {
+    match option {
+        LibinputOption::Tap(enabled) => config.set_tap_enabled(enabled),
+        LibinputOption::TapDrag(enabled) => config.set_tap_drag_enabled(enabled),
+        LibinputOption::NaturalScroll(enabled) => config.set_natural_scroll_enabled(enabled),
+        LibinputOption::AccelSpeed(speed) => config.set_accel_speed(speed),
+        LibinputOption::AccelProfile(profile) => config.set_accel_profile(profile),
+        LibinputOption::LeftHanded(left_handed) => config.set_left_handed(left_handed),
+        LibinputOption::ScrollMethod(method) => config.set_scroll_method(method),
+        LibinputOption::ScrollButton(button) => config.set_scroll_button(button),
+        LibinputOption::Dwt(enabled) => config.set_dwt_enabled(enabled),
+        LibinputOption::MiddleEmulation(enabled) => config.set_middle_emulation_enabled(enabled),
+        LibinputOption::ClickMethod(method) => config.set_click_method(method),
+        LibinputOption::SendEvents(mode) => config.set_send_events_mode(mode),
+        LibinputOption::CalibrationMatrix(matrix) => config.set_calibration_matrix(matrix)
+    }
+}
+
+/// Whether the text matches the glob, where `*` matches any number of
+/// characters and `?` matches one.
+fn glob_matches(glob: &[u8], text: &[u8]) -> bool {
+    match (glob.split_first(), text.split_first()) {
+        (None, None) => true,
+        (Some((&b'*', rest)), _) => {
+            glob_matches(rest, text) || (!text.is_empty() && glob_matches(glob, &text[1..]))
+        }
+        (Some((&b'?', rest)), Some((_, text_rest))) => glob_matches(rest, text_rest),
+        (Some((glob_byte, rest)), Some((text_byte, text_rest))) => {
+            glob_byte == text_byte && glob_matches(rest, text_rest)
+        }
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::glob_matches;
+
+    fn matches(glob: &str, text: &str) -> bool {
+        glob_matches(glob.as_bytes(), text.as_bytes())
+    }
+
+    #[test]
+    fn literal_globs_match_exactly() {
+        assert!(matches("AT Translated Set 2 keyboard", "AT Translated Set 2 keyboard"));
+        assert!(!matches("AT Translated Set 2 keyboard", "AT Translated Set 2 keyboar"));
+        assert!(!matches("keyboard", "keyboards"));
+        assert!(matches("", ""));
+        assert!(!matches("", "keyboard"));
+    }
+
+    #[test]
+    fn star_matches_any_run() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "Logitech USB Receiver"));
+        assert!(matches("Logitech*", "Logitech USB Receiver"));
+        assert!(matches("*Receiver", "Logitech USB Receiver"));
+        assert!(matches("*USB*", "Logitech USB Receiver"));
+        assert!(matches("*e*e*", "Receiver"));
+        assert!(!matches("*Mouse", "Logitech USB Receiver"));
+        assert!(!matches("Logitech*Mouse", "Logitech USB Receiver"));
+    }
+
+    #[test]
+    fn question_mark_matches_one_byte() {
+        assert!(matches("event?", "event3"));
+        assert!(!matches("event?", "event"));
+        assert!(!matches("event?", "event12"));
+        assert!(matches("event??", "event12"));
+        assert!(matches("?*", "x"));
+        assert!(!matches("?*", ""));
+    }
+}
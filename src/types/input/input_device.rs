@@ -4,7 +4,7 @@ use libc::{c_double, c_uint};
 use wlroots_sys::{wlr_input_device, wlr_input_device_pointer, wlr_input_device_type,
                   wlr_input_device_type::*};
 
-use {input::{keyboard, pointer, touch, tablet_pad, tablet_tool},
+use {input::{keyboard, pointer, touch, tablet_pad, tablet_tool, LibinputConfig},
      utils::c_to_rust_string};
 pub(crate) use manager::input_manager::Manager;
 
@@ -70,6 +70,14 @@ impl Device {
         unsafe { (*self.device).type_ }
     }
 
+    /// Get the libinput configuration of the device.
+    ///
+    /// Returns `None` if the device is not from the libinput backend, e.g.
+    /// when running nested or on the headless backend.
+    pub fn libinput_config(&self) -> Option<LibinputConfig> {
+        LibinputConfig::from_device(self)
+    }
+
     /// Get a handle to the backing input device.
     pub fn device(&self) -> Handle {
         unsafe {
@@ -0,0 +1,468 @@
+//! Configuration of devices from the libinput backend, such as tap-to-click
+//! on touchpads or the acceleration of mice.
+//!
+//! Get it with `input::Device::libinput_config`. Each option has a way to
+//! tell whether the device supports it, and setting an option that isn't
+//! supported returns `LibinputConfigError::Unsupported`.
+
+use std::{error::Error, fmt, marker::PhantomData};
+
+use libc::{c_double, c_float, c_int};
+use wlroots_sys::{libinput_device, wlr_input_device_is_libinput, wlr_libinput_get_device_handle};
+
+use input;
+
+#[link(name = "input")]
+extern "C" {
+    fn libinput_device_config_tap_get_finger_count(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_tap_set_enabled(device: *mut libinput_device,
+                                              enable: c_int)
+                                              -> c_int;
+    fn libinput_device_config_tap_get_enabled(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_tap_set_drag_enabled(device: *mut libinput_device,
+                                                   enable: c_int)
+                                                   -> c_int;
+    fn libinput_device_config_tap_get_drag_enabled(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_scroll_has_natural_scroll(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_scroll_set_natural_scroll_enabled(device: *mut libinput_device,
+                                                                enable: c_int)
+                                                                -> c_int;
+    fn libinput_device_config_scroll_get_natural_scroll_enabled(device: *mut libinput_device)
+                                                                -> c_int;
+    fn libinput_device_config_scroll_get_methods(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_scroll_set_method(device: *mut libinput_device,
+                                                method: u32)
+                                                -> c_int;
+    fn libinput_device_config_scroll_get_method(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_scroll_set_button(device: *mut libinput_device,
+                                                button: u32)
+                                                -> c_int;
+    fn libinput_device_config_scroll_get_button(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_accel_is_available(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_accel_set_speed(device: *mut libinput_device,
+                                              speed: c_double)
+                                              -> c_int;
+    fn libinput_device_config_accel_get_speed(device: *mut libinput_device) -> c_double;
+    fn libinput_device_config_accel_get_profiles(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_accel_set_profile(device: *mut libinput_device,
+                                                profile: u32)
+                                                -> c_int;
+    fn libinput_device_config_accel_get_profile(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_left_handed_is_available(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_left_handed_set(device: *mut libinput_device,
+                                              left_handed: c_int)
+                                              -> c_int;
+    fn libinput_device_config_left_handed_get(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_dwt_is_available(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_dwt_set_enabled(device: *mut libinput_device,
+                                              enable: c_int)
+                                              -> c_int;
+    fn libinput_device_config_dwt_get_enabled(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_middle_emulation_is_available(device: *mut libinput_device)
+                                                            -> c_int;
+    fn libinput_device_config_middle_emulation_set_enabled(device: *mut libinput_device,
+                                                           enable: c_int)
+                                                           -> c_int;
+    fn libinput_device_config_middle_emulation_get_enabled(device: *mut libinput_device)
+                                                           -> c_int;
+    fn libinput_device_config_click_get_methods(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_click_set_method(device: *mut libinput_device,
+                                               method: u32)
+                                               -> c_int;
+    fn libinput_device_config_click_get_method(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_send_events_get_modes(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_send_events_set_mode(device: *mut libinput_device,
+                                                   mode: u32)
+                                                   -> c_int;
+    fn libinput_device_config_send_events_get_mode(device: *mut libinput_device) -> u32;
+    fn libinput_device_config_calibration_has_matrix(device: *mut libinput_device) -> c_int;
+    fn libinput_device_config_calibration_set_matrix(device: *mut libinput_device,
+                                                     matrix: *const c_float)
+                                                     -> c_int;
+    fn libinput_device_config_calibration_get_matrix(device: *mut libinput_device,
+                                                     matrix: *mut c_float)
+                                                     -> c_int;
+}
+
+/// `LIBINPUT_CONFIG_STATUS_SUCCESS`, the other statuses are errors.
+const CONFIG_STATUS_SUCCESS: c_int = 0;
+/// `LIBINPUT_CONFIG_STATUS_UNSUPPORTED`.
+const CONFIG_STATUS_UNSUPPORTED: c_int = 1;
+
+/// Why an option couldn't be set.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum LibinputConfigError {
+    /// The device doesn't have this option, e.g. tapping on a mouse.
+    Unsupported,
+    /// The device has the option but not this value, e.g. an acceleration
+    /// profile it doesn't support.
+    Invalid
+}
+
+/// How the pointer accelerates.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AccelProfile {
+    /// The speed is the same regardless of how fast the device moves.
+    Flat,
+    /// The pointer moves further the faster the device moves.
+    Adaptive
+}
+
+/// How scroll events are generated.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ScrollMethod {
+    /// No scroll events are generated, other than by a scroll wheel.
+    NoScroll,
+    /// Scroll by moving two fingers on a touchpad.
+    TwoFinger,
+    /// Scroll by moving a finger along the edge of a touchpad.
+    Edge,
+    /// Scroll by moving the device while holding the scroll button, see
+    /// `LibinputConfig::set_scroll_button`.
+    OnButtonDown
+}
+
+/// How clicks on a touchpad without buttons are turned into buttons.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ClickMethod {
+    /// No buttons are generated.
+    None,
+    /// The button depends on the area of the touchpad that's pressed.
+    ButtonAreas,
+    /// The button depends on the number of fingers on the touchpad.
+    Clickfinger
+}
+
+/// Whether the device sends events.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SendEventsMode {
+    Enabled,
+    Disabled,
+    /// Disabled while an external pointer device is plugged in, e.g. for a
+    /// touchpad when a mouse is used.
+    DisabledOnExternalMouse
+}
+
+/// The libinput configuration of an input device.
+#[derive(Debug)]
+pub struct LibinputConfig<'device> {
+    device: *mut libinput_device,
+    phantom: PhantomData<&'device input::Device>
+}
+
+impl fmt::Display for LibinputConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl Error for LibinputConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            LibinputConfigError::Unsupported => "The option is not supported by the device",
+            LibinputConfigError::Invalid => "The value is not valid for the device"
+        }
+    }
+}
+
+impl AccelProfile {
+    fn bits(self) -> u32 {
+        match self {
+            AccelProfile::Flat => 1 << 0,
+            AccelProfile::Adaptive => 1 << 1
+        }
+    }
+
+    fn from_bits(bits: u32) -> Option<Self> {
+        [AccelProfile::Flat, AccelProfile::Adaptive].iter()
+            .cloned()
+            .find(|profile| profile.bits() == bits)
+    }
+}
+
+impl ScrollMethod {
+    fn bits(self) -> u32 {
+        match self {
+            ScrollMethod::NoScroll => 0,
+            ScrollMethod::TwoFinger => 1 << 0,
+            ScrollMethod::Edge => 1 << 1,
+            ScrollMethod::OnButtonDown => 1 << 2
+        }
+    }
+
+    fn all() -> [ScrollMethod; 3] {
+        [ScrollMethod::TwoFinger, ScrollMethod::Edge, ScrollMethod::OnButtonDown]
+    }
+}
+
+impl ClickMethod {
+    fn bits(self) -> u32 {
+        match self {
+            ClickMethod::None => 0,
+            ClickMethod::ButtonAreas => 1 << 0,
+            ClickMethod::Clickfinger => 1 << 1
+        }
+    }
+
+    fn all() -> [ClickMethod; 2] {
+        [ClickMethod::ButtonAreas, ClickMethod::Clickfinger]
+    }
+}
+
+impl SendEventsMode {
+    fn bits(self) -> u32 {
+        match self {
+            SendEventsMode::Enabled => 0,
+            SendEventsMode::Disabled => 1 << 0,
+            SendEventsMode::DisabledOnExternalMouse => 1 << 1
+        }
+    }
+}
+
+impl<'device> LibinputConfig<'device> {
+    /// Get the libinput configuration of the device, or `None` if it's not
+    /// from the libinput backend.
+    pub(crate) fn from_device(device: &'device input::Device) -> Option<Self> {
+        unsafe {
+            if !wlr_input_device_is_libinput(device.as_ptr()) {
+                return None
+            }
+            let device = wlr_libinput_get_device_handle(device.as_ptr());
+            if device.is_null() {
+                return None
+            }
+            Some(LibinputConfig { device, phantom: PhantomData })
+        }
+    }
+
+    /// Whether the device can tap-to-click, i.e. it's a touchpad.
+    pub fn has_tap(&self) -> bool {
+        unsafe { libinput_device_config_tap_get_finger_count(self.device) > 0 }
+    }
+
+    pub fn tap_enabled(&self) -> bool {
+        unsafe { libinput_device_config_tap_get_enabled(self.device) != 0 }
+    }
+
+    pub fn set_tap_enabled(&mut self, enabled: bool) -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_tap_set_enabled(self.device, enabled as c_int)) }
+    }
+
+    /// Whether tapping and then moving the finger drags.
+    pub fn tap_drag_enabled(&self) -> bool {
+        unsafe { libinput_device_config_tap_get_drag_enabled(self.device) != 0 }
+    }
+
+    pub fn set_tap_drag_enabled(&mut self, enabled: bool) -> Result<(), LibinputConfigError> {
+        unsafe {
+            status(libinput_device_config_tap_set_drag_enabled(self.device, enabled as c_int))
+        }
+    }
+
+    pub fn has_natural_scroll(&self) -> bool {
+        unsafe { libinput_device_config_scroll_has_natural_scroll(self.device) != 0 }
+    }
+
+    pub fn natural_scroll_enabled(&self) -> bool {
+        unsafe { libinput_device_config_scroll_get_natural_scroll_enabled(self.device) != 0 }
+    }
+
+    /// Scroll the content in the direction of the fingers, like on a touch
+    /// screen, instead of the direction of the scroll bar.
+    pub fn set_natural_scroll_enabled(&mut self,
+                                      enabled: bool)
+                                      -> Result<(), LibinputConfigError> {
+        unsafe {
+            let enabled = enabled as c_int;
+            status(libinput_device_config_scroll_set_natural_scroll_enabled(self.device, enabled))
+        }
+    }
+
+    /// The scroll methods the device supports, other than `NoScroll`.
+    pub fn scroll_methods(&self) -> Vec<ScrollMethod> {
+        let methods = unsafe { libinput_device_config_scroll_get_methods(self.device) };
+        ScrollMethod::all().iter().cloned().filter(|method| methods & method.bits() != 0).collect()
+    }
+
+    pub fn scroll_method(&self) -> ScrollMethod {
+        let method = unsafe { libinput_device_config_scroll_get_method(self.device) };
+        ScrollMethod::all().iter()
+            .cloned()
+            .find(|known| known.bits() == method)
+            .unwrap_or(ScrollMethod::NoScroll)
+    }
+
+    pub fn set_scroll_method(&mut self, method: ScrollMethod) -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_scroll_set_method(self.device, method.bits())) }
+    }
+
+    /// The button that scrolls with `ScrollMethod::OnButtonDown`, as a Linux
+    /// event code, e.g. `BTN_MIDDLE`.
+    pub fn scroll_button(&self) -> u32 {
+        unsafe { libinput_device_config_scroll_get_button(self.device) }
+    }
+
+    pub fn set_scroll_button(&mut self, button: u32) -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_scroll_set_button(self.device, button)) }
+    }
+
+    /// Whether the acceleration of the pointer can be configured.
+    pub fn has_accel(&self) -> bool {
+        unsafe { libinput_device_config_accel_is_available(self.device) != 0 }
+    }
+
+    /// The speed of the pointer, from -1.0 (slowest) to 1.0 (fastest).
+    pub fn accel_speed(&self) -> f64 {
+        unsafe { libinput_device_config_accel_get_speed(self.device) }
+    }
+
+    /// Set the speed of the pointer, from -1.0 (slowest) to 1.0 (fastest).
+    ///
+    /// Speeds outside of that range are `LibinputConfigError::Invalid`.
+    pub fn set_accel_speed(&mut self, speed: f64) -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_accel_set_speed(self.device, speed)) }
+    }
+
+    /// The acceleration profiles the device supports.
+    pub fn accel_profiles(&self) -> Vec<AccelProfile> {
+        let profiles = unsafe { libinput_device_config_accel_get_profiles(self.device) };
+        [AccelProfile::Flat, AccelProfile::Adaptive].iter()
+            .cloned()
+            .filter(|profile| profiles & profile.bits() != 0)
+            .collect()
+    }
+
+    /// The acceleration profile, or `None` if the device has no acceleration.
+    pub fn accel_profile(&self) -> Option<AccelProfile> {
+        AccelProfile::from_bits(unsafe { libinput_device_config_accel_get_profile(self.device) })
+    }
+
+    pub fn set_accel_profile(&mut self,
+                             profile: AccelProfile)
+                             -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_accel_set_profile(self.device, profile.bits())) }
+    }
+
+    pub fn has_left_handed(&self) -> bool {
+        unsafe { libinput_device_config_left_handed_is_available(self.device) != 0 }
+    }
+
+    pub fn left_handed(&self) -> bool {
+        unsafe { libinput_device_config_left_handed_get(self.device) != 0 }
+    }
+
+    /// Swap the left and right buttons.
+    pub fn set_left_handed(&mut self, left_handed: bool) -> Result<(), LibinputConfigError> {
+        unsafe {
+            status(libinput_device_config_left_handed_set(self.device, left_handed as c_int))
+        }
+    }
+
+    /// Whether the device can be disabled while typing, i.e. it's a touchpad.
+    pub fn has_dwt(&self) -> bool {
+        unsafe { libinput_device_config_dwt_is_available(self.device) != 0 }
+    }
+
+    pub fn dwt_enabled(&self) -> bool {
+        unsafe { libinput_device_config_dwt_get_enabled(self.device) != 0 }
+    }
+
+    /// Ignore the device while typing on a keyboard.
+    pub fn set_dwt_enabled(&mut self, enabled: bool) -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_dwt_set_enabled(self.device, enabled as c_int)) }
+    }
+
+    pub fn has_middle_emulation(&self) -> bool {
+        unsafe { libinput_device_config_middle_emulation_is_available(self.device) != 0 }
+    }
+
+    pub fn middle_emulation_enabled(&self) -> bool {
+        unsafe { libinput_device_config_middle_emulation_get_enabled(self.device) != 0 }
+    }
+
+    /// Press the middle button by pressing the left and right buttons at once.
+    pub fn set_middle_emulation_enabled(&mut self,
+                                        enabled: bool)
+                                        -> Result<(), LibinputConfigError> {
+        unsafe {
+            let enabled = enabled as c_int;
+            status(libinput_device_config_middle_emulation_set_enabled(self.device, enabled))
+        }
+    }
+
+    /// The click methods the device supports, other than `ClickMethod::None`.
+    pub fn click_methods(&self) -> Vec<ClickMethod> {
+        let methods = unsafe { libinput_device_config_click_get_methods(self.device) };
+        ClickMethod::all().iter().cloned().filter(|method| methods & method.bits() != 0).collect()
+    }
+
+    pub fn click_method(&self) -> ClickMethod {
+        let method = unsafe { libinput_device_config_click_get_method(self.device) };
+        ClickMethod::all().iter()
+            .cloned()
+            .find(|known| known.bits() == method)
+            .unwrap_or(ClickMethod::None)
+    }
+
+    pub fn set_click_method(&mut self, method: ClickMethod) -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_click_set_method(self.device, method.bits())) }
+    }
+
+    /// The modes the device supports, other than `SendEventsMode::Enabled`.
+    pub fn send_events_modes(&self) -> Vec<SendEventsMode> {
+        let modes = unsafe { libinput_device_config_send_events_get_modes(self.device) };
+        [SendEventsMode::Disabled, SendEventsMode::DisabledOnExternalMouse].iter()
+            .cloned()
+            .filter(|mode| modes & mode.bits() != 0)
+            .collect()
+    }
+
+    pub fn send_events_mode(&self) -> SendEventsMode {
+        let mode = unsafe { libinput_device_config_send_events_get_mode(self.device) };
+        if mode & SendEventsMode::Disabled.bits() != 0 {
+            SendEventsMode::Disabled
+        } else if mode & SendEventsMode::DisabledOnExternalMouse.bits() != 0 {
+            SendEventsMode::DisabledOnExternalMouse
+        } else {
+            SendEventsMode::Enabled
+        }
+    }
+
+    pub fn set_send_events_mode(&mut self,
+                                mode: SendEventsMode)
+                                -> Result<(), LibinputConfigError> {
+        unsafe { status(libinput_device_config_send_events_set_mode(self.device, mode.bits())) }
+    }
+
+    /// Whether the device has a calibration matrix, i.e. it's a touch screen
+    /// or a tablet.
+    pub fn has_calibration_matrix(&self) -> bool {
+        unsafe { libinput_device_config_calibration_has_matrix(self.device) != 0 }
+    }
+
+    /// The calibration matrix, the first two rows of a 3x3 matrix that's
+    /// applied to the normalized coordinates of the device.
+    pub fn calibration_matrix(&self) -> [f32; 6] {
+        let mut matrix = [0.0; 6];
+        unsafe {
+            libinput_device_config_calibration_get_matrix(self.device, matrix.as_mut_ptr());
+        }
+        matrix
+    }
+
+    pub fn set_calibration_matrix(&mut self,
+                                  matrix: [f32; 6])
+                                  -> Result<(), LibinputConfigError> {
+        unsafe {
+            status(libinput_device_config_calibration_set_matrix(self.device, matrix.as_ptr()))
+        }
+    }
+}
+
+fn status(status: c_int) -> Result<(), LibinputConfigError> {
+    match status {
+        CONFIG_STATUS_SUCCESS => Ok(()),
+        CONFIG_STATUS_UNSUPPORTED => Err(LibinputConfigError::Unsupported),
+        _ => Err(LibinputConfigError::Invalid)
+    }
+}
@@ -1,4 +1,6 @@
 mod input_device;
+mod libinput_config;
+pub mod config;
 pub mod keyboard;
 pub mod pointer;
 pub mod touch;
@@ -6,6 +8,7 @@ pub mod tablet_tool;
 pub mod tablet_pad;
 
 pub use self::input_device::*;
+pub use self::libinput_config::*;
 
 pub mod manager {
     //! Input resources are managed by the input resource manager.
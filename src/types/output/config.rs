@@ -0,0 +1,376 @@
+//! Changing the state of several outputs at once.
+
+use std::{error::Error, fmt, ptr};
+
+use libc::c_float;
+use wlroots_sys::{wlr_output, wlr_output_enable, wlr_output_layout, wlr_output_layout_get,
+                  wlr_output_layout_move, wlr_output_mode, wlr_output_set_custom_mode,
+                  wlr_output_set_mode, wlr_output_set_position, wlr_output_set_scale,
+                  wlr_output_set_transform};
+
+use {area::{Origin, Size},
+     output::{self, layout::{self, Layout}, Output, Transform},
+     utils::{HandleErr, Handleable}};
+
+/// The mode to set on an output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ModeSetting {
+    /// One of the modes advertised by the output, see `Output::modes`.
+    Preset { size: Size, refresh: i32 },
+    /// A mode the output didn't advertise, e.g. for a headless output.
+    Custom { size: Size, refresh: i32 }
+}
+
+/// Why a `Config` can't be committed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// An output of the configuration is gone or is already borrowed.
+    OutputUnavailable(HandleErr),
+    /// The layout of the configuration is gone or is already borrowed.
+    LayoutUnavailable(HandleErr),
+    /// The preset mode is not one of the modes of the output.
+    UnknownMode { output: String, size: Size, refresh: i32 },
+    /// The scale is not a positive number.
+    InvalidScale { output: String, scale: c_float },
+    /// The backend didn't accept the mode.
+    ModeFailed { output: String },
+    /// The backend couldn't enable or disable the output.
+    EnableFailed { output: String },
+    /// The output would be moved, but there's no layout to move it in.
+    NoLayout { output: String }
+}
+
+/// The changes to make to a single output.
+#[derive(Debug, Clone, Default)]
+struct OutputChanges {
+    enabled: Option<bool>,
+    mode: Option<ModeSetting>,
+    scale: Option<c_float>,
+    transform: Option<Transform>,
+    position: Option<Origin>
+}
+
+/// The state of an output before a configuration was committed, so it can be
+/// restored.
+struct OutputSnapshot {
+    enabled: bool,
+    mode: *mut wlr_output_mode,
+    custom_size: (i32, i32),
+    custom_refresh: i32,
+    transform: Transform,
+    scale: c_float,
+    position: (i32, i32)
+}
+
+/// A batch of changes to several outputs, rolled back if any of them fails.
+///
+/// Changing outputs one by one with e.g. `Output::set_mode` and
+/// `layout::Layout::move_output` leaves the outputs that were already changed
+/// as they are when a later change fails.
+///
+/// A `Config` validates all of the changes first and then applies them output
+/// by output. This is best-effort, not atomic: each output takes its changes,
+/// and tells clients and its `output::Handler` about them, before the next
+/// one is changed. If an output doesn't accept its changes then the outputs
+/// changed before it are restored to the state they had, which they report
+/// like any other change. When the outputs are in a layout, the layout
+/// handler gets a single `on_change` after the whole batch.
+///
+/// ```rust,ignore
+/// output::Config::new()
+///     .layout(layout_handle)
+///     .mode(&laptop, Size::new(1920, 1080), 60000)
+///     .position(&laptop, Origin::new(0, 0))
+///     .enable(&monitor, true)
+///     .scale(&monitor, 2.0)
+///     .position(&monitor, Origin::new(1920, 0))
+///     .commit()?;
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    layout: Option<layout::Handle>,
+    outputs: Vec<(output::Handle, OutputChanges)>
+}
+
+impl Config {
+    /// Make a new configuration that doesn't change anything.
+    pub fn new() -> Self {
+        Config::default()
+    }
+
+    /// Set the layout the outputs are in.
+    ///
+    /// Positions are then set with the layout, and its handler gets a single
+    /// `on_change` when the configuration is committed.
+    pub fn layout(mut self, layout: layout::Handle) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Enable or disable the output.
+    ///
+    /// The other changes to a disabled output are ignored.
+    pub fn enable(mut self, output: &output::Handle, enabled: bool) -> Self {
+        self.changes(output).enabled = Some(enabled);
+        self
+    }
+
+    /// Set the mode of the output to one of the modes it advertises.
+    ///
+    /// The refresh rate is in mHz, see `output::Mode::refresh`.
+    pub fn mode(mut self, output: &output::Handle, size: Size, refresh: i32) -> Self {
+        self.changes(output).mode = Some(ModeSetting::Preset { size, refresh });
+        self
+    }
+
+    /// Set a mode the output doesn't advertise.
+    ///
+    /// Whether this works depends on the backend.
+    pub fn custom_mode(mut self, output: &output::Handle, size: Size, refresh: i32) -> Self {
+        self.changes(output).mode = Some(ModeSetting::Custom { size, refresh });
+        self
+    }
+
+    /// Set the scale of the output.
+    pub fn scale(mut self, output: &output::Handle, scale: c_float) -> Self {
+        self.changes(output).scale = Some(scale);
+        self
+    }
+
+    /// Set the transform of the output.
+    pub fn transform(mut self, output: &output::Handle, transform: Transform) -> Self {
+        self.changes(output).transform = Some(transform);
+        self
+    }
+
+    /// Set the position of the output in layout coordinates.
+    pub fn position(mut self, output: &output::Handle, position: Origin) -> Self {
+        self.changes(output).position = Some(position);
+        self
+    }
+
+    /// Check that the configuration could be committed, without changing
+    /// any output.
+    ///
+    /// This catches modes the outputs don't have and invalid scales, but the
+    /// backend can still reject a configuration that passes.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        for &(ref handle, ref changes) in &self.outputs {
+            handle.run(|output| changes.validate(output))
+                .map_err(ConfigError::OutputUnavailable)??;
+        }
+        Ok(())
+    }
+
+    /// Apply all of the changes.
+    ///
+    /// If any output doesn't accept its changes then the outputs that were
+    /// already changed are restored and the error is returned. Restoring
+    /// them can fail as well, e.g. if the backend no longer accepts the old
+    /// mode, in which case they're left as close to their old state as the
+    /// backend allows.
+    ///
+    /// The outputs and the layout must not be borrowed, e.g. this can't be
+    /// called from within `output::Handle::run` of one of the outputs.
+    pub fn commit(self) -> Result<(), ConfigError> {
+        self.validate()?;
+        let layout = match self.layout {
+            Some(ref handle) => {
+                handle.run(|layout| unsafe { layout.as_ptr() })
+                    .map_err(ConfigError::LayoutUnavailable)?
+            }
+            None => ptr::null_mut()
+        };
+        unsafe {
+            if !layout.is_null() {
+                Layout::begin_change_batch(layout);
+            }
+            let result = self.apply(layout);
+            if !layout.is_null() {
+                Layout::end_change_batch(layout);
+            }
+            result
+        }
+    }
+
+    /// Apply the changes output by output, undoing them all on failure.
+    unsafe fn apply(&self, layout: *mut wlr_output_layout) -> Result<(), ConfigError> {
+        let mut applied: Vec<(&output::Handle, OutputSnapshot)> = vec![];
+        for &(ref handle, ref changes) in &self.outputs {
+            let result = handle.run(|output| {
+                let snapshot = OutputSnapshot::new(output.as_ptr(), layout);
+                let result = changes.apply(output, layout);
+                if result.is_err() {
+                    snapshot.restore(output.as_ptr(), layout);
+                }
+                result.map(|()| snapshot)
+            }).map_err(ConfigError::OutputUnavailable)
+                .and_then(|result| result);
+            match result {
+                Ok(snapshot) => applied.push((handle, snapshot)),
+                Err(err) => {
+                    for (handle, snapshot) in applied.into_iter().rev() {
+                        let _ = handle.run(|output| snapshot.restore(output.as_ptr(), layout));
+                    }
+                    return Err(err)
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the changes for the output, adding an empty set if there are none yet.
+    fn changes(&mut self, output: &output::Handle) -> &mut OutputChanges {
+        let index = match self.outputs.iter().position(|&(ref handle, _)| handle == output) {
+            Some(index) => index,
+            None => {
+                self.outputs.push((output.clone(), OutputChanges::default()));
+                self.outputs.len() - 1
+            }
+        };
+        &mut self.outputs[index].1
+    }
+}
+
+impl OutputChanges {
+    fn validate(&self, output: &Output) -> Result<(), ConfigError> {
+        if self.enabled == Some(false) {
+            return Ok(())
+        }
+        if let Some(scale) = self.scale {
+            if !(scale > 0.0) {
+                return Err(ConfigError::InvalidScale { output: output.name(), scale })
+            }
+        }
+        if let Some(ModeSetting::Preset { size, refresh }) = self.mode {
+            if find_mode(output, size, refresh).is_null() {
+                return Err(ConfigError::UnknownMode { output: output.name(), size, refresh })
+            }
+        }
+        Ok(())
+    }
+
+    unsafe fn apply(&self, output: &Output, layout: *mut wlr_output_layout)
+                    -> Result<(), ConfigError> {
+        let output_ptr = output.as_ptr();
+        if let Some(enabled) = self.enabled {
+            if !wlr_output_enable(output_ptr, enabled) {
+                return Err(ConfigError::EnableFailed { output: output.name() })
+            }
+            if !enabled {
+                return Ok(())
+            }
+        }
+        let mode_set = match self.mode {
+            Some(ModeSetting::Preset { size, refresh }) => {
+                wlr_output_set_mode(output_ptr, find_mode(output, size, refresh))
+            }
+            Some(ModeSetting::Custom { size, refresh }) => {
+                wlr_output_set_custom_mode(output_ptr, size.width, size.height, refresh)
+            }
+            None => true
+        };
+        if !mode_set {
+            return Err(ConfigError::ModeFailed { output: output.name() })
+        }
+        if let Some(transform) = self.transform {
+            wlr_output_set_transform(output_ptr, transform);
+        }
+        if let Some(scale) = self.scale {
+            wlr_output_set_scale(output_ptr, scale);
+        }
+        if let Some(position) = self.position {
+            set_position(output_ptr, layout, position.x, position.y);
+        }
+        Ok(())
+    }
+}
+
+impl OutputSnapshot {
+    unsafe fn new(output: *mut wlr_output, layout: *mut wlr_output_layout) -> Self {
+        let mut position = ((*output).lx, (*output).ly);
+        if !layout.is_null() {
+            let layout_output = wlr_output_layout_get(layout, output);
+            if !layout_output.is_null() {
+                position = ((*layout_output).x, (*layout_output).y);
+            }
+        }
+        OutputSnapshot { enabled: (*output).enabled,
+                         mode: (*output).current_mode,
+                         custom_size: ((*output).width, (*output).height),
+                         custom_refresh: (*output).refresh,
+                         transform: (*output).transform,
+                         scale: (*output).scale,
+                         position }
+    }
+
+    unsafe fn restore(&self, output: *mut wlr_output, layout: *mut wlr_output_layout) {
+        wlr_output_enable(output, self.enabled);
+        if !self.enabled {
+            return
+        }
+        if !self.mode.is_null() {
+            wlr_output_set_mode(output, self.mode);
+        } else {
+            let (width, height) = self.custom_size;
+            wlr_output_set_custom_mode(output, width, height, self.custom_refresh);
+        }
+        wlr_output_set_transform(output, self.transform);
+        wlr_output_set_scale(output, self.scale);
+        set_position(output, layout, self.position.0, self.position.1);
+    }
+}
+
+/// Move the output in the layout if it's part of it, otherwise only set the
+/// position reported to clients.
+unsafe fn set_position(output: *mut wlr_output, layout: *mut wlr_output_layout, x: i32, y: i32) {
+    if !layout.is_null() && !wlr_output_layout_get(layout, output).is_null() {
+        wlr_output_layout_move(layout, output, x, y)
+    } else {
+        wlr_output_set_position(output, x, y)
+    }
+}
+
+/// Find the mode of the output with the size and refresh rate.
+///
+/// Returns null if the output has no such mode.
+fn find_mode(output: &Output, size: Size, refresh: i32) -> *mut wlr_output_mode {
+    output.modes().into_iter()
+        .find(|mode| mode.dimensions() == (size.width, size.height) && mode.refresh() == refresh)
+        .map(|mode| unsafe { mode.as_ptr() })
+        .unwrap_or_else(ptr::null_mut)
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::ConfigError::*;
+        match *self {
+            OutputUnavailable(err) => write!(f, "output is {}", err),
+            LayoutUnavailable(err) => write!(f, "output layout is {}", err),
+            UnknownMode { ref output, size, refresh } => {
+                write!(f, "{} has no {}x{}@{}mHz mode", output, size.width, size.height, refresh)
+            }
+            InvalidScale { ref output, scale } => {
+                write!(f, "{} scale {} is invalid", output, scale)
+            }
+            ModeFailed { ref output } => write!(f, "{} did not accept the mode", output),
+            EnableFailed { ref output } => write!(f, "{} could not be enabled or disabled", output),
+            NoLayout { ref output } => write!(f, "{} can't be moved without a layout", output)
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        use self::ConfigError::*;
+        match *self {
+            OutputUnavailable(_) => "Output is unavailable",
+            LayoutUnavailable(_) => "Output layout is unavailable",
+            UnknownMode { .. } => "Output does not have the mode",
+            InvalidScale { .. } => "Scale is invalid",
+            ModeFailed { .. } => "Output did not accept the mode",
+            EnableFailed { .. } => "Output could not be enabled or disabled",
+            NoLayout { .. } => "Output can't be moved without a layout"
+        }
+    }
+}
@@ -1,10 +1,11 @@
 //! TODO Documentation
 
-use std::{fmt, panic, ptr, cell::Cell, marker::PhantomData, rc::{Rc, Weak}};
+use std::{error::Error, fmt, panic, ptr, cell::Cell, marker::PhantomData, rc::{Rc, Weak}};
 
 use libc::{self, c_double, c_int};
-use wayland_sys::server::{signal::wl_signal_add, WAYLAND_SERVER_HANDLE};
-use wlroots_sys::{wlr_output_effective_resolution, wlr_output_layout, wlr_output_layout_add,
+use wayland_sys::server::{signal::{wl_signal_add, wl_signal_emit}, WAYLAND_SERVER_HANDLE};
+use wlroots_sys::{wlr_output, wlr_output_effective_resolution, wlr_output_layout,
+                  wlr_output_layout_add,
                   wlr_output_layout_add_auto, wlr_output_layout_closest_point,
                   wlr_output_layout_contains_point, wlr_output_layout_create,
                   wlr_output_layout_destroy, wlr_output_layout_get, wlr_output_layout_get_box,
@@ -12,7 +13,7 @@ use wlroots_sys::{wlr_output_effective_resolution, wlr_output_layout, wlr_output
                   wlr_output_layout_move, wlr_output_layout_output, wlr_output_layout_output_at,
                   wlr_output_layout_output_coords, wlr_output_layout_remove};
 
-use {area::{Area, Origin},
+use {area::{Area, Origin, Size},
      compositor,
      output,
      utils::{HandleErr, HandleResult, Handleable}};
@@ -24,7 +25,87 @@ struct OutputLayoutState {
     /// they cannot be upgraded.
     counter: Rc<Cell<bool>>,
     /// A raw pointer to the `output::layout::Layout` on the heap.
-    layout: *mut Layout
+    layout: *mut Layout,
+    /// Whether `Handler::on_change` is held back while an `output::Config`
+    /// is being committed.
+    batching_changes: bool,
+    /// Whether the layout changed while `batching_changes` was set.
+    change_pending: bool,
+    /// Whether an output was destroyed and the layout has to be arranged
+    /// on the next change, see `Policy::Compact`.
+    arrange_pending: bool,
+    /// How outputs are placed by `Layout::add_auto` and `Layout::arrange`.
+    policy: Policy,
+    /// Whether the targets of an `output::Mirror` are placed on their source.
+    follow_mirrors: bool,
+    /// The output boxes as last reported to `Handler::on_change_diff`.
+    boxes: Vec<(output::Handle, Area)>
+}
+
+/// How a `Layout` places its outputs.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Policy {
+    /// Outputs are placed by wlroots when added with `Layout::add_auto`,
+    /// and otherwise stay where they are put.
+    Manual,
+    /// Outputs are placed in a row, each one to the right of the previous.
+    LeftToRight,
+    /// Outputs are placed in a column, each one below the previous.
+    TopToBottom,
+    /// Every output is placed at the origin, showing the same region.
+    Mirror,
+    /// Like `LeftToRight`, but the row is closed up again when an output
+    /// is removed or destroyed so no gaps are left behind.
+    Compact
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy::Manual
+    }
+}
+
+/// Errors from placing an output in a `Layout`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LayoutError {
+    /// The output would overlap with another output in the layout.
+    Overlap {
+        output: output::Handle,
+        other: output::Handle
+    },
+    /// The output is not part of the layout.
+    NotInLayout
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LayoutError::Overlap { ref output, ref other } => {
+                write!(f, "Output {:?} would overlap with output {:?}", output, other)
+            }
+            LayoutError::NotInLayout => write!(f, "{}", self.description())
+        }
+    }
+}
+
+impl Error for LayoutError {
+    fn description(&self) -> &str {
+        match *self {
+            LayoutError::Overlap { .. } => "Output would overlap with another output",
+            LayoutError::NotInLayout => "Output is not part of the layout"
+        }
+    }
+}
+
+/// How the box of an output changed, as reported to `Handler::on_change_diff`.
+///
+/// `before` is `None` when the output was added and `after` is `None` when
+/// it was removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoxChange {
+    pub output: output::Handle,
+    pub before: Option<Area>,
+    pub after: Option<Area>
 }
 
 #[allow(unused_variables)]
@@ -50,6 +131,17 @@ pub trait Handler {
                         layout_handle: Handle,
                         output: Output<'this>) {
     }
+
+    /// Callback that's triggered after `on_change` when the box of any output
+    /// changed.
+    ///
+    /// `changes` has an entry for every output whose box differs from the
+    /// last time this was called.
+    fn on_change_diff(&mut self,
+                      compositor_handle: compositor::Handle,
+                      layout_handle: Handle,
+                      changes: &[BoxChange]) {
+    }
 }
 
 wayland_listener!(pub Layout, (*mut wlr_output_layout, Box<Handler>), [
@@ -61,6 +153,11 @@ wayland_listener!(pub Layout, (*mut wlr_output_layout, Box<Handler>), [
             None => return
         };
         let layout_output = data as *mut wlr_output_layout_output;
+        let mut listener = OutputListener::new(output_ptr);
+        wl_signal_add(&mut (*layout_output).events.destroy as *mut _ as _,
+                      listener.destroy_listener() as _);
+        // NOTE The listener frees itself when the layout output is destroyed.
+        Box::into_raw(listener);
         let layout_output = Output{layout_output, phantom: PhantomData};
         let output_layout = Layout::from_ptr(output_ptr);
 
@@ -91,6 +188,23 @@ wayland_listener!(pub Layout, (*mut wlr_output_layout, Box<Handler>), [
     change_listener => change_notify: |this: &mut Layout, data: *mut libc::c_void,|
     unsafe {
         let (output_ptr, ref mut manager) = this.data;
+        let state = (*output_ptr).data as *mut OutputLayoutState;
+        if !state.is_null() && (*state).batching_changes {
+            (*state).change_pending = true;
+            return
+        }
+        if !state.is_null() && (*state).arrange_pending {
+            // NOTE The arrangement is reported together with the change
+            // that destroyed the output, by emitting the change again.
+            (*state).arrange_pending = false;
+            let mut output_layout = Layout::from_ptr(output_ptr);
+            Layout::begin_change_batch(output_ptr);
+            output_layout.arrange();
+            (*state).change_pending = true;
+            Layout::end_change_batch(output_ptr);
+            Box::into_raw(output_layout);
+            return
+        }
         let compositor = match compositor::handle() {
             Some(handle) => handle,
             None => return
@@ -103,10 +217,35 @@ wayland_listener!(pub Layout, (*mut wlr_output_layout, Box<Handler>), [
                           output_layout.weak_reference(),
                           layout_output);
 
+        if !state.is_null() {
+            let boxes = output_boxes(output_ptr);
+            let changes = diff_boxes(&(*state).boxes, &boxes);
+            (*state).boxes = boxes;
+            if !changes.is_empty() {
+                manager.on_change_diff(compositor, output_layout.weak_reference(), &changes);
+            }
+        }
+
         Box::into_raw(output_layout);
     };
 ]);
 
+wayland_listener!(pub(crate) OutputListener, *mut wlr_output_layout, [
+    destroy_listener => destroy_notify: |this: &mut OutputListener, _data: *mut libc::c_void,|
+    unsafe {
+        // NOTE wlroots emits a change once the output is gone, which is
+        // when the layout is arranged.
+        let state = (*this.data).data as *mut OutputLayoutState;
+        if !state.is_null() && (*state).policy == Policy::Compact {
+            (*state).arrange_pending = true;
+        }
+        ffi_dispatch!(WAYLAND_SERVER_HANDLE,
+                      wl_list_remove,
+                      &mut (*this.destroy_listener()).link as *mut _ as _);
+        Box::from_raw(this as *mut OutputListener);
+    };
+]);
+
 impl fmt::Debug for Layout {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "")
@@ -152,7 +291,13 @@ impl Layout {
             let counter = Rc::new(Cell::new(false));
             let handle = Rc::downgrade(&counter);
             let state = Box::new(OutputLayoutState { counter,
-                                                     layout: Box::into_raw(output_layout) });
+                                                     layout: Box::into_raw(output_layout),
+                                                     batching_changes: false,
+                                                     change_pending: false,
+                                                     arrange_pending: false,
+                                                     policy: Policy::default(),
+                                                     follow_mirrors: false,
+                                                     boxes: Vec::new() });
             (*layout).data = Box::into_raw(state) as *mut libc::c_void;
             Handle { layout, handle }
         }
@@ -162,6 +307,72 @@ impl Layout {
         self.data.0
     }
 
+    /// Hold back `Handler::on_change` until `end_change_batch` is called.
+    pub(crate) unsafe fn begin_change_batch(layout: *mut wlr_output_layout) {
+        let state = (*layout).data as *mut OutputLayoutState;
+        (*state).batching_changes = true;
+        (*state).change_pending = false;
+    }
+
+    /// Stop holding back `Handler::on_change`, emitting a single change if
+    /// the layout changed since `begin_change_batch`.
+    pub(crate) unsafe fn end_change_batch(layout: *mut wlr_output_layout) {
+        let state = (*layout).data as *mut OutputLayoutState;
+        (*state).batching_changes = false;
+        if (*state).change_pending {
+            (*state).change_pending = false;
+            wl_signal_emit(&mut (*layout).events.change as *mut _ as _, layout as *mut _);
+        }
+    }
+
+    unsafe fn state(&self) -> *mut OutputLayoutState {
+        (*self.data.0).data as *mut OutputLayoutState
+    }
+
+    /// Get the source of the output if it's a mirror target, the source is
+    /// in this layout as well and the layout follows mirrors.
+    unsafe fn followed_source(&self, output: *mut wlr_output) -> Option<*mut wlr_output> {
+        if !self.follows_mirrors() {
+            return None
+        }
+        output::mirror_source_of(output)
+            .and_then(|source| {
+                if wlr_output_layout_get(self.data.0, source).is_null() {
+                    None
+                } else {
+                    Some(source)
+                }
+            })
+    }
+
+    /// Determines if the outputs are placed on top of each other because
+    /// they show the same mirror.
+    unsafe fn shares_coords(&self, first: *mut wlr_output, second: *mut wlr_output) -> bool {
+        let (first_source, second_source) =
+            (self.followed_source(first), self.followed_source(second));
+        first_source == Some(second)
+            || second_source == Some(first)
+            || (first_source.is_some() && first_source == second_source)
+    }
+
+    /// Fails if the area would overlap with an output other than the
+    /// given one.
+    unsafe fn check_overlap(&self,
+                            output_ptr: *mut wlr_output,
+                            area: Area)
+                            -> Result<(), LayoutError> {
+        for (other, other_area) in output_boxes(self.data.0) {
+            if other.as_ptr() == output_ptr || self.shares_coords(output_ptr, other.as_ptr()) {
+                continue
+            }
+            if areas_overlap(area, other_area) {
+                let output = output::Handle::from_ptr(output_ptr);
+                return Err(LayoutError::Overlap { output, other })
+            }
+        }
+        Ok(())
+    }
+
     /// Reconstruct the box from the wlr_output_layout.
     unsafe fn from_ptr(layout: *mut wlr_output_layout) -> Box<Layout> {
         let data = (*layout).data as *mut OutputLayoutState;
@@ -207,17 +418,111 @@ impl Layout {
         unsafe { wlr_output_layout_add(self.data.0, output.as_ptr(), x, y) }
     }
 
+    /// Adds an output to the layout at the given coordinates, unless it
+    /// would overlap with an output that's already there.
+    pub fn try_add(&mut self,
+                   output: &mut output::Output,
+                   origin: Origin)
+                   -> Result<(), LayoutError> {
+        unsafe {
+            let area = Area::new(origin, effective_size(output.as_ptr()));
+            self.check_overlap(output.as_ptr(), area)?;
+        }
+        self.add(output, origin);
+        Ok(())
+    }
+
     /// Adds an output to the layout, automatically positioning it with
-    /// the others that are already there.
+    /// the others that are already there according to the `Policy`.
     pub fn add_auto(&mut self, output: &mut output::Output) {
         unsafe {
             let layout_handle = self.weak_reference();
             output.set_output_layout(Some(layout_handle));
-            wlr_output_layout_add_auto(self.data.0, output.as_ptr());
+            let layout = self.data.0;
+            let output_ptr = output.as_ptr();
+            if let Some(source) = self.followed_source(output_ptr) {
+                let source = wlr_output_layout_get(layout, source);
+                wlr_output_layout_add(layout, output_ptr, (*source).x, (*source).y);
+                wlr_log!(WLR_DEBUG, "Added {:?} to {:?} on its mirror source", output, self);
+                return
+            }
+            let others: Vec<Area> = output_boxes(layout).into_iter()
+                .filter(|&(ref handle, _)| handle.as_ptr() != output_ptr)
+                .map(|(_, area)| area)
+                .collect();
+            match auto_origin(self.policy(), &others) {
+                Some(origin) => wlr_output_layout_add(layout, output_ptr, origin.x, origin.y),
+                None => wlr_output_layout_add_auto(layout, output_ptr)
+            }
             wlr_log!(WLR_DEBUG, "Added {:?} to {:?}", output, self);
         }
     }
 
+    /// Get the policy used to place outputs.
+    pub fn policy(&self) -> Policy {
+        unsafe { (*self.state()).policy }
+    }
+
+    /// Set the policy used to place outputs.
+    ///
+    /// The outputs already in the layout are not moved until `arrange` is
+    /// called.
+    pub fn set_policy(&mut self, policy: Policy) {
+        unsafe { (*self.state()).policy = policy }
+    }
+
+    /// Determines if the targets of an `output::Mirror` share the
+    /// coordinates of their source.
+    pub fn follows_mirrors(&self) -> bool {
+        unsafe { (*self.state()).follow_mirrors }
+    }
+
+    /// Set whether the targets of an `output::Mirror` share the coordinates
+    /// of their source, when both are in this layout.
+    ///
+    /// Outputs that share coordinates this way are not arranged by the
+    /// `Policy` and don't count as overlapping. Like the policy, this only
+    /// takes effect for the outputs already in the layout once `arrange` is
+    /// called.
+    pub fn set_follow_mirrors(&mut self, follow_mirrors: bool) {
+        unsafe { (*self.state()).follow_mirrors = follow_mirrors }
+    }
+
+    /// Moves every output in the layout to where the `Policy` puts it.
+    ///
+    /// The outputs keep their order along the row or column, so an
+    /// arrangement made by the user is tidied up rather than replaced.
+    /// If the layout follows mirrors, mirror targets are then moved to
+    /// their source.
+    ///
+    /// `Handler::on_change` is called once for the whole arrangement.
+    pub fn arrange(&mut self) {
+        unsafe {
+            let layout = self.data.0;
+            let (targets, boxes): (Vec<_>, Vec<_>) =
+                output_boxes(layout).into_iter()
+                                    .partition(|&(ref handle, _)| {
+                                        self.followed_source(handle.as_ptr()).is_some()
+                                    });
+            let batching = (*self.state()).batching_changes;
+            if !batching {
+                Layout::begin_change_batch(layout);
+            }
+            for (handle, origin) in arrangement(self.policy(), boxes) {
+                wlr_output_layout_move(layout, handle.as_ptr(), origin.x, origin.y);
+            }
+            for (handle, _) in targets {
+                if let Some(source) = self.followed_source(handle.as_ptr()) {
+                    let source = wlr_output_layout_get(layout, source);
+                    wlr_output_layout_move(layout, handle.as_ptr(), (*source).x, (*source).y);
+                }
+            }
+            if !batching {
+                Layout::end_change_batch(layout);
+            }
+        }
+    }
+
     /// Moves the output to the given coordinates.
     ///
     /// If the output is not part of this layout this does nothing.
@@ -226,6 +531,66 @@ impl Layout {
         unsafe { wlr_output_layout_move(self.data.0, output.as_ptr(), x, y) }
     }
 
+    /// Moves the output to the given coordinates, unless it would overlap
+    /// with another output in the layout.
+    pub fn try_move_output(&mut self,
+                           output: &mut output::Output,
+                           origin: Origin)
+                           -> Result<(), LayoutError> {
+        unsafe {
+            let output_ptr = output.as_ptr();
+            if wlr_output_layout_get(self.data.0, output_ptr).is_null() {
+                return Err(LayoutError::NotInLayout)
+            }
+            let area = Area::new(origin, effective_size(output_ptr));
+            self.check_overlap(output_ptr, area)?;
+        }
+        self.move_output(output, origin);
+        Ok(())
+    }
+
+    /// Get every pair of outputs in the layout that overlap.
+    ///
+    /// With `Policy::Mirror` every pair overlaps on purpose. Outputs that
+    /// share coordinates because the layout follows mirrors are left out.
+    pub fn overlaps(&mut self) -> Vec<(output::Handle, output::Handle)> {
+        let boxes = unsafe { output_boxes(self.data.0) };
+        overlapping_pairs(&boxes, |output, other| unsafe {
+            self.shares_coords(output.as_ptr(), other.as_ptr())
+        })
+    }
+
+    /// Moves the output so that it's edge to edge with the closest output
+    /// in the layout, without overlapping any of them.
+    ///
+    /// This is meant for drag-to-arrange interfaces: move the output to where
+    /// it was dropped and then snap it into place.
+    ///
+    /// Returns the new position, or `None` if the output is not part of the
+    /// layout or there's no free spot next to another output.
+    pub fn snap_to_adjacent(&mut self, output: &mut output::Output) -> Option<Origin> {
+        unsafe {
+            let layout = self.data.0;
+            let output_ptr = output.as_ptr();
+            let layout_output = wlr_output_layout_get(layout, output_ptr);
+            if layout_output.is_null() {
+                return None
+            }
+            let current = Origin::new((*layout_output).x, (*layout_output).y);
+            let size = effective_size(output_ptr);
+            let others: Vec<Area> = output_boxes(layout).into_iter()
+                .filter(|&(ref handle, _)| handle.as_ptr() != output_ptr)
+                .filter(|&(ref handle, _)| !self.shares_coords(output_ptr, handle.as_ptr()))
+                .map(|(_, area)| area)
+                .collect();
+            let origin = adjacent_origin(current, size, &others)?;
+            if origin != current {
+                wlr_output_layout_move(layout, output_ptr, origin.x, origin.y);
+            }
+            Some(origin)
+        }
+    }
+
     /// Get the closest point on this layout from the given point from the reference
     /// output.
     ///
@@ -497,6 +862,144 @@ impl<'output> Output<'output> {
     }
 }
 
+/// Get the boxes of every output in the layout.
+unsafe fn output_boxes(layout: *mut wlr_output_layout) -> Vec<(output::Handle, Area)> {
+    let mut result = vec![];
+    wl_list_for_each!((*layout).outputs, link, (pos: wlr_output_layout_output) => {
+        let area = Area::from_box(*wlr_output_layout_get_box(layout, (*pos).output));
+        result.push((output::Handle::from_ptr((*pos).output), area))
+    });
+    result
+}
+
+/// Compare the boxes from before and after a change to the layout.
+fn diff_boxes(before: &[(output::Handle, Area)],
+              after: &[(output::Handle, Area)])
+              -> Vec<BoxChange> {
+    let find = |boxes: &[(output::Handle, Area)], output: &output::Handle| {
+        boxes.iter().find(|&&(ref handle, _)| handle == output).map(|&(_, area)| area)
+    };
+    let mut changes = Vec::new();
+    for &(ref output, area) in after {
+        let old = find(before, output);
+        if old != Some(area) {
+            changes.push(BoxChange { output: output.clone(), before: old, after: Some(area) })
+        }
+    }
+    for &(ref output, area) in before {
+        if find(after, output).is_none() {
+            changes.push(BoxChange { output: output.clone(), before: Some(area), after: None })
+        }
+    }
+    changes
+}
+
+unsafe fn effective_size(output: *mut wlr_output) -> Size {
+    let (mut width, mut height) = (0, 0);
+    wlr_output_effective_resolution(output, &mut width, &mut height);
+    Size::new(width, height)
+}
+
+/// Where the policy puts an output added next to the `others`, or `None`
+/// if wlroots should place it.
+fn auto_origin(policy: Policy, others: &[Area]) -> Option<Origin> {
+    let right = |area: &Area| area.origin.x + area.size.width;
+    let bottom = |area: &Area| area.origin.y + area.size.height;
+    match policy {
+        Policy::Manual => None,
+        Policy::LeftToRight | Policy::Compact => {
+            Some(Origin::new(others.iter().map(right).max().unwrap_or(0), 0))
+        }
+        Policy::TopToBottom => Some(Origin::new(0, others.iter().map(bottom).max().unwrap_or(0))),
+        Policy::Mirror => Some(Origin::new(0, 0))
+    }
+}
+
+/// Where the policy moves each box, keeping their order along the row or
+/// column. `Policy::Manual` doesn't move any.
+fn arrangement<T>(policy: Policy, mut boxes: Vec<(T, Area)>) -> Vec<(T, Origin)> {
+    match policy {
+        Policy::Manual => return Vec::new(),
+        Policy::LeftToRight | Policy::Compact => {
+            boxes.sort_by_key(|&(_, area)| (area.origin.x, area.origin.y))
+        }
+        Policy::TopToBottom => boxes.sort_by_key(|&(_, area)| (area.origin.y, area.origin.x)),
+        Policy::Mirror => {}
+    }
+    let mut next = 0;
+    boxes.into_iter().map(|(key, area)| {
+        let origin = match policy {
+            Policy::TopToBottom => Origin::new(0, next),
+            Policy::Mirror => Origin::new(0, 0),
+            _ => Origin::new(next, 0)
+        };
+        next += match policy {
+            Policy::TopToBottom => area.size.height,
+            _ => area.size.width
+        };
+        (key, origin)
+    }).collect()
+}
+
+/// Get every pair of boxes that overlap, leaving out the pairs `shared` says
+/// are placed on each other on purpose.
+fn overlapping_pairs<T, F>(boxes: &[(T, Area)], mut shared: F) -> Vec<(T, T)>
+    where T: Clone,
+          F: FnMut(&T, &T) -> bool
+{
+    let mut result = Vec::new();
+    for (index, &(ref first, area)) in boxes.iter().enumerate() {
+        for &(ref second, other_area) in &boxes[index + 1..] {
+            if areas_overlap(area, other_area) && !shared(first, second) {
+                result.push((first.clone(), second.clone()))
+            }
+        }
+    }
+    result
+}
+
+/// Find the position closest to `current` where a box of the size is edge
+/// to edge with one of the `others` without overlapping any of them.
+///
+/// Without `others` the box stays where it is.
+fn adjacent_origin(current: Origin, size: Size, others: &[Area]) -> Option<Origin> {
+    if others.is_empty() {
+        return Some(current)
+    }
+    let clamp = |value: c_int, min: c_int, max: c_int| value.max(min).min(max);
+    let mut candidates = Vec::new();
+    for other in others {
+        let (x, y) = (other.origin.x, other.origin.y);
+        let (width, height) = (other.size.width, other.size.height);
+        // NOTE Keep at least a pixel of the edges touching, so it's
+        // adjacent rather than diagonal.
+        let along_y = clamp(current.y, y - size.height + 1, y + height - 1);
+        let along_x = clamp(current.x, x - size.width + 1, x + width - 1);
+        candidates.push(Origin::new(x - size.width, along_y));
+        candidates.push(Origin::new(x + width, along_y));
+        candidates.push(Origin::new(along_x, y - size.height));
+        candidates.push(Origin::new(along_x, y + height));
+    }
+    let distance = |origin: &Origin| {
+        let (dx, dy) = ((origin.x - current.x) as i64, (origin.y - current.y) as i64);
+        dx * dx + dy * dy
+    };
+    candidates.into_iter()
+        .filter(|&origin| {
+            let area = Area::new(origin, size);
+            !others.iter().any(|&other| areas_overlap(area, other))
+        })
+        .min_by_key(distance)
+}
+
+/// Whether the areas share any pixels. Areas that only touch don't overlap.
+fn areas_overlap(first: Area, second: Area) -> bool {
+    first.origin.x < second.origin.x + second.size.width
+        && second.origin.x < first.origin.x + first.size.width
+        && first.origin.y < second.origin.y + second.size.height
+        && second.origin.y < first.origin.y + first.size.height
+}
+
 impl Default for Handle {
     fn default() -> Self {
         Handle::new()
@@ -510,3 +1013,118 @@ impl PartialEq for Handle {
 }
 
 impl Eq for Handle {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: c_int, y: c_int, width: c_int, height: c_int) -> Area {
+        Area::new(Origin::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn manual_policy_leaves_placement_to_wlroots() {
+        assert_eq!(auto_origin(Policy::Manual, &[area(0, 0, 1920, 1080)]), None);
+        assert_eq!(arrangement(Policy::Manual, vec![(1, area(50, 50, 800, 600))]), vec![]);
+    }
+
+    #[test]
+    fn row_policies_add_to_the_right() {
+        let others = [area(0, 0, 1920, 1080), area(1920, 200, 1280, 1024)];
+        for &policy in &[Policy::LeftToRight, Policy::Compact] {
+            assert_eq!(auto_origin(policy, &others), Some(Origin::new(3200, 0)));
+            assert_eq!(auto_origin(policy, &[]), Some(Origin::new(0, 0)));
+        }
+    }
+
+    #[test]
+    fn column_policy_adds_below() {
+        let others = [area(0, 0, 1920, 1080), area(100, 1080, 1280, 1024)];
+        assert_eq!(auto_origin(Policy::TopToBottom, &others), Some(Origin::new(0, 2104)));
+    }
+
+    #[test]
+    fn mirror_policy_adds_at_the_origin() {
+        let others = [area(0, 0, 1920, 1080)];
+        assert_eq!(auto_origin(Policy::Mirror, &others), Some(Origin::new(0, 0)));
+    }
+
+    #[test]
+    fn arrange_closes_gaps_in_order() {
+        let boxes = vec![("right", area(5000, 300, 1280, 1024)),
+                         ("left", area(-100, 0, 1920, 1080))];
+        assert_eq!(arrangement(Policy::LeftToRight, boxes),
+                   vec![("left", Origin::new(0, 0)), ("right", Origin::new(1920, 0))]);
+    }
+
+    #[test]
+    fn arrange_stacks_a_column() {
+        let boxes = vec![("bottom", area(0, 4000, 1280, 1024)),
+                         ("top", area(300, 0, 1920, 1080))];
+        assert_eq!(arrangement(Policy::TopToBottom, boxes),
+                   vec![("top", Origin::new(0, 0)), ("bottom", Origin::new(0, 1080))]);
+    }
+
+    #[test]
+    fn arrange_mirror_puts_everything_at_the_origin() {
+        let boxes = vec![(1, area(1920, 0, 1280, 1024)), (2, area(0, 500, 800, 600))];
+        assert_eq!(arrangement(Policy::Mirror, boxes),
+                   vec![(1, Origin::new(0, 0)), (2, Origin::new(0, 0))]);
+    }
+
+    #[test]
+    fn touching_areas_do_not_overlap() {
+        assert!(!areas_overlap(area(0, 0, 100, 100), area(100, 0, 100, 100)));
+        assert!(!areas_overlap(area(0, 0, 100, 100), area(0, 100, 100, 100)));
+        assert!(areas_overlap(area(0, 0, 100, 100), area(99, 99, 100, 100)));
+    }
+
+    #[test]
+    fn overlaps_lists_each_pair_once() {
+        let boxes = [(1, area(0, 0, 100, 100)),
+                     (2, area(50, 50, 100, 100)),
+                     (3, area(200, 0, 100, 100)),
+                     (4, area(250, 0, 100, 100))];
+        assert_eq!(overlapping_pairs(&boxes, |_, _| false), vec![(1, 2), (3, 4)]);
+    }
+
+    #[test]
+    fn overlaps_skips_shared_coordinates() {
+        let boxes = [(1, area(0, 0, 100, 100)), (2, area(0, 0, 100, 100))];
+        assert_eq!(overlapping_pairs(&boxes, |&first, &second| (first, second) == (1, 2)),
+                   vec![]);
+    }
+
+    #[test]
+    fn snap_without_others_stays() {
+        let current = Origin::new(10, 20);
+        assert_eq!(adjacent_origin(current, Size::new(100, 100), &[]), Some(current));
+    }
+
+    #[test]
+    fn snap_moves_to_the_closest_edge() {
+        let others = [area(0, 0, 1920, 1080)];
+        let size = Size::new(1280, 1024);
+        assert_eq!(adjacent_origin(Origin::new(1800, 30), size, &others),
+                   Some(Origin::new(1920, 30)));
+        assert_eq!(adjacent_origin(Origin::new(100, 1000), size, &others),
+                   Some(Origin::new(100, 1080)));
+    }
+
+    #[test]
+    fn snap_keeps_edges_touching() {
+        let others = [area(0, 0, 1920, 1080)];
+        let size = Size::new(1280, 1024);
+        assert_eq!(adjacent_origin(Origin::new(2500, 5000), size, &others),
+                   Some(Origin::new(1919, 1080)));
+    }
+
+    #[test]
+    fn snap_avoids_other_outputs() {
+        let others = [area(0, 0, 1000, 1000), area(1000, 0, 1000, 1000)];
+        let origin = adjacent_origin(Origin::new(900, 0), Size::new(500, 500), &others).unwrap();
+        let snapped = area(origin.x, origin.y, 500, 500);
+        assert!(!others.iter().any(|&other| areas_overlap(snapped, other)));
+        assert_eq!(origin, Origin::new(900, -500));
+    }
+}
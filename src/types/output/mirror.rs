@@ -0,0 +1,555 @@
+//! Mirroring shows the content of one output on other outputs, e.g. showing
+//! a laptop panel on a projector.
+//!
+//! Every frame the source output renders is read back when its `Renderer` is
+//! finished, and drawn scaled onto the targets. Frames without damage aren't
+//! read back, the targets keep showing the last one. While an output is a target
+//! of a `Mirror` its `output::Handler::on_frame` is not called, as the mirror
+//! draws it instead.
+
+use std::{cell::{Cell, RefCell}, error::Error, fmt};
+
+use libc::{c_float, c_int};
+use wlroots_sys::{wlr_output, wlr_output_schedule_frame, wlr_texture_destroy,
+                  wl_output_transform::{WL_OUTPUT_TRANSFORM_FLIPPED_180,
+                                        WL_OUTPUT_TRANSFORM_NORMAL},
+                  wl_shm_format::WL_SHM_FORMAT_ARGB8888};
+
+use {area::{Area, Origin, Size},
+     compositor,
+     output::{self, Output},
+     render::{matrix, GenericRenderer, Renderer, Texture},
+     utils::Handleable};
+
+/// How the content of the source output is fitted onto a target output.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Scaling {
+    /// Scale the content to fit, keeping the aspect ratio. The rest of the
+    /// target is filled with the letterbox color.
+    Fit,
+    /// Scale the content to cover the whole target, keeping the aspect
+    /// ratio. Whatever doesn't fit is cut off.
+    Fill,
+    /// Scale the content to the size of the target, ignoring the aspect ratio.
+    Stretch,
+    /// Don't scale the content, centering it on the target.
+    Center
+}
+
+impl Default for Scaling {
+    fn default() -> Self {
+        Scaling::Fit
+    }
+}
+
+/// Errors from setting up a `Mirror`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MirrorError {
+    /// The output is already the source or a target of a mirror.
+    AlreadyMirrored,
+    /// An output can't mirror itself.
+    SameOutput,
+    /// The source output of the mirror was destroyed, or the mirror stopped.
+    Stopped
+}
+
+impl fmt::Display for MirrorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for MirrorError {
+    fn description(&self) -> &str {
+        match *self {
+            MirrorError::AlreadyMirrored => "Output is already part of a mirror",
+            MirrorError::SameOutput => "Output can't mirror itself",
+            MirrorError::Stopped => "Mirror is no longer running"
+        }
+    }
+}
+
+/// Shows the content of a source output on one or more target outputs.
+///
+/// The mirror keeps running until `stop` is called or the source output is
+/// destroyed. Dropping the `Mirror` doesn't stop it.
+#[derive(Debug)]
+pub struct Mirror {
+    id: u64,
+    source: output::Handle
+}
+
+/// The last frame read back from the source output.
+struct Frame {
+    pixels: Vec<u8>,
+    width: c_int,
+    height: c_int,
+    y_invert: bool,
+    /// Tells the frames apart, so a target only uploads each frame once.
+    serial: u64
+}
+
+/// The texture a target draws the frame of its source with.
+struct TargetTexture {
+    target: *mut wlr_output,
+    texture: Texture<'static>,
+    /// The serial of the frame that's in the texture.
+    serial: u64
+}
+
+struct MirrorState {
+    id: u64,
+    source: *mut wlr_output,
+    targets: Vec<*mut wlr_output>,
+    scaling: Scaling,
+    letterbox_color: [c_float; 4],
+    frame: Option<Frame>,
+    frames_read: u64,
+    textures: Vec<TargetTexture>
+}
+
+thread_local! {
+    static MIRRORS: RefCell<Vec<MirrorState>> = RefCell::new(Vec::new());
+    static NEXT_ID: Cell<u64> = Cell::new(0);
+}
+
+impl Mirror {
+    /// Start mirroring the output. It isn't shown anywhere until a target
+    /// is added with `add_target`.
+    pub fn new(source: &mut Output) -> Result<Mirror, MirrorError> {
+        unsafe {
+            let source_ptr = source.as_ptr();
+            if is_mirrored(source_ptr) {
+                return Err(MirrorError::AlreadyMirrored)
+            }
+            let id = NEXT_ID.with(|next| {
+                let id = next.get();
+                next.set(id + 1);
+                id
+            });
+            MIRRORS.with(|mirrors| {
+                mirrors.borrow_mut().push(MirrorState { id,
+                                                        source: source_ptr,
+                                                        targets: Vec::new(),
+                                                        scaling: Scaling::default(),
+                                                        letterbox_color: [0.0, 0.0, 0.0, 1.0],
+                                                        frame: None,
+                                                        frames_read: 0,
+                                                        textures: Vec::new() })
+            });
+            Ok(Mirror { id, source: source.weak_reference() })
+        }
+    }
+
+    /// Get a handle to the output that's being mirrored.
+    pub fn source(&self) -> output::Handle {
+        self.source.clone()
+    }
+
+    /// Show the source output on the target output as well.
+    ///
+    /// If the target is in an `output::layout::Layout` that follows mirrors
+    /// it's moved to the position of the source output.
+    pub fn add_target(&mut self, target: &mut Output) -> Result<(), MirrorError> {
+        unsafe {
+            let target_ptr = target.as_ptr();
+            if self.source.as_ptr() == target_ptr {
+                return Err(MirrorError::SameOutput)
+            }
+            if !self.is_running() {
+                return Err(MirrorError::Stopped)
+            }
+            if is_mirrored(target_ptr) {
+                return Err(MirrorError::AlreadyMirrored)
+            }
+            self.with_state(|state| state.targets.push(target_ptr));
+            mirrors_changed(target);
+            target.schedule_frame();
+            Ok(())
+        }
+    }
+
+    /// Stop showing the source output on the target output.
+    ///
+    /// Returns `false` if it wasn't a target of this mirror.
+    pub fn remove_target(&mut self, target: &mut Output) -> bool {
+        unsafe {
+            let target_ptr = target.as_ptr();
+            let removed = self.with_state(|state| {
+                let len = state.targets.len();
+                state.targets.retain(|&ptr| ptr != target_ptr);
+                destroy_textures(&mut state.textures, target_ptr);
+                len != state.targets.len()
+            });
+            if removed != Some(true) {
+                return false
+            }
+            mirrors_changed(target);
+            target.damage().add_whole();
+            target.schedule_frame();
+            true
+        }
+    }
+
+    /// Get handles to the outputs that show the source output.
+    pub fn targets(&self) -> Vec<output::Handle> {
+        let targets = self.with_state(|state| state.targets.clone()).unwrap_or_else(Vec::new);
+        targets.into_iter()
+            .map(|target| unsafe { output::Handle::from_ptr(target) })
+            .collect()
+    }
+
+    /// Get how the content is fitted onto the targets.
+    pub fn scaling(&self) -> Scaling {
+        self.with_state(|state| state.scaling).unwrap_or_default()
+    }
+
+    /// Set how the content is fitted onto the targets.
+    pub fn set_scaling(&mut self, scaling: Scaling) {
+        self.with_state(|state| state.scaling = scaling);
+    }
+
+    /// Set the color that the parts of a target not covered by the content
+    /// are filled with. Defaults to black.
+    pub fn set_letterbox_color(&mut self, color: [c_float; 4]) {
+        self.with_state(|state| state.letterbox_color = color);
+    }
+
+    /// Determines if the mirror is still running.
+    ///
+    /// It's stopped once the source output is destroyed.
+    pub fn is_running(&self) -> bool {
+        self.with_state(|_| ()).is_some()
+    }
+
+    /// Stop mirroring. The targets go back to being drawn by their
+    /// `output::Handler`.
+    pub fn stop(self) {
+        let state = MIRRORS.with(|mirrors| {
+            let mut mirrors = mirrors.borrow_mut();
+            let index = mirrors.iter().position(|state| state.id == self.id);
+            index.map(|index| mirrors.remove(index))
+        });
+        if let Some(state) = state {
+            unsafe {
+                for texture in state.textures {
+                    wlr_texture_destroy(texture.texture.as_ptr());
+                }
+                for target in state.targets {
+                    output::Handle::from_ptr(target).run(|target| {
+                                                        mirrors_changed(target);
+                                                        target.damage().add_whole();
+                                                        target.schedule_frame();
+                                                    })
+                                                    .ok();
+                }
+            }
+        }
+    }
+
+    fn with_state<F, R>(&self, runner: F) -> Option<R>
+        where F: FnOnce(&mut MirrorState) -> R
+    {
+        MIRRORS.with(|mirrors| {
+            mirrors.borrow_mut().iter_mut().find(|state| state.id == self.id).map(runner)
+        })
+    }
+}
+
+/// Determines if the output is the source or a target of a mirror.
+fn is_mirrored(output: *mut wlr_output) -> bool {
+    MIRRORS.with(|mirrors| {
+        mirrors.borrow().iter().any(|state| {
+            state.source == output || state.targets.contains(&output)
+        })
+    })
+}
+
+/// Get the output that's shown on the given output, if it's a mirror target.
+pub(crate) fn mirror_source_of(target: *mut wlr_output) -> Option<*mut wlr_output> {
+    MIRRORS.with(|mirrors| {
+        mirrors.borrow().iter()
+            .find(|state| state.targets.contains(&target))
+            .map(|state| state.source)
+    })
+}
+
+/// Lets the layout the output is in place it next to or on its source.
+unsafe fn mirrors_changed(output: &mut Output) {
+    if let Some(layout) = output.layout() {
+        layout.run(|layout| {
+                  if layout.follows_mirrors() {
+                      layout.arrange()
+                  }
+              })
+              .ok();
+    }
+}
+
+/// Stops the mirrors of the output that's being destroyed, or stops showing
+/// anything on it if it's a target.
+pub(crate) unsafe fn mirror_output_removed(output: *mut wlr_output) {
+    let orphaned = MIRRORS.with(|mirrors| {
+        let mut mirrors = mirrors.borrow_mut();
+        for state in mirrors.iter_mut() {
+            state.targets.retain(|&target| target != output);
+            destroy_textures(&mut state.textures, output);
+        }
+        let index = mirrors.iter().position(|state| state.source == output);
+        index.map(|index| {
+                 let state = mirrors.remove(index);
+                 for texture in state.textures {
+                     wlr_texture_destroy(texture.texture.as_ptr());
+                 }
+                 state.targets
+             })
+             .unwrap_or_else(Vec::new)
+    });
+    for target in orphaned {
+        output::Handle::from_ptr(target).run(|target| {
+                                            mirrors_changed(target);
+                                            target.damage().add_whole();
+                                            target.schedule_frame();
+                                        })
+                                        .ok();
+    }
+}
+
+/// Reads back what was rendered if the output is the source of a mirror,
+/// and schedules a frame on its targets.
+///
+/// Nothing is read back if the damage of the frame is empty, as the last
+/// frame that was read back still shows the same.
+///
+/// Called before the buffers of the output are swapped.
+pub(crate) unsafe fn capture_mirror_source(renderer: &mut Renderer) {
+    let source = renderer.output.as_ptr();
+    let undamaged = match renderer.damage {
+        Some((ref damage, _)) => damage.is_empty(),
+        None => false
+    };
+    let capture = MIRRORS.with(|mirrors| {
+        mirrors.borrow_mut().iter_mut()
+            .find(|state| state.source == source && !state.targets.is_empty())
+            .and_then(|state| {
+                if undamaged && state.frame.is_some() {
+                    return None
+                }
+                state.frames_read += 1;
+                Some((state.targets.clone(), state.frame.take(), state.frames_read))
+            })
+    });
+    let (targets, old_frame, serial) = match capture {
+        Some(capture) => capture,
+        None => return
+    };
+    let (width, height) = renderer.output.size();
+    let stride = width as u32 * 4;
+    // NOTE Reuse the previous buffer, the size rarely changes.
+    let mut pixels = old_frame.map(|frame| frame.pixels).unwrap_or_else(Vec::new);
+    pixels.resize(stride as usize * height as usize, 0);
+    let frame = renderer.read_pixels(WL_SHM_FORMAT_ARGB8888,
+                                     stride,
+                                     width as u32,
+                                     height as u32,
+                                     0,
+                                     0,
+                                     &mut pixels)
+                        .map(|y_invert| Frame { pixels, width, height, y_invert, serial });
+    if frame.is_none() {
+        wlr_log!(WLR_ERROR, "Could not read pixels of mirrored output");
+    }
+    MIRRORS.with(|mirrors| {
+        let mut mirrors = mirrors.borrow_mut();
+        if let Some(state) = mirrors.iter_mut().find(|state| state.source == source) {
+            state.frame = frame;
+        }
+    });
+    for target in targets {
+        wlr_output_schedule_frame(target)
+    }
+}
+
+/// Draws the source of the mirror on the output, if it's a mirror target.
+///
+/// Returns `false` if it's not a target, and should be drawn as usual.
+pub(crate) unsafe fn render_mirror_target(compositor: compositor::Handle,
+                                          output: &mut Output)
+                                          -> bool {
+    let target = output.as_ptr();
+    let found = MIRRORS.with(|mirrors| {
+        mirrors.borrow_mut().iter_mut()
+            .find(|state| state.targets.contains(&target))
+            .map(|state| {
+                let index = state.textures.iter().position(|texture| texture.target == target);
+                let texture = index.map(|index| state.textures.remove(index));
+                (state.id, state.scaling, state.letterbox_color, state.frame.take(), texture)
+            })
+    });
+    let (id, scaling, letterbox_color, frame, mut texture) = match found {
+        Some(found) => found,
+        None => return false
+    };
+    let target_size = output.transformed_resolution();
+    let projection = output.transform_matrix();
+    let result = compositor.run(|compositor| {
+        let renderer = match compositor.renderer {
+            Some(ref mut renderer) => renderer,
+            None => return
+        };
+        if let Some(ref frame) = frame {
+            texture = upload(renderer, texture.take(), target, frame);
+        }
+        let mut render_context = renderer.render(output, None);
+        render_context.clear(letterbox_color);
+        if let (Some(texture), Some(frame)) = (texture.as_ref(), frame.as_ref()) {
+            let area = fit(scaling, Size::new(frame.width, frame.height), target_size);
+            let transform = if frame.y_invert {
+                WL_OUTPUT_TRANSFORM_FLIPPED_180
+            } else {
+                WL_OUTPUT_TRANSFORM_NORMAL
+            };
+            let matrix = matrix::project_box(area, transform, 0.0, projection);
+            render_context.render_texture_with_matrix(&texture.texture, matrix);
+        }
+    });
+    if result.is_err() {
+        wlr_log!(WLR_ERROR, "Could not borrow the compositor to draw a mirror target");
+    }
+    // NOTE The frame is kept for the other targets, and for redrawing.
+    let texture = MIRRORS.with(|mirrors| {
+        let mut mirrors = mirrors.borrow_mut();
+        let state = match mirrors.iter_mut().find(|state| state.id == id) {
+            Some(state) => state,
+            None => return texture
+        };
+        if state.frame.is_none() {
+            state.frame = frame;
+        }
+        match texture {
+            Some(texture) if state.targets.contains(&target) => {
+                state.textures.push(texture);
+                None
+            }
+            texture => texture
+        }
+    });
+    // NOTE The mirror stopped, or stopped showing on the target, meanwhile.
+    if let Some(texture) = texture {
+        wlr_texture_destroy(texture.texture.as_ptr());
+    }
+    true
+}
+
+/// Gets the texture of the target up to date with the frame.
+///
+/// The pixels are written into the texture the target already has if it's
+/// the size of the frame, so a new one is only made when the size changes.
+unsafe fn upload(renderer: &mut GenericRenderer,
+                 texture: Option<TargetTexture>,
+                 target: *mut wlr_output,
+                 frame: &Frame)
+                 -> Option<TargetTexture> {
+    let (width, height) = (frame.width as u32, frame.height as u32);
+    let stride = width * 4;
+    if let Some(mut texture) = texture {
+        if texture.serial == frame.serial {
+            return Some(texture)
+        }
+        if texture.texture.size() == (frame.width, frame.height) &&
+           texture.texture.write_pixels(stride, width, height, 0, 0, 0, 0, &frame.pixels) {
+            texture.serial = frame.serial;
+            return Some(texture)
+        }
+        renderer.drop_texture(texture.texture);
+    }
+    renderer.create_texture_from_pixels(WL_SHM_FORMAT_ARGB8888,
+                                        stride,
+                                        width,
+                                        height,
+                                        &frame.pixels)
+            .map(|texture| TargetTexture { target, texture, serial: frame.serial })
+}
+
+/// Destroys the texture of the target, if it has one.
+unsafe fn destroy_textures(textures: &mut Vec<TargetTexture>, target: *mut wlr_output) {
+    while let Some(index) = textures.iter().position(|texture| texture.target == target) {
+        wlr_texture_destroy(textures.remove(index).texture.as_ptr());
+    }
+}
+
+/// Get where content of the given size is drawn on a target of the given size.
+fn fit(scaling: Scaling, content: Size, (width, height): (c_int, c_int)) -> Area {
+    if content.width <= 0 || content.height <= 0 {
+        return Area::new(Origin::default(), Size::new(width, height))
+    }
+    let scale_x = width as f64 / content.width as f64;
+    let scale_y = height as f64 / content.height as f64;
+    let scale = match scaling {
+        Scaling::Fit => scale_x.min(scale_y),
+        Scaling::Fill => scale_x.max(scale_y),
+        Scaling::Center => 1.0,
+        Scaling::Stretch => return Area::new(Origin::default(), Size::new(width, height))
+    };
+    let size = Size::new((content.width as f64 * scale).round() as c_int,
+                         (content.height as f64 * scale).round() as c_int);
+    let origin = Origin::new((width - size.width) / 2, (height - size.height) / 2);
+    Area::new(origin, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(x: c_int, y: c_int, width: c_int, height: c_int) -> Area {
+        Area::new(Origin::new(x, y), Size::new(width, height))
+    }
+
+    #[test]
+    fn fit_letterboxes_wider_content() {
+        assert_eq!(fit(Scaling::Fit, Size::new(1920, 1080), (1280, 1024)),
+                   area(0, 152, 1280, 720));
+    }
+
+    #[test]
+    fn fit_pillarboxes_taller_content() {
+        assert_eq!(fit(Scaling::Fit, Size::new(1280, 1024), (1920, 1080)),
+                   area(285, 0, 1350, 1080));
+    }
+
+    #[test]
+    fn fill_covers_the_target() {
+        assert_eq!(fit(Scaling::Fill, Size::new(1920, 1080), (1280, 1024)),
+                   area(-270, 0, 1820, 1024));
+    }
+
+    #[test]
+    fn stretch_uses_the_whole_target() {
+        assert_eq!(fit(Scaling::Stretch, Size::new(1920, 1080), (1280, 1024)),
+                   area(0, 0, 1280, 1024));
+    }
+
+    #[test]
+    fn center_keeps_the_content_size() {
+        assert_eq!(fit(Scaling::Center, Size::new(800, 600), (1920, 1080)),
+                   area(560, 240, 800, 600));
+        assert_eq!(fit(Scaling::Center, Size::new(3840, 2160), (1920, 1080)),
+                   area(-960, -540, 3840, 2160));
+    }
+
+    #[test]
+    fn same_aspect_ratio_fills_exactly() {
+        for &scaling in &[Scaling::Fit, Scaling::Fill] {
+            assert_eq!(fit(scaling, Size::new(3840, 2160), (1920, 1080)),
+                       area(0, 0, 1920, 1080));
+        }
+    }
+
+    #[test]
+    fn empty_content_uses_the_whole_target() {
+        assert_eq!(fit(Scaling::Fit, Size::new(0, 1080), (1280, 1024)),
+                   area(0, 0, 1280, 1024));
+        assert_eq!(fit(Scaling::Center, Size::new(1920, -1), (1280, 1024)),
+                   area(0, 0, 1280, 1024));
+    }
+}
@@ -1,11 +1,16 @@
 mod output;
+mod config;
 pub mod layout;
+pub mod profiles;
 mod mode;
 mod cursor;
 mod damage;
+mod mirror;
 
+pub use self::config::*;
 pub use self::cursor::*;
 pub use self::damage::*;
+pub use self::mirror::*;
 pub use self::output::*;
 pub use self::mode::*;
 
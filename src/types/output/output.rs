@@ -1,22 +1,29 @@
 //! TODO Documentation
 
-use std::{cell::Cell, ffi::CStr, mem::ManuallyDrop, rc::{Rc, Weak},
-          time::Duration, panic, ptr};
+use std::{cell::Cell, ffi::CStr, mem::ManuallyDrop, rc::{Rc, Weak}, time::Duration, panic, ptr};
+#[cfg(feature = "adaptive_sync")]
+use std::{error::Error, fmt};
 
 use libc::{c_float, c_int, clock_t};
 use wayland_sys::server::WAYLAND_SERVER_HANDLE;
 use wlroots_sys::{timespec, wl_list, wl_output_subpixel, wl_output_transform, wlr_output,
                   wlr_output_damage, wlr_output_effective_resolution, wlr_output_enable,
+                  wlr_output_is_drm,
                   wlr_output_get_gamma_size, wlr_output_make_current, wlr_output_mode,
                   wlr_output_schedule_frame, wlr_output_set_custom_mode,
                   wlr_output_set_gamma, wlr_output_set_mode,
                   wlr_output_set_position, wlr_output_set_scale, wlr_output_set_transform,
                   wlr_output_swap_buffers, wlr_output_transformed_resolution};
+#[cfg(feature = "adaptive_sync")]
+use wlroots_sys::{wlr_output_adaptive_sync_status, wlr_output_enable_adaptive_sync,
+                  wlr_output_is_headless};
 
 use {area::{Origin, Size},
+     backend,
      utils::{self, HandleErr, HandleResult, Handleable, c_to_rust_string},
      output::{self, layout},
-     render::PixmanRegion};
+     render::PixmanRegion,
+     surface};
 pub use manager::output_handler::*;
 pub use manager::output_manager::{OutputBuilder as Builder, BuilderResult};
 pub(crate) use manager::output_manager::Manager;
@@ -24,11 +31,94 @@ pub(crate) use manager::output_manager::Manager;
 pub type Subpixel = wl_output_subpixel;
 pub type Transform = wl_output_transform;
 
+/// Whether variable refresh rate is in use on an output.
+#[cfg(feature = "adaptive_sync")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AdaptiveSyncStatus {
+    Disabled,
+    Enabled,
+    /// The backend doesn't know, e.g. because it's nested in another
+    /// compositor that decides.
+    Unknown
+}
+
+/// The reasons `Output::set_adaptive_sync` can fail.
+#[cfg(feature = "adaptive_sync")]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AdaptiveSyncError {
+    /// The backend can't use variable refresh rate at all, e.g. because it's
+    /// headless.
+    Unsupported
+}
+
+#[cfg(feature = "adaptive_sync")]
+impl fmt::Display for AdaptiveSyncError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            AdaptiveSyncError::Unsupported => {
+                write!(f, "Adaptive sync is not supported by the output")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "adaptive_sync")]
+impl Error for AdaptiveSyncError {
+    fn description(&self) -> &str {
+        match *self {
+            AdaptiveSyncError::Unsupported => "Adaptive sync is not supported by the output"
+        }
+    }
+}
+
+/// How a length is rounded after it's been scaled by a fractional scale.
+///
+/// wlroots truncates, which is what `Rounding::Down` does.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Rounding {
+    Down,
+    Nearest,
+    Up
+}
+
+impl Rounding {
+    /// Convert a length in pixels to a length in layout coordinates.
+    pub fn to_logical(self, length: c_int, scale: c_float) -> c_int {
+        self.round(length as f64 / scale as f64)
+    }
+
+    /// Convert a length in layout coordinates to a length in pixels.
+    pub fn to_physical(self, length: c_int, scale: c_float) -> c_int {
+        self.round(length as f64 * scale as f64)
+    }
+
+    fn round(self, length: f64) -> c_int {
+        match self {
+            Rounding::Down => length.floor() as c_int,
+            Rounding::Nearest => length.round() as c_int,
+            Rounding::Up => length.ceil() as c_int
+        }
+    }
+}
+
 pub(crate) struct OutputState {
     pub(crate) output: *mut UserOutput,
     handle: Weak<Cell<bool>>,
     damage: *mut wlr_output_damage,
-    layout_handle: Option<layout::Handle>
+    layout_handle: Option<layout::Handle>,
+    /// Surfaces rendered since the buffers were last swapped, which get
+    /// presentation feedback when they are.
+    #[cfg(feature = "presentation_time")]
+    presented_surfaces: Vec<surface::Handle>,
+    /// The number of times the buffers have been swapped.
+    #[cfg(feature = "presentation_time")]
+    presentation_seq: u64,
+    /// The adaptive sync status last reported to the `output::Handler`.
+    #[cfg(feature = "adaptive_sync")]
+    adaptive_sync_status: AdaptiveSyncStatus,
+    /// Whether adaptive sync was set since the status was last reported.
+    #[cfg(feature = "adaptive_sync")]
+    adaptive_sync_requested: bool
 }
 
 #[derive(Debug)]
@@ -79,7 +169,16 @@ impl Output {
         let state = Box::new(OutputState { output: ptr::null_mut(),
                                            handle,
                                            damage: damage.as_ptr(),
-                                           layout_handle: None });
+                                           layout_handle: None,
+                                           #[cfg(feature = "presentation_time")]
+                                           presented_surfaces: Vec::new(),
+                                           #[cfg(feature = "presentation_time")]
+                                           presentation_seq: 0,
+                                           #[cfg(feature = "adaptive_sync")]
+                                           adaptive_sync_status:
+                                               AdaptiveSyncStatus::Disabled,
+                                           #[cfg(feature = "adaptive_sync")]
+                                           adaptive_sync_requested: false });
         (*output).data = Box::into_raw(state) as *mut _;
         Output { liveliness,
                  damage,
@@ -103,6 +202,48 @@ impl Output {
         (*self.output).data as *mut _
     }
 
+    /// Records that the surface was rendered in the frame being drawn.
+    #[cfg(feature = "presentation_time")]
+    pub(crate) unsafe fn add_presented_surface(&mut self, surface: surface::Handle) {
+        let user_data = self.user_data();
+        if user_data.is_null() {
+            return
+        }
+        if !(*user_data).presented_surfaces.contains(&surface) {
+            (*user_data).presented_surfaces.push(surface)
+        }
+    }
+
+    /// Takes the surfaces rendered since the last swap, along with the
+    /// sequence number of the frame they were presented in.
+    #[cfg(feature = "presentation_time")]
+    pub(crate) unsafe fn take_presented_surfaces(&mut self) -> (Vec<surface::Handle>, u64) {
+        let user_data = self.user_data();
+        if user_data.is_null() {
+            return (Vec::new(), 0)
+        }
+        (*user_data).presentation_seq += 1;
+        let surfaces = (*user_data).presented_surfaces.drain(..).collect();
+        (surfaces, (*user_data).presentation_seq)
+    }
+
+    /// Records the current adaptive sync status, returning whether it changed
+    /// or was requested with `set_adaptive_sync` since the last time this was
+    /// called.
+    #[cfg(feature = "adaptive_sync")]
+    pub(crate) unsafe fn update_adaptive_sync_status(&mut self) -> bool {
+        let user_data = self.user_data();
+        if user_data.is_null() {
+            return false
+        }
+        let status = self.adaptive_sync_status();
+        let changed = (*user_data).adaptive_sync_status != status ||
+                      (*user_data).adaptive_sync_requested;
+        (*user_data).adaptive_sync_status = status;
+        (*user_data).adaptive_sync_requested = false;
+        changed
+    }
+
     /// Used to clear the pointer to an OutputLayout when the OutputLayout
     /// removes this Output from its listing.
     pub(crate) unsafe fn clear_output_layout_data(&mut self) {
@@ -213,6 +354,19 @@ impl Output {
         }
     }
 
+    /// Gets the GPU the output is connected to.
+    ///
+    /// Returns `None` if it's not a DRM output, or if its backend wasn't
+    /// created with `backend::Drm::new`, e.g. by `backend::Multi::auto_create`.
+    pub fn gpu(&self) -> Option<backend::GpuInfo> {
+        unsafe {
+            if !wlr_output_is_drm(self.output) {
+                return None
+            }
+            backend::drm::drm_gpu((*self.output).backend)
+        }
+    }
+
     /// Determines if the output is enabled or not.
     pub fn enabled(&self) -> bool {
         unsafe { (*self.output).enabled }
@@ -342,6 +496,10 @@ impl Output {
     }
 
     /// Computes the transformed and scaled output resolution.
+    ///
+    /// With a fractional scale the size is rounded down, e.g. a 1366 pixel wide
+    /// output at scale 1.5 is 910 wide in layout coordinates. Use
+    /// `effective_resolution_rounded` to round differently.
     pub fn effective_resolution(&self) -> (c_int, c_int) {
         unsafe {
             let (mut x, mut y) = (0, 0);
@@ -350,6 +508,27 @@ impl Output {
         }
     }
 
+    /// Computes the transformed and scaled output resolution, rounding the
+    /// size in layout coordinates as requested.
+    ///
+    /// `Rounding::Up` makes sure the whole output is covered when the layout
+    /// size is scaled back up, at the cost of the last row or column of
+    /// pixels possibly being outside of it.
+    pub fn effective_resolution_rounded(&self, rounding: Rounding) -> (c_int, c_int) {
+        let (width, height) = self.transformed_resolution();
+        let scale = self.scale();
+        (rounding.to_logical(width, scale), rounding.to_logical(height, scale))
+    }
+
+    /// Get the integer buffer scale clients should render at, which is the
+    /// output scale rounded up.
+    ///
+    /// This is the scale advertised by `wl_output`. Clients that support
+    /// `extensions::fractional_scale` can render at the exact scale instead.
+    pub fn preferred_buffer_scale(&self) -> i32 {
+        (self.scale().ceil() as i32).max(1)
+    }
+
     pub fn transform_matrix(&self) -> [c_float; 9] {
         unsafe { (*self.output).transform_matrix }
     }
@@ -378,6 +557,46 @@ impl Output {
         unsafe { wlr_output_enable(self.output, enable) }
     }
 
+    /// Enables or disables variable refresh rate on the output.
+    ///
+    /// When enabled the output waits for frames to be rendered instead of
+    /// refreshing at a fixed rate, up to the refresh rate of its mode.
+    ///
+    /// Whether the output can do it is only known once the backend tried, so
+    /// `output::Handler::on_adaptive_sync_change` is called before the next
+    /// frame with the result: if `Output::adaptive_sync_status` is still
+    /// `Disabled` after enabling, the output doesn't support it.
+    ///
+    /// Enabling fails right away with `AdaptiveSyncError::Unsupported` only
+    /// on the headless backend, which never supports it.
+    #[cfg(feature = "adaptive_sync")]
+    pub fn set_adaptive_sync(&mut self, enabled: bool) -> Result<(), AdaptiveSyncError> {
+        unsafe {
+            if enabled && wlr_output_is_headless(self.output) {
+                return Err(AdaptiveSyncError::Unsupported)
+            }
+            wlr_output_enable_adaptive_sync(self.output, enabled);
+            let user_data = self.user_data();
+            if !user_data.is_null() {
+                (*user_data).adaptive_sync_requested = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get whether variable refresh rate is in use on the output.
+    #[cfg(feature = "adaptive_sync")]
+    pub fn adaptive_sync_status(&self) -> AdaptiveSyncStatus {
+        use self::wlr_output_adaptive_sync_status::*;
+        unsafe {
+            match (*self.output).adaptive_sync_status {
+                WLR_OUTPUT_ADAPTIVE_SYNC_DISABLED => AdaptiveSyncStatus::Disabled,
+                WLR_OUTPUT_ADAPTIVE_SYNC_ENABLED => AdaptiveSyncStatus::Enabled,
+                WLR_OUTPUT_ADAPTIVE_SYNC_UNKNOWN => AdaptiveSyncStatus::Unknown
+            }
+        }
+    }
+
     /// Sets the gamma based on the size.
     pub fn set_gamma(&mut self, size: usize, mut r: u16, mut g: u16, mut b: u16) -> bool {
         unsafe { wlr_output_set_gamma(self.output, size, &mut r, &mut g, &mut b) }
@@ -394,6 +613,10 @@ impl Output {
     }
 
     /// Set the scale applied to this output.
+    ///
+    /// The scale may be fractional, e.g. 1.5. Surfaces are then told the exact
+    /// scale through `extensions::fractional_scale`, and everything else
+    /// renders at `preferred_buffer_scale` and is scaled down.
     pub fn set_scale(&mut self, scale: c_float) {
         unsafe { wlr_output_set_scale(self.output, scale) }
     }
@@ -0,0 +1,709 @@
+//! Remembering how a set of outputs should be configured.
+//!
+//! A [`Profile`](./struct.Profile.html) describes how to configure every output
+//! of one particular setup, e.g. "laptop alone" or "laptop on the desk with two
+//! monitors". Outputs are recognized by their make, model and serial (or their
+//! connector name), so a monitor gets its settings back no matter which port
+//! it's plugged into.
+//!
+//! Give the saved profiles to `output::manager::Builder::profiles`. Whenever
+//! an output is added or removed the first profile that matches the connected
+//! outputs is applied, after the `output_added` callback has run.
+//!
+//! Profiles are saved in a simple line based text format:
+//!
+//! ```text
+//! # Comments start with a '#'
+//! profile docked
+//! output make="Dell Inc." model="DELL U2415" serial=7MT0186R0CLS mode=1920x1200@59950
+//! output name=eDP-1 scale=2 position=1920,0 transform=normal
+//!
+//! profile laptop
+//! output name=eDP-1 custom_mode=2560x1440 scale=1.5
+//! output make=* disable
+//! ```
+//!
+//! A profile matches when each of its outputs matches exactly one connected
+//! output and no connected output is left over. Criteria that are left out or
+//! are `*` match anything, while a quoted `"*"` only matches a literal `*`.
+//! The mode refresh rate is in mHz; when it's left out the highest refresh
+//! rate for the size is used.
+
+use std::{error::Error, fmt, str::FromStr};
+
+use libc::c_float;
+use wlroots_sys::wl_output_transform::{self, *};
+
+use {area::{Origin, Size},
+     output::{self, layout, ConfigError, Output, Transform},
+     utils::HandleErr};
+
+/// Which outputs an entry of a `Profile` applies to.
+///
+/// `None` matches anything.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OutputMatch {
+    /// The connector name, e.g. `eDP-1`. See `Output::name`.
+    pub name: Option<String>,
+    /// See `Output::make`.
+    pub make: Option<String>,
+    /// See `Output::model`.
+    pub model: Option<String>,
+    /// See `Output::serial`.
+    pub serial: Option<String>
+}
+
+/// A mode to set, as written in a profile.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct ProfileMode {
+    pub size: Size,
+    /// The refresh rate in mHz, or `None` for the highest one available.
+    pub refresh: Option<i32>,
+    /// Whether the mode is not one of the modes of the output.
+    pub custom: bool
+}
+
+/// The settings for an output matched by a `Profile`.
+///
+/// Settings that are `None` are left as they are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputSettings {
+    pub enabled: bool,
+    pub mode: Option<ProfileMode>,
+    pub scale: Option<c_float>,
+    pub transform: Option<Transform>,
+    pub position: Option<Origin>
+}
+
+/// An output of a `Profile`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileOutput {
+    pub criteria: OutputMatch,
+    pub settings: OutputSettings
+}
+
+/// The configuration of every output of one particular setup.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub outputs: Vec<ProfileOutput>
+}
+
+/// A set of saved profiles, and the outputs they are matched against.
+#[derive(Debug, Clone, Default)]
+pub struct Profiles {
+    profiles: Vec<Profile>,
+    layout: Option<layout::Handle>,
+    connected: Vec<output::Handle>,
+    active: Option<String>
+}
+
+/// A syntax error in the text format of profiles.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    pub message: String
+}
+
+impl OutputMatch {
+    /// Match the output by its make, model and serial.
+    pub fn identify(output: &Output) -> Self {
+        OutputMatch { name: None,
+                      make: Some(output.make()),
+                      model: Some(output.model()),
+                      serial: Some(output.serial()) }
+    }
+
+    /// Whether the output matches all the criteria.
+    pub fn matches(&self, output: &Output) -> bool {
+        self.matches_identity(&output.name(), &output.make(), &output.model(), &output.serial())
+    }
+
+    fn matches_identity(&self, name: &str, make: &str, model: &str, serial: &str) -> bool {
+        fn matches(criterion: &Option<String>, value: &str) -> bool {
+            criterion.as_ref().map(|criterion| criterion == value).unwrap_or(true)
+        }
+        matches(&self.name, name) && matches(&self.make, make) &&
+        matches(&self.model, model) && matches(&self.serial, serial)
+    }
+}
+
+impl Default for OutputSettings {
+    fn default() -> Self {
+        OutputSettings { enabled: true,
+                         mode: None,
+                         scale: None,
+                         transform: None,
+                         position: None }
+    }
+}
+
+impl OutputSettings {
+    /// Get the current settings of the output.
+    pub fn current(output: &Output) -> Self {
+        let (x, y) = output.layout_space_pos();
+        let mode = output.current_mode().map(|mode| {
+            let (width, height) = mode.dimensions();
+            ProfileMode { size: Size::new(width, height),
+                          refresh: Some(mode.refresh()),
+                          custom: false }
+        });
+        OutputSettings { enabled: output.enabled(),
+                         mode,
+                         scale: Some(output.scale()),
+                         transform: Some(output.get_transform()),
+                         position: Some(Origin::new(x, y)) }
+    }
+}
+
+impl Profile {
+    /// Make a profile that restores the current state of the outputs.
+    pub fn from_outputs<'output, I>(name: String, outputs: I) -> Self
+        where I: IntoIterator<Item = &'output Output>
+    {
+        let outputs = outputs.into_iter()
+            .map(|output| ProfileOutput { criteria: OutputMatch::identify(output),
+                                          settings: OutputSettings::current(output) })
+            .collect();
+        Profile { name, outputs }
+    }
+
+    /// Pair each connected output with the entry of this profile it matches.
+    ///
+    /// Returns `None` if the profile doesn't match the connected outputs.
+    pub fn assign<'profile>(&'profile self,
+                            outputs: &[output::Handle])
+                            -> Option<Vec<(output::Handle, &'profile OutputSettings)>> {
+        if outputs.len() != self.outputs.len() {
+            return None
+        }
+        let mut candidates = Vec::with_capacity(outputs.len());
+        for handle in outputs {
+            let matching = handle.run(|output| {
+                                          self.outputs.iter()
+                                              .map(|entry| entry.criteria.matches(output))
+                                              .collect::<Vec<_>>()
+                                      }).ok()?;
+            candidates.push(matching);
+        }
+        let mut assignment = vec![None; outputs.len()];
+        if !assign(&candidates, 0, &mut assignment) {
+            return None
+        }
+        Some(outputs.iter()
+                 .zip(assignment)
+                 .map(|(handle, entry)| (handle.clone(), &self.outputs[entry.unwrap()].settings))
+                 .collect())
+    }
+}
+
+/// Find an entry for each output from `index` on, backtracking when the
+/// outputs before claimed the entries the later ones need.
+fn assign(candidates: &[Vec<bool>], index: usize, assignment: &mut [Option<usize>]) -> bool {
+    if index == candidates.len() {
+        return true
+    }
+    for entry in 0..candidates[index].len() {
+        if !candidates[index][entry] || assignment.contains(&Some(entry)) {
+            continue
+        }
+        assignment[index] = Some(entry);
+        if assign(candidates, index + 1, assignment) {
+            return true
+        }
+        assignment[index] = None;
+    }
+    false
+}
+
+impl Profiles {
+    /// Use the profiles, in order of preference.
+    pub fn new(profiles: Vec<Profile>) -> Self {
+        Profiles { profiles, ..Profiles::default() }
+    }
+
+    /// Parse profiles from the text format described in the module documentation.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        text.parse()
+    }
+
+    /// Set the layout the outputs are in, so positions are set in it.
+    pub fn layout(mut self, layout: layout::Handle) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    /// Get the saved profiles.
+    pub fn profiles(&self) -> &[Profile] {
+        &self.profiles
+    }
+
+    /// Get the saved profiles mutably, e.g. to save the current setup with
+    /// `Profile::from_outputs`.
+    ///
+    /// Changes are taken into account the next time outputs are added or
+    /// removed, or when `reapply` is called.
+    pub fn profiles_mut(&mut self) -> &mut Vec<Profile> {
+        &mut self.profiles
+    }
+
+    /// Get the name of the profile that was applied last, if the connected
+    /// outputs matched one.
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_ref().map(|name| name.as_str())
+    }
+
+    /// Get the outputs the profiles are matched against.
+    pub fn connected(&self) -> &[output::Handle] {
+        &self.connected
+    }
+
+    /// Find the first profile that matches the connected outputs.
+    pub fn find_match(&self) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.assign(&self.connected).is_some())
+    }
+
+    /// Apply the first profile that matches the connected outputs.
+    ///
+    /// Returns the name of the profile, or `None` if none matched. If the
+    /// profile couldn't be applied the outputs are left unchanged.
+    pub fn reapply(&mut self) -> Result<Option<String>, ConfigError> {
+        // Outputs destroyed while the profiles were borrowed are still here.
+        self.connected.retain(|handle| handle.run(|_| ()) != Err(HandleErr::AlreadyDropped));
+        let (name, config) = {
+            let profile = match self.find_match() {
+                Some(profile) => profile,
+                None => {
+                    self.active = None;
+                    return Ok(None)
+                }
+            };
+            let assignment = profile.assign(&self.connected).unwrap_or_else(Vec::new);
+            let mut config = output::Config::new();
+            if let Some(ref layout) = self.layout {
+                config = config.layout(layout.clone());
+            }
+            for (handle, settings) in assignment {
+                config = settings.add_to(config, &handle);
+            }
+            (profile.name.clone(), config)
+        };
+        config.commit()?;
+        self.active = Some(name.clone());
+        Ok(Some(name))
+    }
+
+    /// Start matching against a newly added output.
+    pub(crate) fn output_added(&mut self, output: output::Handle) {
+        self.connected.push(output);
+        self.reapply_logged()
+    }
+
+    /// Stop matching against an output that is being destroyed.
+    pub(crate) fn output_removed(&mut self, output: &output::Handle) {
+        self.connected.retain(|connected| connected != output);
+        self.reapply_logged()
+    }
+
+    fn reapply_logged(&mut self) {
+        match self.reapply() {
+            Ok(Some(name)) => wlr_log!(WLR_DEBUG, "Applied output profile {}", name),
+            Ok(None) => wlr_log!(WLR_DEBUG, "No output profile matches the outputs"),
+            Err(err) => wlr_log!(WLR_ERROR, "Could not apply output profile: {}", err)
+        }
+    }
+}
+
+impl OutputSettings {
+    fn add_to(&self, config: output::Config, handle: &output::Handle) -> output::Config {
+        let config = config.enable(handle, self.enabled);
+        if !self.enabled {
+            return config
+        }
+        let config = match self.mode {
+            Some(ProfileMode { size, custom: true, refresh }) => {
+                config.custom_mode(handle, size, refresh.unwrap_or(0))
+            }
+            Some(ProfileMode { size, refresh: Some(refresh), .. }) => {
+                config.mode(handle, size, refresh)
+            }
+            Some(ProfileMode { size, refresh: None, .. }) => {
+                // NOTE If the output has no mode of this size the config
+                // reports it when it's validated.
+                let refresh = handle.run(|output| {
+                                             output.modes().iter()
+                                                   .filter(|mode| {
+                                                       mode.dimensions() ==
+                                                       (size.width, size.height)
+                                                   })
+                                                   .map(|mode| mode.refresh())
+                                                   .max()
+                                         })
+                                    .unwrap_or(None)
+                                    .unwrap_or(0);
+                config.mode(handle, size, refresh)
+            }
+            None => config
+        };
+        let config = match self.scale {
+            Some(scale) => config.scale(handle, scale),
+            None => config
+        };
+        let config = match self.transform {
+            Some(transform) => config.transform(handle, transform),
+            None => config
+        };
+        match self.position {
+            Some(position) => config.position(handle, position),
+            None => config
+        }
+    }
+}
+
+const TRANSFORM_NAMES: [(wl_output_transform, &str); 8] =
+    [(WL_OUTPUT_TRANSFORM_NORMAL, "normal"),
+     (WL_OUTPUT_TRANSFORM_90, "90"),
+     (WL_OUTPUT_TRANSFORM_180, "180"),
+     (WL_OUTPUT_TRANSFORM_270, "270"),
+     (WL_OUTPUT_TRANSFORM_FLIPPED, "flipped"),
+     (WL_OUTPUT_TRANSFORM_FLIPPED_90, "flipped-90"),
+     (WL_OUTPUT_TRANSFORM_FLIPPED_180, "flipped-180"),
+     (WL_OUTPUT_TRANSFORM_FLIPPED_270, "flipped-270")];
+
+impl FromStr for Profiles {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, ParseError> {
+        let mut profiles: Vec<Profile> = vec![];
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| ParseError { line: index + 1, message };
+            let words = split_words(line).map_err(&error)?;
+            let (keyword, rest) = match words.split_first() {
+                Some((keyword, rest)) => (keyword.text.as_str(), rest),
+                None => continue
+            };
+            match keyword {
+                "profile" => {
+                    if rest.len() != 1 {
+                        return Err(error("expected a single profile name".into()))
+                    }
+                    profiles.push(Profile { name: rest[0].text.clone(), outputs: vec![] })
+                }
+                "output" => {
+                    let output = parse_output(rest).map_err(&error)?;
+                    match profiles.last_mut() {
+                        Some(profile) => profile.outputs.push(output),
+                        None => return Err(error("output outside of a profile".into()))
+                    }
+                }
+                _ => return Err(error(format!("unknown keyword {:?}", keyword)))
+            }
+        }
+        Ok(Profiles::new(profiles))
+    }
+}
+
+/// A word of a line, `literal` when any part of it was quoted.
+#[derive(Debug, PartialEq)]
+struct Word {
+    text: String,
+    literal: bool
+}
+
+/// Split a line into words, honouring quotes and stopping at comments.
+fn split_words(line: &str) -> Result<Vec<Word>, String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut literal = false;
+    let mut quoted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+                literal = true;
+            }
+            '\\' if quoted => match chars.next() {
+                Some(c) => word.push(c),
+                None => return Err("line ends with an escape".into())
+            },
+            '#' if !quoted => break,
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(Word { text: word.split_off(0), literal });
+                    in_word = false;
+                    literal = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".into())
+    }
+    if in_word {
+        words.push(Word { text: word, literal })
+    }
+    Ok(words)
+}
+
+fn parse_output(words: &[Word]) -> Result<ProfileOutput, String> {
+    let mut criteria = OutputMatch::default();
+    let mut settings = OutputSettings::default();
+    for word in words {
+        let text = &word.text;
+        let (key, value) = match text.find('=') {
+            Some(index) => (&text[..index], &text[index + 1..]),
+            None => (text.as_str(), "")
+        };
+        let criterion = || if value == "*" && !word.literal {
+            None
+        } else {
+            Some(value.to_string())
+        };
+        match key {
+            "name" => criteria.name = criterion(),
+            "make" => criteria.make = criterion(),
+            "model" => criteria.model = criterion(),
+            "serial" => criteria.serial = criterion(),
+            "enable" => settings.enabled = true,
+            "disable" => settings.enabled = false,
+            "mode" => settings.mode = Some(parse_mode(value, false)?),
+            "custom_mode" => settings.mode = Some(parse_mode(value, true)?),
+            "scale" => {
+                settings.scale = Some(value.parse()
+                                           .map_err(|_| format!("invalid scale {:?}", value))?)
+            }
+            "transform" => {
+                let transform = TRANSFORM_NAMES.iter()
+                    .find(|&&(_, name)| name == value)
+                    .map(|&(transform, _)| transform)
+                    .ok_or_else(|| format!("invalid transform {:?}", value))?;
+                settings.transform = Some(transform)
+            }
+            "position" => {
+                let mut coords = value.splitn(2, ',').map(|coord| coord.parse::<i32>());
+                match (coords.next(), coords.next()) {
+                    (Some(Ok(x)), Some(Ok(y))) => settings.position = Some(Origin::new(x, y)),
+                    _ => return Err(format!("invalid position {:?}", value))
+                }
+            }
+            _ => return Err(format!("unknown output setting {:?}", key))
+        }
+    }
+    Ok(ProfileOutput { criteria, settings })
+}
+
+/// Parse a `WIDTHxHEIGHT[@REFRESH]` mode.
+fn parse_mode(value: &str, custom: bool) -> Result<ProfileMode, String> {
+    let invalid = || format!("invalid mode {:?}", value);
+    let (size, refresh) = match value.find('@') {
+        Some(index) => {
+            (&value[..index], Some(value[index + 1..].parse().map_err(|_| invalid())?))
+        }
+        None => (value, None)
+    };
+    let mut dimensions = size.splitn(2, 'x').map(|dimension| dimension.parse::<i32>());
+    match (dimensions.next(), dimensions.next()) {
+        (Some(Ok(width)), Some(Ok(height))) => {
+            Ok(ProfileMode { size: Size::new(width, height), refresh, custom })
+        }
+        _ => Err(invalid())
+    }
+}
+
+/// Write a word, quoting it if `split_words` wouldn't read it back as is.
+fn write_word(f: &mut fmt::Formatter, word: &str) -> fmt::Result {
+    let special = |c: char| c.is_whitespace() || "\"#\\*".contains(c);
+    if !word.is_empty() && !word.contains(special) {
+        write!(f, "{}", word)
+    } else {
+        write!(f, "\"{}\"", word.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+fn write_criterion(f: &mut fmt::Formatter, key: &str, value: &Option<String>) -> fmt::Result {
+    match *value {
+        None => Ok(()),
+        Some(ref value) => {
+            write!(f, " {}=", key)?;
+            write_word(f, value)
+        }
+    }
+}
+
+impl fmt::Display for Profiles {
+    /// Write the profiles in the format `Profiles::parse` reads.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, profile) in self.profiles.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "profile ")?;
+            write_word(f, &profile.name)?;
+            writeln!(f)?;
+            for output in &profile.outputs {
+                write!(f, "output")?;
+                let criteria = &output.criteria;
+                write_criterion(f, "name", &criteria.name)?;
+                write_criterion(f, "make", &criteria.make)?;
+                write_criterion(f, "model", &criteria.model)?;
+                write_criterion(f, "serial", &criteria.serial)?;
+                let settings = &output.settings;
+                if !settings.enabled {
+                    writeln!(f, " disable")?;
+                    continue
+                }
+                if let Some(mode) = settings.mode {
+                    let key = if mode.custom { "custom_mode" } else { "mode" };
+                    write!(f, " {}={}x{}", key, mode.size.width, mode.size.height)?;
+                    if let Some(refresh) = mode.refresh {
+                        write!(f, "@{}", refresh)?;
+                    }
+                }
+                if let Some(scale) = settings.scale {
+                    write!(f, " scale={}", scale)?;
+                }
+                if let Some(transform) = settings.transform {
+                    let name = TRANSFORM_NAMES.iter()
+                        .find(|&&(value, _)| value == transform)
+                        .map(|&(_, name)| name)
+                        .unwrap_or("normal");
+                    write!(f, " transform={}", name)?;
+                }
+                if let Some(position) = settings.position {
+                    write!(f, " position={},{}", position.x, position.y)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCKED: &str = "# Comments start with a '#'
+profile docked
+output make=\"Dell Inc.\" model=\"DELL U2415\" serial=7MT0186R0CLS mode=1920x1200@59950
+output name=eDP-1 scale=2 position=1920,0 transform=normal
+
+profile laptop
+output name=eDP-1 custom_mode=2560x1440 scale=1.5
+output make=* disable
+";
+
+    #[test]
+    fn parses_the_documented_format() {
+        let profiles = Profiles::parse(DOCKED).unwrap();
+        let profiles = profiles.profiles();
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "docked");
+        let dell = &profiles[0].outputs[0];
+        assert_eq!(dell.criteria.make, Some("Dell Inc.".to_string()));
+        assert_eq!(dell.criteria.model, Some("DELL U2415".to_string()));
+        assert_eq!(dell.criteria.name, None);
+        assert_eq!(dell.settings.mode,
+                   Some(ProfileMode { size: Size::new(1920, 1200),
+                                      refresh: Some(59950),
+                                      custom: false }));
+        let laptop = &profiles[0].outputs[1];
+        assert_eq!(laptop.settings.scale, Some(2.0));
+        assert_eq!(laptop.settings.position, Some(Origin::new(1920, 0)));
+        assert_eq!(laptop.settings.transform, Some(WL_OUTPUT_TRANSFORM_NORMAL));
+        let custom = &profiles[1].outputs[0];
+        assert_eq!(custom.settings.mode,
+                   Some(ProfileMode { size: Size::new(2560, 1440), refresh: None, custom: true }));
+        let any = &profiles[1].outputs[1];
+        assert_eq!(any.criteria, OutputMatch::default());
+        assert!(!any.settings.enabled);
+    }
+
+    #[test]
+    fn round_trips_through_display() {
+        let profiles = Profiles::parse(DOCKED).unwrap();
+        let reparsed = Profiles::parse(&profiles.to_string()).unwrap();
+        assert_eq!(profiles.profiles(), reparsed.profiles());
+    }
+
+    #[test]
+    fn reports_the_line_of_errors() {
+        let err = Profiles::parse("profile a\n\noutput scale=big").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert_eq!(Profiles::parse("output name=eDP-1").unwrap_err().line, 1);
+        assert!(Profiles::parse("profile a\noutput mode=1920").is_err());
+        assert!(Profiles::parse("profile a\noutput position=1,x").is_err());
+        assert!(Profiles::parse("profile a\noutput transform=sideways").is_err());
+        assert!(Profiles::parse("profile \"a").is_err());
+        assert!(Profiles::parse("monitor a").is_err());
+    }
+
+    #[test]
+    fn splits_quoted_words() {
+        assert_eq!(split_words(r#"output make="a \"b\" c" # comment"#).unwrap(),
+                   vec![Word { text: "output".into(), literal: false },
+                        Word { text: r#"make=a "b" c"#.into(), literal: true }]);
+        assert_eq!(split_words(r#"name="""#).unwrap(),
+                   vec![Word { text: "name=".into(), literal: true }]);
+        assert!(split_words(r#"name="a\"#).is_err());
+    }
+
+    #[test]
+    fn quoted_star_is_not_a_wildcard() {
+        let profiles = Profiles::parse("profile a\noutput make=\"*\" model=*").unwrap();
+        let criteria = &profiles.profiles()[0].outputs[0].criteria;
+        assert_eq!(criteria.make, Some("*".to_string()));
+        assert_eq!(criteria.model, None);
+        let text = profiles.to_string();
+        assert!(text.contains("make=\"*\""));
+        assert_eq!(Profiles::parse(&text).unwrap().profiles(), profiles.profiles());
+    }
+
+    #[test]
+    fn criteria_match_identity() {
+        let criteria = OutputMatch { name: None,
+                                     make: Some("Dell Inc.".into()),
+                                     model: Some("DELL U2415".into()),
+                                     serial: None };
+        assert!(criteria.matches_identity("DP-1", "Dell Inc.", "DELL U2415", "123"));
+        assert!(criteria.matches_identity("HDMI-A-1", "Dell Inc.", "DELL U2415", "456"));
+        assert!(!criteria.matches_identity("DP-1", "Dell Inc.", "DELL P2415", "123"));
+        assert!(OutputMatch::default().matches_identity("eDP-1", "", "", ""));
+    }
+
+    #[test]
+    fn assign_backtracks() {
+        // The first output matches both entries, the second only the first.
+        let candidates = vec![vec![true, true], vec![true, false]];
+        let mut assignment = vec![None; 2];
+        assert!(assign(&candidates, 0, &mut assignment));
+        assert_eq!(assignment, vec![Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn assign_needs_an_entry_per_output() {
+        let candidates = vec![vec![true, false], vec![true, false]];
+        let mut assignment = vec![None; 2];
+        assert!(!assign(&candidates, 0, &mut assignment));
+    }
+}
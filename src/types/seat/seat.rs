@@ -27,15 +27,24 @@ use wlroots_sys::{wlr_axis_orientation, wlr_seat, wlr_seat_create, wlr_seat_dest
                   wlr_seat_touch_point_focus, wlr_seat_touch_send_down,
                   wlr_seat_touch_send_motion, wlr_seat_touch_send_up, wlr_seat_touch_start_grab,
                   wlr_axis_source, wlr_drag_icon};
+#[cfg(feature = "relative_pointer")]
+use wlroots_sys::wlr_relative_pointer_manager_v1_send_relative_motion;
 pub use wlroots_sys::wayland_server::protocol::wl_seat::Capability;
 use xkbcommon::xkb::Keycode;
 
 use {KeyboardModifiers,
      compositor::{self, Compositor},
-     input::{self, keyboard},
+     extensions::idle,
+     input::{self, keyboard, pointer},
      surface::{self, Surface},
      seat::{self, grab, touch_point::{TouchId, TouchPoint}, drag_icon::{self, DragIcon}},
      utils::{ToMs, HandleErr, HandleResult, c_to_rust_string, safe_as_cstring}};
+#[cfg(feature = "input_method")]
+use extensions::{input_method, text_input};
+#[cfg(feature = "keyboard_shortcuts_inhibit")]
+use extensions::keyboard_shortcuts_inhibit;
+#[cfg(feature = "relative_pointer")]
+use extensions::relative_pointer;
 pub use events::seat_events as event;
 
 struct SeatState {
@@ -510,7 +519,33 @@ impl Seat {
     ///
     /// Pass surface-local coordinates where the pointer motion occurred.
     pub fn pointer_notify_motion(&self, time: Duration, sx: f64, sy: f64) {
-        unsafe { wlr_seat_pointer_notify_motion(self.data.0, time.to_ms(), sx, sy) }
+        unsafe {
+            idle::seat_activity(self.data.0);
+            wlr_seat_pointer_notify_motion(self.data.0, time.to_ms(), sx, sy)
+        }
+    }
+
+    /// Send relative motion to the client with pointer focus, if it has bound
+    /// the relative pointer protocol.
+    ///
+    /// Pass the motion event as it came from the pointer, before any
+    /// constraints on the cursor were applied.
+    #[cfg(feature = "relative_pointer")]
+    pub fn send_relative_motion(&self,
+                                manager: &relative_pointer::Manager,
+                                event: &pointer::event::Motion) {
+        let (delta_x, delta_y) = event.delta();
+        let (unaccel_dx, unaccel_dy) = event.unaccelerated_delta();
+        let time_usec = event.time_msec() as u64 * 1000;
+        unsafe {
+            wlr_relative_pointer_manager_v1_send_relative_motion(manager.as_ptr(),
+                                                                 self.data.0,
+                                                                 time_usec,
+                                                                 delta_x,
+                                                                 delta_y,
+                                                                 unaccel_dx,
+                                                                 unaccel_dy)
+        }
     }
 
     // TODO Wrapper type around Button and State
@@ -519,7 +554,10 @@ impl Seat {
     ///
     /// Returns the serial of the button press or zero if no button press was sent.
     pub fn pointer_notify_button(&self, time: Duration, button: u32, state: u32) -> u32 {
-        unsafe { wlr_seat_pointer_notify_button(self.data.0, time.to_ms(), button, state) }
+        unsafe {
+            idle::seat_activity(self.data.0);
+            wlr_seat_pointer_notify_button(self.data.0, time.to_ms(), button, state)
+        }
     }
 
     /// Notify the seat of an axis event.
@@ -529,7 +567,15 @@ impl Seat {
                                value: f64,
                                value_discrete: i32,
                                source: wlr_axis_source) {
-        unsafe { wlr_seat_pointer_notify_axis(self.data.0, time.to_ms(), orientation, value, value_discrete, source) }
+        unsafe {
+            idle::seat_activity(self.data.0);
+            wlr_seat_pointer_notify_axis(self.data.0,
+                                         time.to_ms(),
+                                         orientation,
+                                         value,
+                                         value_discrete,
+                                         source)
+        }
     }
 
     /// Set this keyboard as the active keyboard for the seat.
@@ -569,6 +615,8 @@ impl Seat {
     /// focused surface for this keyboard.
     ///
     /// Defers to any current grab of the seat's keyboard.
+    ///
+    /// Text inputs of the seat follow the keyboard focus.
     pub fn keyboard_notify_enter(&self,
                                  surface: &mut Surface,
                                  keycodes: &mut [Keycode],
@@ -579,7 +627,9 @@ impl Seat {
                                            surface.as_ptr(),
                                            keycodes.as_mut_ptr(),
                                            keycodes_length,
-                                           modifiers)
+                                           modifiers);
+            #[cfg(feature = "input_method")]
+            text_input::seat_focus(self.data.0, surface.as_ptr());
         }
     }
 
@@ -621,26 +671,63 @@ impl Seat {
         unsafe { wlr_seat_keyboard_has_grab(self.data.0) }
     }
 
+    /// Whether the keyboard shortcuts of this seat are inhibited, i.e. a
+    /// client has an active keyboard shortcuts inhibitor for the surface with
+    /// keyboard focus.
+    ///
+    /// Always `false` if the compositor has no keyboard shortcuts inhibit
+    /// manager.
+    #[cfg(feature = "keyboard_shortcuts_inhibit")]
+    pub fn keyboard_shortcuts_inhibited(&self) -> bool {
+        unsafe { keyboard_shortcuts_inhibit::seat_inhibited(self.data.0) }
+    }
+
     /// Clear the focused surface for the keyboard and leave all entered
     /// surfaces.
+    ///
+    /// Text inputs of the seat lose focus as well.
     pub fn keyboard_clear_focus(&self) {
-        unsafe { wlr_seat_keyboard_clear_focus(self.data.0) }
+        unsafe {
+            wlr_seat_keyboard_clear_focus(self.data.0);
+            #[cfg(feature = "input_method")]
+            text_input::seat_focus(self.data.0, ptr::null_mut());
+        }
     }
 
     /// Notify the seat that the modifiers for the keyboard have changed.
     ///
-    /// Defers to any keyboard grabs.
+    /// Defers to any keyboard grabs, including the keyboard grab of an
+    /// input method bound to the seat.
     pub fn keyboard_notify_modifiers(&self, modifiers: &mut KeyboardModifiers) {
-        unsafe { wlr_seat_keyboard_notify_modifiers(self.data.0, modifiers) }
+        unsafe {
+            idle::seat_activity(self.data.0);
+            #[cfg(feature = "input_method")]
+            {
+                if input_method::grab_modifiers(self.data.0, modifiers) {
+                    return
+                }
+            }
+            wlr_seat_keyboard_notify_modifiers(self.data.0, modifiers)
+        }
     }
 
     // TODO Wrapper type for Key and State
 
     /// Notify the seat that a key has been pressed on the keyboard.
     ///
-    /// Defers to any keyboard grabs.
+    /// Defers to any keyboard grabs, including the keyboard grab of an
+    /// input method bound to the seat.
     pub fn keyboard_notify_key(&self, time: Duration, key: u32, state: u32) {
-        unsafe { wlr_seat_keyboard_notify_key(self.data.0, time.to_ms(), key, state) }
+        unsafe {
+            idle::seat_activity(self.data.0);
+            #[cfg(feature = "input_method")]
+            {
+                if input_method::grab_key(self.data.0, time.to_ms(), key, state) {
+                    return
+                }
+            }
+            wlr_seat_keyboard_notify_key(self.data.0, time.to_ms(), key, state)
+        }
     }
 
     /// How many touch ponits are currently down for the seat.
@@ -768,6 +855,7 @@ impl Seat {
                              sy: f64)
                              -> u32 {
         unsafe {
+            idle::seat_activity(self.data.0);
             wlr_seat_touch_notify_down(self.data.0,
                                        surface.as_ptr(),
                                        time.to_ms(),
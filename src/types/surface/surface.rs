@@ -17,6 +17,12 @@ use {compositor,
      output::Output,
      render::Texture,
      utils::{self, Handleable, HandleErr, HandleResult, c_to_rust_string}};
+#[cfg(feature = "viewporter")]
+use wlroots_sys::{wlr_fbox, wlr_surface_get_buffer_source_box};
+#[cfg(feature = "viewporter")]
+use area::FloatArea;
+#[cfg(feature = "idle_inhibit")]
+use extensions::idle_inhibit;
 
 pub type Handle = utils::Handle<Weak<Box<SubsurfaceManager>>,
                                 wlr_surface,
@@ -210,6 +216,21 @@ impl Surface {
         }
     }
 
+    /// Get the part of the buffer that should be sampled when rendering the
+    /// texture, in buffer pixels.
+    ///
+    /// This takes the buffer scale, the buffer transform and the source box
+    /// set with `wp_viewporter` into account. Pass it to
+    /// `Renderer::render_subtexture_with_matrix`.
+    #[cfg(feature = "viewporter")]
+    pub fn buffer_source_box(&self) -> FloatArea {
+        unsafe {
+            let mut fbox = wlr_fbox::default();
+            wlr_surface_get_buffer_source_box(self.surface, &mut fbox);
+            FloatArea::from_fbox(fbox)
+        }
+    }
+
     /// Get the lifetime bound role (if one exists) for this surface.
     pub fn role(&self) -> Option<String> {
         unsafe { c_to_rust_string((*(*self.surface).role).name) }
@@ -293,6 +314,23 @@ impl Surface {
     }
 }
 
+#[cfg(feature = "idle_inhibit")]
+impl Handle {
+    /// Whether the surface holds an idle inhibitor.
+    ///
+    /// Unlike `run` this doesn't borrow the surface, so it also works in the
+    /// callbacks of the idle inhibit manager.
+    ///
+    /// Always `false` if the surface was destroyed or the compositor has no
+    /// idle inhibit manager.
+    pub fn has_idle_inhibitor(&self) -> bool {
+        if self.handle.upgrade().is_none() {
+            return false
+        }
+        unsafe { idle_inhibit::surface_inhibits(self.ptr) }
+    }
+}
+
 impl Handleable<Weak<Box<SubsurfaceManager>>, wlr_surface> for Surface {
     #[doc(hidden)]
     unsafe fn from_ptr(surface: *mut wlr_surface) -> Self {
@@ -4,8 +4,12 @@ use std::marker::PhantomData;
 
 use libc::c_int;
 use wlroots_sys::{wl_output_transform, wl_resource, wlr_surface_state};
+#[cfg(feature = "viewporter")]
+use wlroots_sys::wlr_surface_state_field;
 
 use {render::PixmanRegion, surface::Surface};
+#[cfg(feature = "viewporter")]
+use area::{FloatArea, Size};
 
 #[derive(Debug)]
 #[repr(u32)]
@@ -29,9 +33,14 @@ pub enum InvalidState {
     Transform = 32,
     Scale = 64,
     SubsurfacePosition = 128,
-    FrameCallbackList = 256
+    FrameCallbackList = 256,
+    #[cfg(feature = "viewporter")]
+    Viewport = VIEWPORT
 }
 
+#[cfg(feature = "viewporter")]
+const VIEWPORT: u32 = wlr_surface_state_field::WLR_SURFACE_STATE_VIEWPORT as u32;
+
 /// Surface state as reported by wlroots.
 #[derive(Debug)]
 pub struct State<'surface> {
@@ -66,6 +75,8 @@ impl<'surface> State<'surface> {
                 64 => Scale,
                 128 => SubsurfacePosition,
                 256 => FrameCallbackList,
+                #[cfg(feature = "viewporter")]
+                VIEWPORT => Viewport,
                 invalid => {
                     wlr_log!(WLR_ERROR, "Invalid invalid state {}", invalid);
                     panic!("Invalid invalid state in wlr_surface_state")
@@ -96,10 +107,42 @@ impl<'surface> State<'surface> {
     }
 
     /// Get the scale applied to the surface.
+    ///
+    /// This is the integer buffer scale set by the client. Clients using
+    /// `extensions::fractional_scale` leave it at 1 and set the size of the
+    /// surface with a viewport instead.
     pub fn scale(&self) -> i32 {
         unsafe { self.state.scale }
     }
 
+    /// Get the part of the buffer the client cropped the surface to with
+    /// `wp_viewporter`, or `None` if the whole buffer is shown.
+    ///
+    /// The box is in surface-local coordinates before scaling, i.e. it still
+    /// has to be multiplied by `scale` and transformed to get buffer pixels.
+    /// `Surface::buffer_source_box` does that.
+    #[cfg(feature = "viewporter")]
+    pub fn source_box(&self) -> Option<FloatArea> {
+        if self.state.viewport.has_src {
+            Some(FloatArea::from_fbox(self.state.viewport.src))
+        } else {
+            None
+        }
+    }
+
+    /// Get the size the client asked the surface to be scaled to with
+    /// `wp_viewporter`, or `None` if it's sized by its buffer.
+    ///
+    /// When this is set it's also what `size` returns.
+    #[cfg(feature = "viewporter")]
+    pub fn destination_size(&self) -> Option<Size> {
+        if self.state.viewport.has_dst {
+            Some(Size::new(self.state.viewport.dst_width, self.state.viewport.dst_height))
+        } else {
+            None
+        }
+    }
+
     /// Get the output transform applied to the surface.
     pub fn transform(&self) -> wl_output_transform {
         unsafe { self.state.transform }
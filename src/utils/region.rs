@@ -3,19 +3,32 @@
 //! [The Pixman library](http://www.pixman.org/) is a library for pixel
 //! manipulation.
 
-use std::mem;
+use std::{mem, ops::{Deref, DerefMut}};
 
 use libc::{c_double, c_float, c_int};
-use wlroots_sys::{pixman_region32_t, pixman_region32_init, wlr_region_scale,
+use wlroots_sys::{pixman_region32_t, pixman_region32_init, pixman_region32_fini,
+                  wlr_region_scale,
                   wlr_region_transform, wlr_region_expand,
                   wlr_region_rotated_bounds, wlr_region_confine,
                   wl_output_transform};
 
 /// A thin wrapper around a 32 bit Pixman region.
+///
+/// The region is not finalized when this is dropped, as it's also used to
+/// wrap regions owned by wlroots. Use `OwnedPixmanRegion32` for a region that
+/// cleans up after itself.
+#[derive(Debug)]
 pub struct PixmanRegion32 {
     pub region: pixman_region32_t
 }
 
+/// A 32 bit Pixman region that is owned by Rust, and is finalized when it's
+/// dropped.
+#[derive(Debug)]
+pub struct OwnedPixmanRegion32 {
+    region: PixmanRegion32
+}
+
 impl PixmanRegion32 {
     /// Construct a new Pixman region.
     pub fn new() -> Self {
@@ -111,3 +124,30 @@ impl PixmanRegion32 {
         }
     }
 }
+
+impl OwnedPixmanRegion32 {
+    /// Construct a new, empty, Pixman region.
+    pub fn new() -> Self {
+        OwnedPixmanRegion32 { region: PixmanRegion32::new() }
+    }
+}
+
+impl Deref for OwnedPixmanRegion32 {
+    type Target = PixmanRegion32;
+
+    fn deref(&self) -> &PixmanRegion32 {
+        &self.region
+    }
+}
+
+impl DerefMut for OwnedPixmanRegion32 {
+    fn deref_mut(&mut self) -> &mut PixmanRegion32 {
+        &mut self.region
+    }
+}
+
+impl Drop for OwnedPixmanRegion32 {
+    fn drop(&mut self) {
+        unsafe { pixman_region32_fini(&mut self.region.region) }
+    }
+}
@@ -0,0 +1,88 @@
+//! Tests of the `testing` harness itself.
+
+extern crate wlroots;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use wlroots::{compositor::{self, Compositor}, shell::xdg_shell, surface, testing};
+
+#[test]
+fn client_sees_globals() {
+    let compositor = testing::build(|builder| builder.wl_shm(true), ());
+    testing::run(compositor, |harness| {
+        let client = harness.connect();
+        let globals = client.globals();
+        assert!(globals.iter().any(|&(ref interface, _)| interface == "wl_compositor"));
+        assert!(globals.iter().any(|&(ref interface, _)| interface == "wl_shm"));
+    });
+}
+
+// NOTE This runs alongside the other test, which would panic in
+// `compositor::Builder::new` if the harness didn't wait for it.
+#[test]
+fn harnesses_run_one_at_a_time() {
+    for _ in 0..2 {
+        let compositor = testing::build(|builder| builder, ());
+        testing::run(compositor, |harness| {
+            harness.step();
+        });
+    }
+}
+
+static MAPPED: AtomicUsize = AtomicUsize::new(0);
+
+struct Toplevel;
+
+impl xdg_shell::Handler for Toplevel {
+    fn map_request(&mut self,
+                   _: compositor::Handle,
+                   _: surface::Handle,
+                   _: xdg_shell::Handle) {
+        MAPPED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn new_surface(_: compositor::Handle,
+               _: xdg_shell::Handle)
+               -> (Option<Box<xdg_shell::Handler>>, Option<Box<surface::Handler>>) {
+    (Some(Box::new(Toplevel)), None)
+}
+
+fn build_with_xdg_shell() -> Compositor {
+    testing::build(|builder| {
+                       builder.wl_shm(true)
+                              .xdg_shell_manager(xdg_shell::manager::Builder::default()
+                                                     .surface_added(new_surface))
+                   },
+                   ())
+}
+
+#[test]
+fn toplevel_is_configured_before_mapping() {
+    let compositor = build_with_xdg_shell();
+    testing::run(compositor, |harness| {
+        let mut client = harness.connect();
+        let toplevel = client.create_toplevel();
+        harness.step();
+        let configures = toplevel.configures();
+        assert_eq!(configures.len(), 1);
+        assert_eq!((configures[0].width, configures[0].height), (0, 0));
+        assert!(!toplevel.close_requested());
+    });
+}
+
+#[test]
+fn toplevel_maps_after_attaching_a_buffer() {
+    let compositor = build_with_xdg_shell();
+    testing::run(compositor, |harness| {
+        let mut client = harness.connect();
+        let mut toplevel = client.create_toplevel();
+        harness.step();
+        let configure = toplevel.configures().pop().expect("No configure was sent");
+        toplevel.ack_configure(configure.serial);
+        let mapped = MAPPED.load(Ordering::SeqCst);
+        toplevel.attach_buffer(&mut client, 100, 100, 0xff_00_00_ff);
+        harness.step();
+        assert_eq!(MAPPED.load(Ordering::SeqCst), mapped + 1);
+    });
+}
@@ -0,0 +1,328 @@
+//! Tests of the outputs and fake input devices of the headless backend.
+
+extern crate wlroots;
+
+use std::{cell::RefCell, sync::atomic::{AtomicUsize, Ordering}};
+
+use wlroots::{area::{Origin, Size}, compositor::{self, Compositor},
+              backend::{Backend, HeadlessOutputConfig}, cursor::{self, Cursor},
+              input::{self, keyboard, pointer, config::{Action, Match, Rule, Rules}},
+              output::{self, layout::{self, Layout, Policy}}, testing, utils::HandleErr};
+use wlroots::wlroots_sys::wlr_input_device_type::WLR_INPUT_DEVICE_POINTER;
+
+static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+static LAST_KEY: AtomicUsize = AtomicUsize::new(0);
+static LAYOUT_CHANGES: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    /// The output `RemovingOutput` removes the next time its mode changes.
+    static DOOMED: RefCell<Option<output::Handle>> = RefCell::new(None);
+}
+
+struct CountingOutput;
+
+impl output::Handler for CountingOutput {
+    fn destroyed(&mut self, _: compositor::Handle, _: output::Handle) {
+        DESTROYED.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn output_added<'output>(_: compositor::Handle,
+                         builder: output::Builder<'output>)
+                         -> Option<output::BuilderResult<'output>> {
+    Some(builder.build_best_mode(CountingOutput))
+}
+
+/// Removes the `DOOMED` output when its own mode changes, so an
+/// `output::Config` can fail after it changed this output.
+struct RemovingOutput;
+
+impl output::Handler for RemovingOutput {
+    fn on_mode_change(&mut self, compositor: compositor::Handle, _: output::Handle) {
+        let doomed = match DOOMED.with(|doomed| doomed.borrow_mut().take()) {
+            Some(doomed) => doomed,
+            None => return
+        };
+        compositor.run(|compositor| match *compositor.backend() {
+                      Backend::Headless(ref headless) => assert!(headless.remove_output(doomed)),
+                      _ => panic!("The test compositor must use the headless backend")
+                  })
+                  .unwrap();
+    }
+}
+
+fn removing_output_added<'output>(_: compositor::Handle,
+                                  builder: output::Builder<'output>)
+                                  -> Option<output::BuilderResult<'output>> {
+    Some(builder.build_best_mode(RemovingOutput))
+}
+
+struct KeyboardHandler;
+
+impl keyboard::Handler for KeyboardHandler {
+    fn on_key(&mut self, _: compositor::Handle, _: keyboard::Handle, event: &keyboard::event::Key) {
+        LAST_KEY.store(event.keycode() as usize, Ordering::SeqCst);
+    }
+}
+
+fn keyboard_added(_: compositor::Handle,
+                  _: keyboard::Handle)
+                  -> Option<Box<keyboard::Handler>> {
+    Some(Box::new(KeyboardHandler))
+}
+
+struct PointerHandler;
+
+impl pointer::Handler for PointerHandler {}
+
+fn pointer_added(_: compositor::Handle,
+                 _: pointer::Handle)
+                 -> Option<Box<pointer::Handler>> {
+    Some(Box::new(PointerHandler))
+}
+
+struct CursorHandler;
+
+impl cursor::Handler for CursorHandler {}
+
+struct LayoutHandler;
+
+impl layout::Handler for LayoutHandler {
+    fn on_change<'this>(&mut self,
+                        _: compositor::Handle,
+                        _: layout::Handle,
+                        _: layout::Output<'this>) {
+        LAYOUT_CHANGES.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+fn build_with_outputs() -> Compositor {
+    testing::build(|builder| {
+                       builder.output_manager(output::manager::Builder::default()
+                                                  .output_added(output_added))
+                   },
+                   ())
+}
+
+#[test]
+fn output_gets_the_configured_refresh_rate() {
+    let compositor = build_with_outputs();
+    testing::run(compositor, |harness| {
+        // NOTE The backend emits a frame every `1_000_000 / refresh` ms, so
+        // this is a frame every 5ms instead of the default 16ms.
+        let config = HeadlessOutputConfig::new(800, 600).refresh(200_000);
+        let output = harness.headless().add_output_with(config).expect("Could not add output");
+        harness.step();
+        assert_eq!(output.run(|output| output.refresh_rate()), Ok(200_000));
+    });
+}
+
+#[test]
+fn removed_output_is_destroyed() {
+    let compositor = build_with_outputs();
+    testing::run(compositor, |harness| {
+        let output = harness.headless()
+                            .add_output_with(HeadlessOutputConfig::new(800, 600))
+                            .expect("Could not add output");
+        harness.step();
+        let destroyed = DESTROYED.load(Ordering::SeqCst);
+        assert!(harness.headless().remove_output(output.clone()));
+        harness.step();
+        assert_eq!(DESTROYED.load(Ordering::SeqCst), destroyed + 1);
+        assert!(output.run(|_| ()).is_err());
+        assert!(!harness.headless().remove_output(output));
+    });
+}
+
+#[test]
+fn compact_layout_closes_the_gap_of_a_removed_output() {
+    let compositor = build_with_outputs();
+    testing::run(compositor, |harness| {
+        let layout = Layout::create(Box::new(LayoutHandler));
+        let first = harness.headless()
+                           .add_output_with(HeadlessOutputConfig::new(800, 600))
+                           .expect("Could not add output");
+        let second = harness.headless()
+                            .add_output_with(HeadlessOutputConfig::new(1024, 768))
+                            .expect("Could not add output");
+        harness.step();
+        layout.run(|layout| {
+                  layout.set_policy(Policy::Compact);
+                  first.run(|output| layout.add_auto(output)).unwrap();
+                  second.run(|output| layout.add_auto(output)).unwrap();
+              })
+              .unwrap();
+        // Destroy the output, like unplugging the monitor would.
+        assert!(harness.headless().remove_output(first));
+        harness.step();
+        let coords = layout.run(|layout| {
+                               second.run(|output| {
+                                         layout.get_output_info(output).map(|info| info.coords())
+                                     })
+                                     .unwrap()
+                           })
+                           .unwrap();
+        assert_eq!(coords, Some((0, 0)));
+        layout.destroy();
+    });
+}
+
+#[test]
+fn fake_keyboard_needs_a_running_compositor() {
+    let compositor =
+        testing::build(|builder| {
+                           builder.input_manager(input::manager::Builder::default()
+                                                     .keyboard_added(keyboard_added))
+                       },
+                       ());
+    match *compositor.backend() {
+        Backend::Headless(ref headless) => assert!(headless.add_keyboard().is_none()),
+        _ => panic!("The test compositor must use the headless backend")
+    }
+    testing::run(compositor, |harness| {
+        let mut keyboard = harness.headless().add_keyboard().expect("Could not add keyboard");
+        // KEY_A
+        keyboard.tap_key(30).unwrap();
+        harness.step();
+        assert_eq!(LAST_KEY.load(Ordering::SeqCst), 30);
+    });
+}
+
+#[test]
+fn pointer_is_mapped_to_an_output_added_after_it() {
+    // NOTE The headless backend numbers its outputs, this is the second one.
+    let mut rules = Rules::new();
+    rules.push(Rule::new(Match::any().device_type(WLR_INPUT_DEVICE_POINTER))
+                   .action(Action::MapToOutput("HEADLESS-2".into())));
+    let compositor =
+        testing::build(|builder| {
+                           builder.input_manager(input::manager::Builder::default()
+                                                     .pointer_added(pointer_added)
+                                                     .config(rules))
+                                  .output_manager(output::manager::Builder::default()
+                                                      .output_added(output_added))
+                       },
+                       ());
+    testing::run(compositor, |harness| {
+        let layout = Layout::create(Box::new(LayoutHandler));
+        let cursor = Cursor::create(Box::new(CursorHandler));
+        layout.run(|layout| cursor.run(|cursor| cursor.attach_output_layout(layout)).unwrap())
+              .unwrap();
+        input::manager::with_config(|rules| rules.set_cursor(cursor.clone()))
+            .expect("The rules are not available");
+        let first = harness.headless()
+                           .add_output_with(HeadlessOutputConfig::new(800, 600))
+                           .expect("Could not add output");
+        harness.step();
+        let pointer = harness.headless().add_pointer().expect("Could not add pointer");
+        let late = harness.headless()
+                          .add_output_with(HeadlessOutputConfig::new(1024, 768))
+                          .expect("Could not add output");
+        harness.step();
+        layout.run(|layout| {
+                  first.run(|output| layout.add_auto(output)).unwrap();
+                  late.run(|output| layout.add_auto(output)).unwrap();
+              })
+              .unwrap();
+        let pointer = match pointer.input_handle() {
+            input::Handle::Pointer(pointer) => pointer,
+            _ => panic!("The fake pointer is not a pointer")
+        };
+        // The top left corner of the output the pointer is mapped to.
+        let coords = cursor.run(|cursor| {
+                               pointer.run(|pointer| {
+                                          cursor.absolute_to_layout_coords(pointer.input_device(),
+                                                                           0.0,
+                                                                           0.0)
+                                      })
+                                      .unwrap()
+                           })
+                           .unwrap();
+        assert_eq!(coords, (800.0, 0.0));
+        cursor.destroy();
+        layout.destroy();
+    });
+}
+
+/// Get the size, scale and position in the layout of the output.
+fn output_state(layout: &layout::Handle, output: &output::Handle)
+                -> ((i32, i32), f32, Option<(i32, i32)>) {
+    layout.run(|layout| {
+              output.run(|output| {
+                        let coords = layout.get_output_info(output).map(|info| info.coords());
+                        (output.size(), output.scale(), coords)
+                    })
+                    .unwrap()
+          })
+          .unwrap()
+}
+
+#[test]
+fn config_changes_every_output_in_one_layout_change() {
+    let compositor = build_with_outputs();
+    testing::run(compositor, |harness| {
+        let layout = Layout::create(Box::new(LayoutHandler));
+        let first = harness.headless()
+                           .add_output_with(HeadlessOutputConfig::new(800, 600))
+                           .expect("Could not add output");
+        let second = harness.headless()
+                            .add_output_with(HeadlessOutputConfig::new(1024, 768))
+                            .expect("Could not add output");
+        harness.step();
+        layout.run(|layout| {
+                  first.run(|output| layout.add_auto(output)).unwrap();
+                  second.run(|output| layout.add_auto(output)).unwrap();
+              })
+              .unwrap();
+        let changes = LAYOUT_CHANGES.load(Ordering::SeqCst);
+        output::Config::new().layout(layout.clone())
+                             .custom_mode(&first, Size::new(640, 480), 0)
+                             .scale(&first, 2.0)
+                             .position(&second, Origin::new(320, 0))
+                             .position(&first, Origin::new(0, 0))
+                             .commit()
+                             .expect("Could not commit the configuration");
+        assert_eq!(LAYOUT_CHANGES.load(Ordering::SeqCst), changes + 1);
+        assert_eq!(output_state(&layout, &first), ((640, 480), 2.0, Some((0, 0))));
+        assert_eq!(output_state(&layout, &second), ((1024, 768), 1.0, Some((320, 0))));
+        layout.destroy();
+    });
+}
+
+#[test]
+fn config_restores_the_outputs_changed_before_one_fails() {
+    let compositor =
+        testing::build(|builder| {
+                           builder.output_manager(output::manager::Builder::default()
+                                                      .output_added(removing_output_added))
+                       },
+                       ());
+    testing::run(compositor, |harness| {
+        let layout = Layout::create(Box::new(LayoutHandler));
+        let first = harness.headless()
+                           .add_output_with(HeadlessOutputConfig::new(800, 600))
+                           .expect("Could not add output");
+        let second = harness.headless()
+                            .add_output_with(HeadlessOutputConfig::new(1024, 768))
+                            .expect("Could not add output");
+        harness.step();
+        layout.run(|layout| {
+                  first.run(|output| layout.add_auto(output)).unwrap();
+                  second.run(|output| layout.add_auto(output)).unwrap();
+              })
+              .unwrap();
+        // The first output takes its changes, and its new mode removes the
+        // second output before that one is changed.
+        DOOMED.with(|doomed| *doomed.borrow_mut() = Some(second.clone()));
+        let result = output::Config::new().layout(layout.clone())
+                                          .custom_mode(&first, Size::new(640, 480), 0)
+                                          .scale(&first, 2.0)
+                                          .position(&first, Origin::new(2000, 0))
+                                          .custom_mode(&second, Size::new(1280, 720), 0)
+                                          .commit();
+        assert_eq!(result,
+                   Err(output::ConfigError::OutputUnavailable(HandleErr::AlreadyDropped)));
+        assert_eq!(output_state(&layout, &first), ((800, 600), 1.0, Some((0, 0))));
+        layout.destroy();
+    });
+}
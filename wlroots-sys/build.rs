@@ -8,6 +8,23 @@ use std::{env, io, fs};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
+/// The protocol extensions that are enabled by the cargo feature of the same name.
+const EXTENSIONS: &[&str] = &["fractional_scale",
+                              "idle_inhibit",
+                              "idle_notify",
+                              "input_method",
+                              "keyboard_shortcuts_inhibit",
+                              "output_management",
+                              "output_power_management",
+                              "pointer_constraints",
+                              "pointer_gestures",
+                              "presentation_time",
+                              "relative_pointer",
+                              "tearing_control",
+                              "viewporter",
+                              "virtual_keyboard",
+                              "virtual_pointer"];
+
 fn main() {
     meson();
     let protocol_header_path =
@@ -43,6 +60,13 @@ fn main() {
     if cfg!(feature = "unstable") {
         builder = builder.clang_arg("-DWLR_USE_UNSTABLE");
     }
+    // Protocol extensions from later wlroots versions, see src/wlroots.h.
+    for extension in EXTENSIONS {
+        let feature = format!("CARGO_FEATURE_{}", extension.to_uppercase());
+        if env::var_os(feature).is_some() {
+            builder = builder.clang_arg(format!("-DWLR_RS_HAS_{}=1", extension.to_uppercase()));
+        }
+    }
     if !cfg!(feature = "static") {
         // config.h won't exist, so make a dummy file.
         // We don't need it because of the following -D defines.
@@ -144,20 +168,29 @@ fn meson() {
     }
 }
 
-/// Gets the unstable and stable protocols in /usr/share-wayland-protocols and
-/// generates server headers for them.
+/// Gets the stable, staging and unstable protocols in
+/// /usr/share/wayland-protocols and generates server headers for them.
+///
+/// The path to the folder with the generated headers is returned.
 ///
-/// The path to the folder with the generated headers is returned. It will
-/// have two directories, `stable`, and `unstable`.
+/// Older releases of wayland-protocols have no `staging` directory, so it's
+/// skipped when it doesn't exist.
 fn generate_protocol_headers() -> io::Result<PathBuf> {
     let output_dir_str = env::var("OUT_DIR").unwrap();
     let out_path: PathBuf = format!("{}/wayland-protocols", output_dir_str).into();
     fs::create_dir(&out_path).ok();
     let protocols_prefix = pkg_config::get_variable("wayland-protocols", "prefix").unwrap();
-    let protocols = fs::read_dir(format!("{}/share/wayland-protocols/stable", protocols_prefix))?
-        .chain(fs::read_dir(format!("{}/share/wayland-protocols/unstable", protocols_prefix))?);
+    let mut protocols = Vec::new();
+    for kind in &["stable", "staging", "unstable"] {
+        let path = format!("{}/share/wayland-protocols/{}", protocols_prefix, kind);
+        if *kind == "staging" && !Path::new(&path).exists() {
+            continue
+        }
+        for entry in fs::read_dir(path)? {
+            protocols.push(entry?);
+        }
+    }
     for entry in protocols {
-        let entry = entry?;
         for entry in fs::read_dir(entry.path())? {
             let entry = entry?;
             let path = entry.path();
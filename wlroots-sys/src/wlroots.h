@@ -10,6 +10,8 @@
 
 #ifdef WLR_USE_UNSTABLE
 
+// Headers of the wlroots version in the `wlroots` submodule.
+
 #include <wlr/backend.h>
 #include <wlr/backend/drm.h>
 #include <wlr/backend/headless.h>
@@ -28,6 +30,9 @@
 #include <wlr/render/interface.h>
 #include <wlr/render/wlr_texture.h>
 
+#include <wlr/interfaces/wlr_input_device.h>
+#include <wlr/interfaces/wlr_keyboard.h>
+
 #include <wlr/types/wlr_box.h>
 // NOTE this is stable, but it relies on wlr_box.h which isn't
 #include <wlr/types/wlr_matrix.h>
@@ -35,6 +40,7 @@
 #include <wlr/types/wlr_cursor.h>
 #include <wlr/types/wlr_data_device.h>
 #include <wlr/types/wlr_gamma_control.h>
+#include <wlr/types/wlr_idle.h>
 #include <wlr/types/wlr_input_device.h>
 #include <wlr/types/wlr_keyboard.h>
 #include <wlr/types/wlr_output.h>
@@ -54,6 +60,56 @@
 #include <wlr/types/wlr_xdg_shell.h>
 #include <wlr/types/wlr_xcursor_manager.h>
 
+// Protocol extensions from later wlroots versions, enabled with the cargo
+// feature of the same name. See Cargo.toml.
+
+#if WLR_RS_HAS_FRACTIONAL_SCALE
+#include <wlr/types/wlr_fractional_scale_v1.h>
+#endif
+#if WLR_RS_HAS_IDLE_INHIBIT
+#include <wlr/types/wlr_idle_inhibit_v1.h>
+#endif
+#if WLR_RS_HAS_IDLE_NOTIFY
+#include <wlr/types/wlr_idle_notify_v1.h>
+#endif
+#if WLR_RS_HAS_INPUT_METHOD
+#include <wlr/types/wlr_input_method_v2.h>
+#include <wlr/types/wlr_text_input_v3.h>
+#endif
+#if WLR_RS_HAS_KEYBOARD_SHORTCUTS_INHIBIT
+#include <wlr/types/wlr_keyboard_shortcuts_inhibit_v1.h>
+#endif
+#if WLR_RS_HAS_OUTPUT_MANAGEMENT
+#include <wlr/types/wlr_output_management_v1.h>
+#endif
+#if WLR_RS_HAS_OUTPUT_POWER_MANAGEMENT
+#include <wlr/types/wlr_output_power_management_v1.h>
+#endif
+#if WLR_RS_HAS_POINTER_CONSTRAINTS
+#include <wlr/types/wlr_pointer_constraints_v1.h>
+#endif
+#if WLR_RS_HAS_POINTER_GESTURES
+#include <wlr/types/wlr_pointer_gestures_v1.h>
+#endif
+#if WLR_RS_HAS_PRESENTATION_TIME
+#include <wlr/types/wlr_presentation_time.h>
+#endif
+#if WLR_RS_HAS_RELATIVE_POINTER
+#include <wlr/types/wlr_relative_pointer_v1.h>
+#endif
+#if WLR_RS_HAS_TEARING_CONTROL
+#include <wlr/types/wlr_tearing_control_v1.h>
+#endif
+#if WLR_RS_HAS_VIEWPORTER
+#include <wlr/types/wlr_viewporter.h>
+#endif
+#if WLR_RS_HAS_VIRTUAL_KEYBOARD
+#include <wlr/types/wlr_virtual_keyboard_v1.h>
+#endif
+#if WLR_RS_HAS_VIRTUAL_POINTER
+#include <wlr/types/wlr_virtual_pointer_v1.h>
+#endif
+
 
 #include <xwayland.h>
 #include <xkbcommon/xkbcommon.h>